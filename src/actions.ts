@@ -0,0 +1,299 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { promptQuery } from './completion';
+import { blankComments, matchingClose } from './registrations';
+import { subjectOf } from './diagnostics';
+import { expandModuleFromPath } from './extension';
+import { systemPath } from './export';
+import { parseQueryPart, queryParts } from './symbols';
+import { splitFeatures } from './cfg';
+
+// The app builder call each missing-registration lint's quickfix chains on.
+const missingCalls: Record<string, string> = {
+    "missing-event": "add_event",
+    "missing-resource": "init_resource",
+    "missing-gizmo-group": "init_gizmo_group",
+};
+
+function snakeCase(name: string): string {
+    return name.replace(/<.*$/, "").replace(/([a-z0-9])([A-Z])/g, "$1_$2").toLowerCase();
+}
+
+// Turns a query like `*Health &Transform +Player <DamageEvent #Time @Update` into a system stub with matching
+// parameters, and the schedule it should be registered in (`Update` unless the query names one).
+export function systemSkeleton(query: string, name: string): [string, string] {
+    let components: string[] = [];
+    let filters: string[] = [];
+    let params: string[] = [];
+    let schedule = "Update";
+
+    for (const part of queryParts(query.replace(/^:/, "")).filter(part => part.length > 0)) {
+        const [meaning, _, ident] = parseQueryPart(part);
+        switch (meaning) {
+            case "query": components.push("&" + ident); break;
+            case "mut_query": components.push("&mut " + ident); break;
+            case "with": filters.push("With<" + ident + ">"); break;
+            case "without": filters.push("Without<" + ident + ">"); break;
+            case "res": params.push(snakeCase(ident) + ": Res<" + ident + ">"); break;
+            case "mut_res": params.push("mut " + snakeCase(ident) + ": ResMut<" + ident + ">"); break;
+            case "event_read": params.push("mut " + snakeCase(ident).replace(/_event$/, "") + "_events: EventReader<" + ident + ">"); break;
+            case "event_write": params.push("mut " + snakeCase(ident).replace(/_event$/, "") + "_writer: EventWriter<" + ident + ">"); break;
+            case "schedule": schedule = ident; break;
+        }
+    }
+
+    if (components.length > 0 || filters.length > 0) {
+        const data = components.length == 1 ? components[0] : "(" + (components.length > 0 ? components.join(", ") : "Entity") + ")";
+        const filter = filters.length == 0 ? "" : ", " + (filters.length == 1 ? filters[0] : "(" + filters.join(", ") + ")");
+        const mutable = components.some(component => component.startsWith("&mut "));
+        params.unshift((mutable ? "mut " : "") + "query: Query<" + data + filter + ">");
+    }
+
+    const signature = "fn " + name + "(" + (params.length > 2 ? "\n" + params.map(param => "    " + param + ",\n").join("") : params.join(", ")) + ")";
+    return [signature + " {\n    todo!()\n}\n", schedule];
+}
+
+// Where to chain a new `.add_systems(..)` in `text`: right after the last existing call, with that call's indentation.
+export function registrationInsertPoint(text: string): [number, string] | undefined {
+    const blanked = blankComments(text);
+    const pattern = /\.\s*add_systems\s*\(/g;
+    let last: [number, string] | undefined = undefined;
+    let found;
+    while ((found = pattern.exec(blanked)) !== null) {
+        const close = matchingClose(blanked, found.index + found[0].length - 1);
+        if (close < 0) continue;
+
+        const lineStart = blanked.lastIndexOf("\n", found.index) + 1;
+        const line = blanked.slice(lineStart, found.index);
+        const indent = line.match(/^\s*/)?.[0] ?? "";
+        last = [close + 1, line.trim().length == 0 ? indent : indent + "    "];
+    }
+
+    return last;
+}
+
+// Where a `.run_if(..)` goes for `system` in the registration starting on `line`: after its name and whatever is chained
+// onto it, as [offset, prefix]. Chains already broken over lines get the condition on a line of its own, indented like
+// their last call.
+export function conditionInsertPoint(text: string, line: number, system: string): [number, string] | undefined {
+    const blanked = blankComments(text);
+    let lineStart = 0;
+    for (let i = 0; i < line; i++) {
+        lineStart = blanked.indexOf("\n", lineStart) + 1;
+        if (lineStart == 0) return undefined;
+    }
+    const lineEnd = blanked.indexOf("\n", lineStart);
+    const name = new RegExp("\\b" + system + "\\b(?:\\s*::\\s*<[^>]*>)?", "g");
+    name.lastIndex = lineStart;
+    const found = name.exec(blanked);
+    if (!found || (lineEnd >= 0 && found.index > lineEnd)) return undefined;
+
+    const start = found.index + found[0].length;
+    let depth = 0;
+    let end = start;
+    for (let i = start; i < blanked.length; i++) {
+        const c = blanked[i];
+        if (c == "(" || c == "[" || c == "{") depth++;
+        else if (c == ")" || c == "]" || c == "}") {
+            if (depth == 0) break;
+            depth--;
+        } else if ((c == "," || c == ";") && depth == 0) break;
+        if (!/\s/.test(c)) end = i + 1;
+    }
+
+    const chain = blanked.slice(found.index, end).split("\n").slice(1).filter(part => part.trim().startsWith("."));
+    if (chain.length > 0) {
+        return [end, "\n" + (chain[chain.length - 1].match(/^\s*/)?.[0] ?? "")];
+    }
+    return [end, ""];
+}
+
+class GenerateSystemActionProvider implements vscode.CodeActionProvider {
+    provideCodeActions(document: vscode.TextDocument, range: vscode.Range): vscode.CodeAction[] {
+        let action = new vscode.CodeAction("bevyrly: Generate system from query...", vscode.CodeActionKind.RefactorRewrite);
+        action.command = { title: action.title, command: 'bevyrly.generateSystem', arguments: [document.uri, range.start] };
+        return [action];
+    }
+}
+
+class DiagnosticQuickFixProvider implements vscode.CodeActionProvider {
+    provideCodeActions(document: vscode.TextDocument, _range: vscode.Range, context: vscode.CodeActionContext): vscode.CodeAction[] {
+        let actions: vscode.CodeAction[] = [];
+        for (const diagnostic of context.diagnostics.filter(diagnostic => diagnostic.source == "bevyrly")) {
+            const subject = subjectOf(diagnostic);
+            if (diagnostic.code == "orphan-system" && subject) {
+                let action = new vscode.CodeAction("bevyrly: Register " + subject + " with add_systems...", vscode.CodeActionKind.QuickFix);
+                action.command = { title: action.title, command: 'bevyrly.registerSystem', arguments: [subject] };
+                action.diagnostics = [diagnostic];
+                action.isPreferred = true;
+                actions.push(action);
+            } else if (typeof diagnostic.code == "string" && diagnostic.code in missingCalls && subject) {
+                const call = missingCalls[diagnostic.code] + "::<" + subject + ">()";
+                let action = new vscode.CodeAction("bevyrly: Add ." + call + " to the nearest app builder", vscode.CodeActionKind.QuickFix);
+                action.command = { title: action.title, command: 'bevyrly.addToBuilder', arguments: [call, document.uri.toString()] };
+                action.diagnostics = [diagnostic];
+                action.isPreferred = true;
+                actions.push(action);
+            } else if (diagnostic.code == "ungated-event-system" && subject) {
+                const event = Array.from(diagnostic.message.matchAll(/`([^`]+)`/g))[1]?.[1];
+                const insertPoint = event ? conditionInsertPoint(document.getText(), diagnostic.range.start.line, subject) : undefined;
+                if (!insertPoint) continue;
+
+                const [offset, prefix] = insertPoint;
+                let action = new vscode.CodeAction("bevyrly: Gate " + subject + " with .run_if(on_event::<" + event + ">())", vscode.CodeActionKind.QuickFix);
+                action.edit = new vscode.WorkspaceEdit();
+                action.edit.insert(document.uri, document.positionAt(offset), prefix + ".run_if(on_event::<" + event + ">())");
+                action.diagnostics = [diagnostic];
+                action.isPreferred = true;
+                actions.push(action);
+            }
+        }
+
+        for (const diagnostic of context.diagnostics.filter(diagnostic => diagnostic.source == "bevyrly")) {
+            let action = new vscode.CodeAction("bevyrly: Explain " + diagnostic.code, vscode.CodeActionKind.QuickFix);
+            action.command = { title: action.title, command: 'bevyrly.explainFinding', arguments: [document.uri.toString(), diagnostic.range.start.line, diagnostic.code] };
+            action.diagnostics = [diagnostic];
+            actions.push(action);
+        }
+
+        return actions;
+    }
+}
+
+function commonPrefix(a: string, b: string): number {
+    let i = 0;
+    while (i < a.length && i < b.length && a[i] == b[i]) i++;
+    return i;
+}
+
+// The indexed file with an `add_systems` builder closest to `filepath` in the directory tree, preferring itself.
+export function nearestBuilder(bevyrlyIndex: BevyrlyIndex, filepath: string): [string, number, string] | undefined {
+    let best: [string, number, string] | undefined = undefined;
+    let bestScore = -1;
+    for (const [file, text] of bevyrlyIndex.sources) {
+        const insertPoint = registrationInsertPoint(text);
+        if (!insertPoint) continue;
+
+        const score = file == filepath ? Number.MAX_SAFE_INTEGER : commonPrefix(file, filepath);
+        if (score > bestScore) {
+            best = [file, ...insertPoint];
+            bestScore = score;
+        }
+    }
+
+    return best;
+}
+
+// Chains `.call` onto the builder closest to `filepath`, which for a type is usually the plugin of its own module.
+async function addToBuilder(bevyrlyIndex: BevyrlyIndex, call: string, filepath: string) {
+    const builder = nearestBuilder(bevyrlyIndex, filepath);
+    if (!builder) {
+        vscode.window.showWarningMessage("bevyrly found no app builder to add ." + call + " to");
+        return;
+    }
+
+    const [target, offset, indent] = builder;
+    const document = await vscode.workspace.openTextDocument(vscode.Uri.parse(target));
+    let edit = new vscode.WorkspaceEdit();
+    edit.insert(document.uri, document.positionAt(offset), "\n" + indent + "." + call);
+    await vscode.workspace.applyEdit(edit);
+}
+
+async function registerOrphanSystem(bevyrlyIndex: BevyrlyIndex, system: string) {
+    const filepath = bevyrlyIndex.locs.get(system)?.src.filepath ?? "";
+    const schedules = Array.from(bevyrlyIndex.schedules().entries())
+        .sort(([_a, a], [_b, b]) => b.size - a.size)
+        .map(([schedule, _]) => schedule);
+    const schedule = await vscode.window.showQuickPick(schedules.length > 0 ? schedules : ["Update"], { title: 'Schedule for ' + system });
+    if (!schedule) {
+        return;
+    }
+
+    // Run conditions of sibling systems in the same schedule, those from the same file first.
+    const siblings = Array.from(bevyrlyIndex.registrations.values()).flat()
+        .filter(reg => reg.schedule == schedule)
+        .sort((a, b) => Number(b.filepath == filepath) - Number(a.filepath == filepath));
+    const conditions = Array.from(new Set(siblings.flatMap(reg => reg.conditions)));
+    const noCondition = "(no run condition)";
+    const condition = await vscode.window.showQuickPick([noCondition, ...conditions], { title: 'Run condition' });
+    if (!condition) {
+        return;
+    }
+
+    const builder = nearestBuilder(bevyrlyIndex, filepath);
+    if (!builder) {
+        vscode.window.showWarningMessage("bevyrly found no add_systems call to register " + system + " next to");
+        return;
+    }
+
+    const [target, offset, indent] = builder;
+    const path = target == filepath ? system : systemPath(bevyrlyIndex, system, expandModuleFromPath(bevyrlyIndex, target)[0]);
+    const registration = ".add_systems(" + schedule + ", " + path + (condition == noCondition ? "" : ".run_if(" + condition + ")") + ")";
+
+    const document = await vscode.workspace.openTextDocument(vscode.Uri.parse(target));
+    let edit = new vscode.WorkspaceEdit();
+    edit.insert(document.uri, document.positionAt(offset), "\n" + indent + registration);
+    await vscode.workspace.applyEdit(edit);
+
+    const position = document.positionAt(offset + 1 + indent.length);
+    await vscode.window.showTextDocument(document, { selection: new vscode.Range(position, position), preview: false });
+}
+
+export function registerCodeActions(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    context.subscriptions.push(vscode.languages.registerCodeActionsProvider({ language: 'rust' }, new GenerateSystemActionProvider(), {
+        providedCodeActionKinds: [vscode.CodeActionKind.RefactorRewrite]
+    }));
+
+    context.subscriptions.push(vscode.languages.registerCodeActionsProvider({ language: 'rust' }, new DiagnosticQuickFixProvider(), {
+        providedCodeActionKinds: [vscode.CodeActionKind.QuickFix]
+    }));
+
+    let disposableRegister = vscode.commands.registerCommand('bevyrly.registerSystem', async (system: string) => {
+        await registerOrphanSystem(bevyrlyIndex, system);
+    });
+    context.subscriptions.push(disposableRegister);
+
+    let disposableAdd = vscode.commands.registerCommand('bevyrly.addToBuilder', async (call: string, filepath: string) => {
+        await addToBuilder(bevyrlyIndex, call, filepath);
+    });
+    context.subscriptions.push(disposableAdd);
+
+    let disposableGenerate = vscode.commands.registerCommand('bevyrly.generateSystem', async (uri?: vscode.Uri, position?: vscode.Position) => {
+        const editor = vscode.window.activeTextEditor;
+        uri = uri ?? editor?.document.uri;
+        position = position ?? editor?.selection.active;
+        if (!uri || !position) {
+            return;
+        }
+
+        const query = await promptQuery(bevyrlyIndex, bevyrlyIndex.lastQuery, '*Health <DamageEvent @Update');
+
+        if (!query) {
+            return;
+        }
+
+        const name = await vscode.window.showInputBox({ value: 'new_system', prompt: 'System name' });
+        if (!name) {
+            return;
+        }
+
+        const document = await vscode.workspace.openTextDocument(uri);
+        const [skeleton, schedule] = systemSkeleton(splitFeatures(query)[0], name);
+        let edit = new vscode.WorkspaceEdit();
+        edit.insert(uri, new vscode.Position(position.line, 0), skeleton + "\n");
+
+        const insertPoint = registrationInsertPoint(document.getText());
+        const registration = ".add_systems(" + schedule + ", " + name + ")";
+        if (insertPoint) {
+            const [offset, indent] = insertPoint;
+            edit.insert(uri, document.positionAt(offset), "\n" + indent + registration);
+        } else {
+            await vscode.env.clipboard.writeText("app" + registration + ";");
+            vscode.window.showInformationMessage("No add_systems call in this file; copied the registration to the clipboard");
+        }
+
+        await vscode.workspace.applyEdit(edit);
+    });
+
+    context.subscriptions.push(disposableGenerate);
+}
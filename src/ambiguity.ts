@@ -0,0 +1,160 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, QueryStorage, whenIndexed } from '.';
+import { SystemRegistration } from './registrations';
+import { escapeHtml } from './extension';
+
+export interface Ambiguity {
+    schedule: string;
+    first: string;
+    second: string;
+    conflicts: string[];
+}
+
+// Reachability over `.before`/`.after`/`.chain()` edges, so that transitively ordered systems aren't reported.
+export function orderingClosure(registrations: SystemRegistration[]): Map<string, Set<string>> {
+    let edges: Map<string, Set<string>> = new Map();
+    const addEdge = (from: string, to: string) => {
+        if (!edges.has(from)) {
+            edges.set(from, new Set());
+        }
+        edges.get(from)?.add(to);
+    };
+
+    for (const reg of registrations) {
+        reg.before.forEach(next => addEdge(reg.system, next));
+        reg.after.forEach(prev => addEdge(prev, reg.system));
+    }
+
+    let closure: Map<string, Set<string>> = new Map();
+    for (const start of edges.keys()) {
+        let seen: Set<string> = new Set();
+        let stack = Array.from(edges.get(start) ?? []);
+        while (stack.length > 0) {
+            const next = stack.pop() as string;
+            if (seen.has(next)) continue;
+            seen.add(next);
+            stack.push(...(edges.get(next) ?? []));
+        }
+        closure.set(start, seen);
+    }
+
+    return closure;
+}
+
+function has(accesses: Map<QueryStorage, string[]>, storage: QueryStorage, key: string): boolean {
+    return accesses.get(storage)?.includes(key) ?? false;
+}
+
+export function conflictsBetween(bevyrlyIndex: BevyrlyIndex, first: string, second: string): string[] {
+    const a = bevyrlyIndex.accessesOf(first);
+    const b = bevyrlyIndex.accessesOf(second);
+
+    let conflicts: Set<string> = new Set();
+    const disjoint = (a.get("with") ?? []).some(key => has(b, "without", key)) ||
+        (a.get("without") ?? []).some(key => has(b, "with", key));
+
+    for (const [x, y] of [[a, b], [b, a]]) {
+        if (!disjoint) {
+            for (const key of x.get("mut_query") ?? []) {
+                if (has(y, "query", key) || has(y, "mut_query", key)) conflicts.add(key);
+            }
+        }
+        for (const key of x.get("mut_res") ?? []) {
+            if (has(y, "res", key) || has(y, "mut_res", key)) conflicts.add(key);
+        }
+        for (const key of x.get("event_write") ?? []) {
+            if (has(y, "event_read", key) || has(y, "event_write", key)) conflicts.add("Events<" + key + ">");
+        }
+    }
+
+    return Array.from(conflicts).sort();
+}
+
+// Pairs of systems in the same schedule with conflicting access and no ordering between them. Filters are only
+// known per system rather than per query, so `With`/`Without` disjointness is an approximation.
+export function findAmbiguities(bevyrlyIndex: BevyrlyIndex): Ambiguity[] {
+    return bevyrlyIndex.derived("ambiguities", () => computeAmbiguities(bevyrlyIndex));
+}
+
+function computeAmbiguities(bevyrlyIndex: BevyrlyIndex): Ambiguity[] {
+    let schedules: Map<string, SystemRegistration[]> = new Map();
+    for (const reg of Array.from(bevyrlyIndex.registrations.values()).flat()) {
+        if (!schedules.has(reg.schedule)) {
+            schedules.set(reg.schedule, []);
+        }
+        schedules.get(reg.schedule)?.push(reg);
+    }
+
+    let result: Ambiguity[] = [];
+    for (const [schedule, registrations] of schedules) {
+        const closure = orderingClosure(registrations);
+        const systems = Array.from(new Set(registrations.map(reg => reg.system))).sort();
+        for (let i = 0; i < systems.length; i++) {
+            for (let j = i + 1; j < systems.length; j++) {
+                const [first, second] = [systems[i], systems[j]];
+                if (closure.get(first)?.has(second) || closure.get(second)?.has(first)) continue;
+
+                const conflicts = conflictsBetween(bevyrlyIndex, first, second);
+                if (conflicts.length > 0) {
+                    result.push({ schedule, first, second, conflicts });
+                }
+            }
+        }
+    }
+
+    return result;
+}
+
+export function ambiguityHeatmap(ambiguities: Ambiguity[]): string {
+    let html = ["<h1>Bevyrly ambiguities</h1>"];
+    if (ambiguities.length == 0) {
+        html.push("<p>No conflicting unordered systems found.</p>");
+    }
+
+    const schedules = Array.from(new Set(ambiguities.map(amb => amb.schedule))).sort();
+    for (const schedule of schedules) {
+        const local = ambiguities.filter(amb => amb.schedule == schedule);
+        let weight: Map<string, number> = new Map();
+        for (const amb of local) {
+            weight.set(amb.first, (weight.get(amb.first) ?? 0) + amb.conflicts.length);
+            weight.set(amb.second, (weight.get(amb.second) ?? 0) + amb.conflicts.length);
+        }
+
+        const systems = Array.from(weight.keys()).sort((x, y) => (weight.get(y) ?? 0) - (weight.get(x) ?? 0));
+        const max = Math.max(...local.map(amb => amb.conflicts.length));
+
+        html.push("<h2>" + escapeHtml(schedule) + " <small>(" + local.length + " pairs)</small></h2>");
+        html.push("<table><tr><th></th>" + systems.map(system => "<th class='col'>" + escapeHtml(system) + "</th>").join("") + "</tr>");
+        for (const row of systems) {
+            html.push("<tr><th>" + escapeHtml(row) + "</th>");
+            for (const col of systems) {
+                const amb = local.find(amb => (amb.first == row && amb.second == col) || (amb.first == col && amb.second == row));
+                if (amb) {
+                    const alpha = (0.25 + 0.75 * amb.conflicts.length / max).toFixed(2);
+                    html.push("<td style='background: rgba(220, 50, 50, " + alpha + ")' title='" +
+                        escapeHtml(amb.conflicts.join(", ")) + "'>" + amb.conflicts.length + "</td>");
+                } else {
+                    html.push("<td></td>");
+                }
+            }
+            html.push("</tr>");
+        }
+        html.push("</table>");
+    }
+
+    return "<!DOCTYPE html><html><head><style>" +
+        "table { border-collapse: collapse; } td, th { border: 1px solid #555; padding: 2px 6px; text-align: center; } " +
+        "th.col { writing-mode: vertical-rl; } th { font-weight: normal; font-family: monospace; }" +
+        "</style></head><body>" + html.join("\n") + "</body></html>";
+}
+
+export function registerAmbiguities(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableAmbiguities = vscode.commands.registerCommand('bevyrly.showAmbiguities', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const panel = vscode.window.createWebviewPanel('bevyrlyAmbiguities', 'Bevyrly Ambiguities', vscode.ViewColumn.One, {});
+        panel.webview.html = ambiguityHeatmap(findAmbiguities(bevyrlyIndex));
+    });
+
+    context.subscriptions.push(disposableAmbiguities);
+}
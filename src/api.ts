@@ -0,0 +1,180 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, startBevyrlyIndexing, whenIndexed } from '.';
+import { ExportedSystem, exportSystems } from './export';
+import { Finding, findAll } from './diagnostics';
+import { lintLevel } from './config';
+import { QueryHistoryEntry, queryHistoryEntries } from './history';
+import { QuerySession, sessions } from './sessions';
+import { SystemMatch, queryMatches, resultPage, systemMatch } from './results';
+import { QueryMeaning, symbolOf } from './symbols';
+import { CrateStats, indexStats } from './stats';
+import { resolveChanged } from './changed';
+import { SystemExplanation, explainQuery } from './explain';
+import { featureScopedGet } from './cfg';
+import { RunsBefore, runsBefore, scheduleOrders } from './order';
+
+// `system`, `location`, `matched` and `schedules` are the original fields; the rest were added without breaking them.
+export type QueryResult = SystemMatch;
+
+export interface QueryResultPage {
+    total: number;
+    page: number;
+    pages: number;
+    results: QueryResult[];
+}
+
+// Writes queries without string concatenation: `query().writesEvent("DamageEvent").inSchedule("Update")` builds the
+// same text a user would type (`>DamageEvent @Update`, with the symbols of the active mapping), so both evaluate alike.
+export class QueryBuilder {
+    private _parts: string[] = [];
+    private _declarations: boolean = false;
+    private _flags: string[] = [];
+
+    private _add(meaning: QueryMeaning | undefined, name: string): QueryBuilder {
+        if (name.length == 0 || /\s/.test(name)) {
+            throw new Error("bevyrly query names cannot be empty or contain whitespace: '" + name + "'");
+        }
+        const symbol = meaning ? symbolOf(meaning) : "";
+        if (meaning && symbol.length == 0) {
+            throw new Error("bevyrly.querySymbols maps no symbol to " + meaning);
+        }
+        this._parts.push(symbol + name);
+        return this;
+    }
+
+    reads(component: string) { return this._add("query", component); }
+    writes(component: string) { return this._add("mut_query", component); }
+    with(component: string) { return this._add("with", component); }
+    without(component: string) { return this._add("without", component); }
+    readsResource(resource: string) { return this._add("res", resource); }
+    writesResource(resource: string) { return this._add("mut_res", resource); }
+    readsEvent(event: string) { return this._add("event_read", event); }
+    writesEvent(event: string) { return this._add("event_write", event); }
+    inSchedule(schedule: string) { return this._add("schedule", schedule); }
+    ownedBy(author: string) { return this._add("owner", author); }
+    readsAsset(field: string) { return this._add("asset", field); }
+    spawnsAsset(field: string) { return this._add("asset_spawn", field); }
+    usesAssetType(type: string) { return this._add("asset_type", type); }
+    inCategory(category: string) { return this._add("category", category); }
+    taggedWith(tag: string) { return this._add("tag", tag); }
+    conditionedOn(resource: string) { return this._add("res_condition", resource); }
+    inState(state: string) { return this._add("state", state); }
+    changesState(state: string) { return this._add("next_state", state); }
+    changedSince(revision: string) { return this._add("changed", "since:" + revision); }
+    changedInWorkingTree() { return this._add("changed", "working-tree"); }
+    withParam(name: string) { return this._add("param", name); }
+    firstParam(type: string) { return this._add("first", type); }
+    lastParam(type: string) { return this._add("last", type); }
+    drawsGizmos(group: string) { return this._add("gizmo_group", group); }
+    mentions(text: string) { return this._add(undefined, text); }
+
+    // Asks for full declarations, like a `:` in front of a typed query.
+    declarations(): QueryBuilder {
+        this._declarations = true;
+        return this;
+    }
+
+    // Only matches systems compiled in with these features, like `--features a,b` in a typed query.
+    withFeatures(...features: string[]): QueryBuilder {
+        this._flags.push("--features " + features.join(","));
+        return this;
+    }
+
+    withoutDefaultFeatures(): QueryBuilder {
+        this._flags.push("--no-default-features");
+        return this;
+    }
+
+    // Without `debug_assertions`, like `--release`.
+    release(): QueryBuilder {
+        this._flags.push("--release");
+        return this;
+    }
+
+    toString(): string {
+        return (this._declarations ? ":" : "") + [...this._parts, ...this._flags].join(" ");
+    }
+}
+
+// Returned from `activate`, so other extensions can drive bevyrly with
+// `vscode.extensions.getExtension('DEVLIKE.bevyrly')?.activate()`. Bump `version` on breaking changes.
+export interface BevyrlyApi {
+    version: 1;
+    reindex(): Promise<void>;
+    query(query: string | QueryBuilder): Promise<QueryResult[]>;
+    // `page` is 1-based; `size` defaults to `bevyrly.pageSize`, and 0 returns every result on one page.
+    queryPage(query: string | QueryBuilder, page: number, size?: number): Promise<QueryResultPage>;
+    queryBuilder(): QueryBuilder;
+    history(): QueryHistoryEntry[];
+    sessions(): QuerySession[];
+    // Which parts of the query matched `system` and why the others didn't; without one, for every system matching a part.
+    explain(query: string | QueryBuilder, system?: string): Promise<SystemExplanation[]>;
+    lint(): Promise<Finding[]>;
+    // A plausible execution order per schedule (or for just `schedule`) that respects every ordering constraint.
+    scheduleOrder(schedule?: string): Promise<{ schedule: string, order: string[], cycle: string[] }[]>;
+    // Whether `first` runs before `second` always, never, or either way ("ambiguous"), and why.
+    runsBefore(first: string, second: string): Promise<RunsBefore>;
+    stats(): Promise<CrateStats[]>;
+    export(): Promise<ExportedSystem[]>;
+}
+
+export function createApi(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex): BevyrlyApi {
+    const ready = async () => {
+        await whenIndexed(context, bevyrlyIndex);
+    };
+
+    return {
+        version: 1,
+        reindex: () => startBevyrlyIndexing(context, bevyrlyIndex),
+        query: async (built: string | QueryBuilder) => {
+            await ready();
+            const query = built.toString();
+            await resolveChanged(bevyrlyIndex, query);
+            return queryMatches(bevyrlyIndex, query);
+        },
+        queryPage: async (built: string | QueryBuilder, page: number, size?: number) => {
+            await ready();
+            const query = built.toString();
+            await resolveChanged(bevyrlyIndex, query);
+            const [found, _, parts] = featureScopedGet(bevyrlyIndex, query);
+            const slice = resultPage(found.slice().sort(), page, size);
+            return {
+                total: slice.total,
+                page: slice.page,
+                pages: slice.pages,
+                results: slice.systems.map(system => systemMatch(bevyrlyIndex, parts, system)),
+            };
+        },
+        queryBuilder: () => new QueryBuilder(),
+        history: () => queryHistoryEntries(),
+        sessions: () => sessions(),
+        explain: async (built: string | QueryBuilder, system?: string) => {
+            await ready();
+            const query = built.toString();
+            await resolveChanged(bevyrlyIndex, query);
+            return explainQuery(bevyrlyIndex, query, system);
+        },
+        lint: async () => {
+            await ready();
+            return findAll(bevyrlyIndex).filter(finding => lintLevel(bevyrlyIndex, finding) != "off");
+        },
+        scheduleOrder: async (schedule?: string) => {
+            await ready();
+            return Array.from(scheduleOrders(bevyrlyIndex).values())
+                .filter(order => schedule === undefined || order.schedule == schedule)
+                .map(order => ({ schedule: order.schedule, order: order.order, cycle: order.cycle }));
+        },
+        runsBefore: async (first: string, second: string) => {
+            await ready();
+            return runsBefore(bevyrlyIndex, first, second);
+        },
+        stats: async () => {
+            await ready();
+            return indexStats(bevyrlyIndex);
+        },
+        export: async () => {
+            await ready();
+            return exportSystems(bevyrlyIndex);
+        },
+    };
+}
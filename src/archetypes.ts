@@ -0,0 +1,117 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { blankComments, matchingClose, splitTopLevel } from './registrations';
+import { escapeHtml, expandLinkFromName } from './extension';
+import { spawnSiteAssets } from './assets';
+
+export interface SpawnSite {
+    components: string[];
+    offset: number;
+    // Just past the closing parenthesis of the last call, chained `.insert(..)`s included.
+    end: number;
+}
+
+export interface Archetype {
+    components: string[];
+    // [system, line, asset collection fields its handles come from]
+    sites: [string, number, string[]][];
+}
+
+// `SpriteSheetBundle { .. }`, `Velocity(Vec2::ZERO)`, `Ship::default()`, `Side::Enemy` and
+// `bevy::prelude::Transform::IDENTITY` all name their component by the first capitalized path segment, since
+// whatever follows it is an associated item or an enum variant.
+export function componentNameOf(expr: string): string | undefined {
+    const path = expr.trim().match(/^[\w:]+/)?.[0] ?? "";
+    return path.split("::").find(segment => /^[A-Z]/.test(segment));
+}
+
+export function componentsOf(args: string): string[] {
+    let inner = args.trim();
+    if (inner.startsWith("(") && matchingClose(inner, 0) == inner.length - 1) {
+        inner = inner.slice(1, -1);
+    }
+
+    return splitTopLevel(inner)
+        .map(([item, _]) => componentNameOf(item))
+        .filter((name): name is string => name !== undefined);
+}
+
+// Finds `.spawn(..)` calls along with any `.insert(..)` chained directly onto them.
+export function parseSpawns(source: string): SpawnSite[] {
+    const text = blankComments(source);
+    let sites: SpawnSite[] = [];
+    const pattern = /\.\s*spawn(?:_bundle)?\s*\(/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        const open = found.index + found[0].length - 1;
+        let close = matchingClose(text, open);
+        if (close < 0) continue;
+
+        let components = componentsOf(text.slice(open + 1, close));
+        let insert;
+        while ((insert = text.slice(close + 1).match(/^\s*\.\s*insert\s*\(/)) !== null) {
+            const next = close + insert[0].length;
+            const end = matchingClose(text, next);
+            if (end < 0) break;
+            components.push(...componentsOf(text.slice(next + 1, end)));
+            close = end;
+        }
+
+        if (components.length > 0) {
+            sites.push({ components: Array.from(new Set(components)).sort(), offset: found.index, end: close + 1 });
+        }
+    }
+
+    return sites;
+}
+
+export function inferArchetypes(bevyrlyIndex: BevyrlyIndex): Archetype[] {
+    let archetypes: Map<string, Archetype> = new Map();
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        const assets = spawnSiteAssets(bevyrlyIndex, system);
+        for (const site of parseSpawns(loc.getText())) {
+            const key = site.components.join(", ");
+            if (!archetypes.has(key)) {
+                archetypes.set(key, { components: site.components, sites: [] });
+            }
+            archetypes.get(key)?.sites.push([system, loc.src.l(loc[0] + site.offset) + 1, assets.get(site.offset) ?? []]);
+        }
+    }
+
+    return Array.from(archetypes.values()).sort((a, b) => b.sites.length - a.sites.length);
+}
+
+// With `conventions.cleanup-markers` configured, archetypes carrying none of them (or `StateScoped`) are flagged.
+export function archetypesView(bevyrlyIndex: BevyrlyIndex, archetypes: Archetype[]): string {
+    const markers = new Set(Array.from(bevyrlyIndex.configs.values()).flatMap(config => config.cleanupMarkers));
+    let rows = archetypes.map(archetype => {
+        const sites = archetype.sites.map(([system, line, assets]) => {
+            const file = expandLinkFromName(bevyrlyIndex, system)?.[1].split(":")[0] ?? "";
+            return "<code>" + escapeHtml(system) + "</code> <small>" + escapeHtml(file) + ":" + line + "</small>" +
+                (assets.length > 0 ? " <small>from " + assets.map(asset => "<code>" + escapeHtml(asset) + "</code>").join(", ") + "</small>" : "");
+        });
+        const cleanup = markers.size == 0 ? "" :
+            "<td>" + (archetype.components.some(component => markers.has(component) || component == "StateScoped") ? "yes" : "<b>no</b>") + "</td>";
+        return "<tr><td>" + archetype.sites.length + "</td><td>" +
+            archetype.components.map(component => "<code>" + escapeHtml(component) + "</code>").join(", ") +
+            "</td>" + cleanup + "<td>" + sites.join("<br>") + "</td></tr>";
+    });
+
+    return "<!DOCTYPE html><html><head><style>" +
+        "table { border-collapse: collapse; } td, th { border: 1px solid #555; padding: 4px 8px; vertical-align: top; }" +
+        "</style></head><body><h1>Bevyrly spawned archetypes</h1>" +
+        "<p>" + archetypes.length + " distinct component sets spawned.</p>" +
+        "<table><tr><th>Spawns</th><th>Components</th>" + (markers.size == 0 ? "" : "<th>Cleanup marker</th>") + "<th>Spawn sites</th></tr>" + rows.join("\n") + "</table>" +
+        "</body></html>";
+}
+
+export function registerArchetypes(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableArchetypes = vscode.commands.registerCommand('bevyrly.showArchetypes', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const panel = vscode.window.createWebviewPanel('bevyrlyArchetypes', 'Bevyrly Archetypes', vscode.ViewColumn.One, {});
+        panel.webview.html = archetypesView(bevyrlyIndex, inferArchetypes(bevyrlyIndex));
+    });
+
+    context.subscriptions.push(disposableArchetypes);
+}
@@ -0,0 +1,269 @@
+import { BevyrlyIndex } from '.';
+import { blankComments, lineAt, matchingClose, parseDerives, splitTopLevel } from './registrations';
+import { systemParameters } from './complexity';
+import { SpawnSite, parseSpawns } from './archetypes';
+
+// One field of a `#[derive(AssetCollection)]` resource (bevy_asset_loader), e.g. `ImageAssets.smoke`.
+export interface AssetField {
+    collection: string;
+    field: string;
+    // `Handle<TextureAtlas>`, `Vec<Handle<Image>>`, ...
+    handle: string;
+    // From `#[asset(key = "smoke")]` (dynamic assets) and `#[asset(path = "smoke.png")]`.
+    key?: string;
+    path?: string;
+    filepath: string;
+    line: number;
+}
+
+// Finds `#[derive(.., AssetCollection, ..)]` structs and their named fields, with the `#[asset(..)]` attribute of each.
+export function parseAssetCollections(source: string, filepath: string): AssetField[] {
+    const text = blankComments(source);
+    let fields: AssetField[] = [];
+    const pattern = /#\[derive\(([^)]*)\)\]\s*(?:#\[[^\]]*\]\s*)*(?:pub(?:\([^)]*\))?\s+)?struct\s+(\w+)[^{;(]*\{/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        if (!found[1].split(",").some(derive => derive.trim().split("::").pop() == "AssetCollection")) continue;
+        const open = found.index + found[0].length - 1;
+        const close = matchingClose(text, open);
+        if (close < 0) continue;
+
+        for (const [item, at] of splitTopLevel(text.slice(open + 1, close))) {
+            // Attributes stay attached to the field they precede; strings are read from the original source.
+            const attributes = item.match(/^\s*(?:#\[[\s\S]*?\]\s*)*/)?.[0] ?? "";
+            const declaration = item.slice(attributes.length).match(/^(?:pub(?:\([^)]*\))?\s+)?(\w+)\s*:\s*([\s\S]+)$/);
+            if (!declaration) continue;
+
+            const start = open + 1 + at;
+            const original = source.slice(start, start + attributes.length);
+            fields.push({
+                collection: found[2],
+                field: declaration[1],
+                handle: declaration[2].trim().replace(/\s+/g, " "),
+                key: original.match(/\bkey\s*=\s*"([^"]*)"/)?.[1],
+                path: original.match(/\bpath\s*=\s*"([^"]*)"/)?.[1],
+                filepath,
+                line: lineAt(text, start + attributes.length),
+            });
+        }
+    }
+
+    return fields;
+}
+
+export function assetFields(bevyrlyIndex: BevyrlyIndex): AssetField[] {
+    return bevyrlyIndex.derived("asset-fields", () =>
+        Array.from(bevyrlyIndex.sources.entries()).flatMap(([filepath, text]) => parseAssetCollections(text, filepath)));
+}
+
+function collectionFields(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    let collections: Map<string, Set<string>> = new Map();
+    for (const field of assetFields(bevyrlyIndex)) {
+        if (!collections.has(field.collection)) {
+            collections.set(field.collection, new Set());
+        }
+        collections.get(field.collection)?.add(field.field);
+    }
+    return collections;
+}
+
+// Every read of a collection field in a system's (comment-blanked) text, as [`Collection.field`, offset]. Fields are
+// read through a `Res<Collection>` (or `ResMut`, `Option<Res>`) parameter, as in `image_assets.smoke.clone()`.
+export function fieldReads(text: string, collections: Map<string, Set<string>>): [string, number][] {
+    let reads: [string, number][] = [];
+    for (const [name, type] of systemParameters(text)) {
+        const collection = type.match(/^(?:Option\s*<\s*)?(?:Res|ResMut)\s*<\s*(?:'\w+\s*,\s*)?(?:[\w:]*::)?(\w+)\s*>/)?.[1];
+        const names = collection ? collections.get(collection) : undefined;
+        if (!names || !/^\w+$/.test(name)) continue;
+
+        const access = new RegExp("\\b" + name + "\\s*\\.\\s*(\\w+)", "g");
+        let found;
+        while ((found = access.exec(text)) !== null) {
+            if (names.has(found[1])) {
+                reads.push([collection + "." + found[1], found.index]);
+            }
+        }
+    }
+    return reads;
+}
+
+function addUse(uses: Map<string, Set<string>>, key: string, system: string) {
+    if (!uses.has(key)) {
+        uses.set(key, new Set());
+    }
+    uses.get(key)?.add(system);
+}
+
+// `Collection.field` → the systems reading that field. These are the keys `asset:` query parts match against.
+export function assetUses(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("asset-uses", () => {
+        const collections = collectionFields(bevyrlyIndex);
+        let uses: Map<string, Set<string>> = new Map();
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            for (const [key, _] of fieldReads(blankComments(loc.getText()), collections)) {
+                addUse(uses, key, system);
+            }
+        }
+        return uses;
+    });
+}
+
+// The fields whose handles end up in a spawned entity: read inside a `spawn(..)`/`insert(..)` call, or bound by a
+// `let` (`let texture = image_assets.explosion.clone();`) whose variable is then used inside one.
+export function spawnedFieldReads(text: string, collections: Map<string, Set<string>>): [string, SpawnSite][] {
+    const sites = parseSpawns(text);
+    const siteAt = (offset: number) => sites.find(site => site.offset <= offset && offset < site.end);
+    let spawned: [string, SpawnSite][] = [];
+    for (const [key, offset] of fieldReads(text, collections)) {
+        const site = siteAt(offset);
+        if (site) {
+            spawned.push([key, site]);
+            continue;
+        }
+
+        const binding = text.slice(0, offset).match(/\blet\s+(?:mut\s+)?(\w+)\s*(?::[^=;]*)?=\s*[^;]*$/);
+        if (!binding) continue;
+        const statement = text.indexOf(";", offset);
+        const variable = new RegExp("\\b" + binding[1] + "\\b", "g");
+        variable.lastIndex = statement < 0 ? text.length : statement;
+        let found;
+        while ((found = variable.exec(text)) !== null) {
+            const site = siteAt(found.index);
+            if (site && !spawned.some(([other, known]) => other == key && known === site)) {
+                spawned.push([key, site]);
+            }
+        }
+    }
+    return spawned;
+}
+
+// `Collection.field` → the systems spawning entities with a handle from that field; what `spawns:` parts match.
+export function assetSpawns(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("asset-spawns", () => {
+        const collections = collectionFields(bevyrlyIndex);
+        let spawns: Map<string, Set<string>> = new Map();
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            for (const [key, _] of spawnedFieldReads(blankComments(loc.getText()), collections)) {
+                addUse(spawns, key, system);
+            }
+        }
+        return spawns;
+    });
+}
+
+// For each spawn site of `system` (by offset into its text), the collection fields its handles come from.
+export function spawnSiteAssets(bevyrlyIndex: BevyrlyIndex, system: string): Map<number, string[]> {
+    let assets: Map<number, string[]> = new Map();
+    const text = blankComments(bevyrlyIndex.locs.get(system)?.getText() ?? "");
+    for (const [key, site] of spawnedFieldReads(text, collectionFields(bevyrlyIndex))) {
+        assets.set(site.offset, Array.from(new Set([...assets.get(site.offset) ?? [], key])));
+    }
+    return assets;
+}
+
+// An `AssetLoader` impl, or a bevy_common_assets plugin such as `RonAssetPlugin::<LevelBlueprint>::new(&["level.ron"])`,
+// with the file extensions it loads.
+export interface AssetLoader {
+    asset: string;
+    extensions: string[];
+    filepath: string;
+    line: number;
+}
+
+// Extensions Bevy's own loaders handle, for loads of built-in asset types.
+const builtinExtensions: [string, string][] = [
+    ["png", "Image"], ["jpg", "Image"], ["jpeg", "Image"], ["ktx2", "Image"], ["hdr", "Image"], ["exr", "Image"],
+    ["gltf", "Gltf"], ["glb", "Gltf"], ["ogg", "AudioSource"], ["wav", "AudioSource"], ["mp3", "AudioSource"],
+    ["flac", "AudioSource"], ["ttf", "Font"], ["otf", "Font"], ["wgsl", "Shader"], ["scn.ron", "DynamicScene"],
+];
+
+export function parseAssetLoaders(source: string, filepath: string): AssetLoader[] {
+    const text = blankComments(source);
+    const strings = (start: number, end: number) => Array.from(source.slice(start, end).matchAll(/"([^"]+)"/g), found => found[1]);
+    let loaders: AssetLoader[] = [];
+
+    const impl = /impl\s+(?:[\w:]*::)?AssetLoader\s+for\s+(\w+)[^{]*\{/g;
+    let found;
+    while ((found = impl.exec(text)) !== null) {
+        const open = found.index + found[0].length - 1;
+        const close = matchingClose(text, open);
+        const body = text.slice(open, close);
+        const asset = body.match(/type\s+Asset\s*=\s*(?:[\w:]*::)?(\w+)/)?.[1];
+        const extensions = body.match(/fn\s+extensions\s*\([^)]*\)[^{]*\{/);
+        if (!asset || !extensions || extensions.index === undefined) continue;
+        const start = open + extensions.index + extensions[0].length - 1;
+        loaders.push({ asset, extensions: strings(start, matchingClose(text, start)), filepath, line: lineAt(text, found.index) });
+    }
+
+    const plugin = /\b\w+AssetPlugin\s*::\s*<\s*(?:[\w:]*::)?(\w+)\s*>\s*::\s*new\s*\(/g;
+    while ((found = plugin.exec(text)) !== null) {
+        const open = found.index + found[0].length - 1;
+        loaders.push({ asset: found[1], extensions: strings(open, matchingClose(text, open)), filepath, line: lineAt(text, found.index) });
+    }
+
+    return loaders;
+}
+
+export function assetLoaders(bevyrlyIndex: BevyrlyIndex): AssetLoader[] {
+    return bevyrlyIndex.derived("asset-loaders", () =>
+        Array.from(bevyrlyIndex.sources.entries()).flatMap(([filepath, text]) => parseAssetLoaders(text, filepath)));
+}
+
+// The asset type a path loads as, by its longest matching extension; `#Scene0`-style labels of glTF files are scenes.
+export function assetTypeOfPath(path: string, extensions: [string, string][]): string | undefined {
+    const [file, label] = path.split("#");
+    if (label !== undefined && /^Scene\d*$/.test(label)) return "Scene";
+    const name = file.split("/").pop()?.toLowerCase() ?? "";
+    const found = extensions
+        .filter(([extension, _]) => name.endsWith("." + extension.toLowerCase()))
+        .sort(([a, _a], [b, _b]) => b.length - a.length)[0];
+    return found?.[1];
+}
+
+// `asset_server.load(..)` calls in a system's (original) text, as [asset type, path, offset]. The type comes from a
+// turbofish, a `Handle<T>` annotation on the `let` it's bound to, or the path's extension.
+export function parseAssetLoads(source: string, extensions: [string, string][]): [string | undefined, string, number][] {
+    const text = blankComments(source);
+    let loads: [string | undefined, string, number][] = [];
+    const pattern = /\.\s*load(?:_with_settings|_untyped)?\s*(?:::\s*<\s*(?:[\w:]*::)?(\w+)\s*>\s*)?\(\s*"([^"]*)"/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        const path = source.slice(found.index, found.index + found[0].length).match(/"([^"]*)"$/)?.[1] ?? found[2];
+        const annotated = text.slice(0, found.index).match(/\blet\s+(?:mut\s+)?\w+\s*:\s*Handle\s*<\s*(?:[\w:]*::)?(\w+)\s*>\s*=[^;]*$/)?.[1];
+        loads.push([found[1] ?? annotated ?? assetTypeOfPath(path, extensions), path, found.index]);
+    }
+    return loads;
+}
+
+export function assetExtensions(bevyrlyIndex: BevyrlyIndex): [string, string][] {
+    return [
+        ...builtinExtensions,
+        ...assetLoaders(bevyrlyIndex).flatMap(loader => loader.extensions.map((extension): [string, string] => [extension, loader.asset])),
+    ];
+}
+
+// Asset type → the systems that load it with the asset server or consume it through `Assets<T>` or `Handle<T>`
+// parameters. What `asset_type:` query parts match against; every `#[derive(Asset)]` type is a key, used or not.
+export function assetTypeUses(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("asset-type-uses", () => {
+        const extensions = assetExtensions(bevyrlyIndex);
+        let uses: Map<string, Set<string>> = new Map();
+        for (const text of bevyrlyIndex.sources.values()) {
+            for (const type of parseDerives(text).filter(type => type.derives.includes("Asset"))) {
+                uses.set(type.name, uses.get(type.name) ?? new Set());
+            }
+        }
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            const text = loc.getText();
+            for (const [type, _path, _offset] of parseAssetLoads(text, extensions)) {
+                if (type) addUse(uses, type, system);
+            }
+            for (const [_, param] of systemParameters(text)) {
+                for (const found of param.matchAll(/\b(?:Assets|Handle)\s*<\s*(?:[\w:]*::)?(\w+)\s*>/g)) {
+                    addUse(uses, found[1], system);
+                }
+            }
+        }
+        return uses;
+    });
+}
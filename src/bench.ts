@@ -0,0 +1,158 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, indexSourceFile } from '.';
+import { findAll } from './diagnostics';
+
+// Budgets a release should stay within on the synthetic workspace; the report flags any measurement above them.
+export const benchmarkBudgets = {
+    indexMsPerKloc: 40,
+    lintMsPerKloc: 5,
+    queryMs: 5,
+    heapMbPerKloc: 2,
+};
+
+const benchmarkQueries = ["&Comp3", "*Comp7 +Comp11", "<Event5", "!Event5 #Res2", ":&Comp1 *Comp2 -Comp4", "system_42"];
+
+const sharedComponents = 64;
+
+// A Bevy-shaped crate of roughly `lines` lines: each module declares components, an event and a resource, a handful of
+// systems with queries, filters, resources and events drawn from a shared pool, and a plugin that registers them.
+export function syntheticWorkspace(lines: number): Map<string, string> {
+    let files: Map<string, string> = new Map();
+    let total = 0;
+    for (let module = 0; total < lines; module++) {
+        const component = (k: number) => "Comp" + ((module * 7 + k * 13) % sharedComponents);
+        const event = "Event" + (module % 32);
+        const resource = "Res" + (module % 16);
+        let text = "use bevy::prelude::*;\n\n";
+        text += "#[derive(Component)]\npub struct Local" + module + " {\n    pub value: f32,\n}\n\n";
+        text += "#[derive(Event)]\npub struct Module" + module + "Event;\n\n";
+
+        let systems: string[] = [];
+        for (let j = 0; j < 8; j++) {
+            const name = "system_" + (module * 8 + j);
+            systems.push(name);
+            text += "/// Generated system " + j + " of module " + module + ".\n";
+            text += "pub fn " + name + "(\n";
+            text += "    mut query: Query<(&mut " + component(j) + ", &" + component(j + 1) + "), With<" + component(j + 2) + ">>,\n";
+            text += "    others: Query<&Local" + module + ", Without<" + component(j + 3) + ">>,\n";
+            text += j % 2 == 0 ? "    res: Res<" + resource + ">,\n" : "    mut res: ResMut<" + resource + ">,\n";
+            text += j % 3 == 0 ? "    mut events: EventWriter<" + event + ">,\n" : "    mut events: EventReader<" + event + ">,\n";
+            text += ") {\n";
+            text += "    for (mut a, b) in query.iter_mut() {\n";
+            text += "        let _ = (&mut a, b, &res);\n";
+            text += "    }\n";
+            text += "    for local in others.iter() {\n";
+            text += "        if local.value > 0.0 {\n";
+            text += "            continue;\n";
+            text += "        }\n";
+            text += "    }\n";
+            text += "    let _ = &mut events;\n";
+            text += "}\n\n";
+        }
+
+        text += "pub struct Module" + module + "Plugin;\n\n";
+        text += "impl Plugin for Module" + module + "Plugin {\n";
+        text += "    fn build(&self, app: &mut App) {\n";
+        text += "        app.add_event::<Module" + module + "Event>()\n";
+        text += "            .add_systems(Update, (" + systems.slice(0, 4).join(", ") + ").chain())\n";
+        text += "            .add_systems(FixedUpdate, (" + systems.slice(4).join(", ") + ").run_if(in_state(GameState::Playing)));\n";
+        text += "    }\n";
+        text += "}\n";
+
+        files.set("file:///bench/src/module_" + module + ".rs", text);
+        total += text.split("\n").length;
+    }
+
+    return files;
+}
+
+export interface BenchmarkResult {
+    lines: number;
+    files: number;
+    systems: number;
+    indexMs: number;
+    // Every lint pass over the whole index, as run after each reindex.
+    lintMs: number;
+    heapMb: number;
+    queries: [string, number][];
+}
+
+function median(values: number[]): number {
+    const sorted = values.slice().sort((a, b) => a - b);
+    return sorted[Math.floor(sorted.length / 2)];
+}
+
+// Indexes a fresh synthetic workspace (without the on-disk cache) and times each benchmark query, median of `runs`.
+export function runBenchmark(lines: number, runs: number = 15): BenchmarkResult {
+    const files = syntheticWorkspace(lines);
+    const heapBefore = process.memoryUsage().heapUsed;
+    let bevyrlyIndex = new BevyrlyIndex();
+
+    const start = performance.now();
+    for (const [filepath, text] of files) {
+        indexSourceFile(bevyrlyIndex, text, filepath);
+    }
+    const indexMs = performance.now() - start;
+    const heapMb = (process.memoryUsage().heapUsed - heapBefore) / (1024 * 1024);
+
+    const lintStart = performance.now();
+    findAll(bevyrlyIndex);
+    const lintMs = performance.now() - lintStart;
+
+    let queries: [string, number][] = [];
+    for (const query of benchmarkQueries) {
+        let timings: number[] = [];
+        for (let run = 0; run < runs; run++) {
+            const before = performance.now();
+            bevyrlyIndex.evaluate(query);
+            timings.push(performance.now() - before);
+        }
+        queries.push([query, median(timings)]);
+    }
+
+    return {
+        lines: Array.from(files.values()).reduce((sum, text) => sum + text.split("\n").length, 0),
+        files: files.size,
+        systems: bevyrlyIndex.locs.size,
+        indexMs,
+        lintMs,
+        heapMb,
+        queries,
+    };
+}
+
+export function benchmarkReport(result: BenchmarkResult): string {
+    const kloc = result.lines / 1000;
+    const verdict = (value: number, budget: number) => value <= budget ? "ok" : "**over budget**";
+    let report = "# Bevyrly benchmark\n\n";
+    report += result.lines + " lines in " + result.files + " files, " + result.systems + " systems.\n\n";
+    report += "| Measurement | Value | Budget | |\n|---|---|---|---|\n";
+    report += "| Index time | " + result.indexMs.toFixed(0) + " ms (" + (result.indexMs / kloc).toFixed(1) + " ms/kLOC) | " +
+        benchmarkBudgets.indexMsPerKloc + " ms/kLOC | " + verdict(result.indexMs / kloc, benchmarkBudgets.indexMsPerKloc) + " |\n";
+    report += "| Lint passes | " + result.lintMs.toFixed(0) + " ms (" + (result.lintMs / kloc).toFixed(1) + " ms/kLOC) | " +
+        benchmarkBudgets.lintMsPerKloc + " ms/kLOC | " + verdict(result.lintMs / kloc, benchmarkBudgets.lintMsPerKloc) + " |\n";
+    report += "| Heap growth | " + result.heapMb.toFixed(1) + " MB (" + (result.heapMb / kloc).toFixed(2) + " MB/kLOC) | " +
+        benchmarkBudgets.heapMbPerKloc + " MB/kLOC | " + verdict(result.heapMb / kloc, benchmarkBudgets.heapMbPerKloc) + " |\n";
+    for (const [query, ms] of result.queries) {
+        report += "| Query `" + query + "` | " + ms.toFixed(2) + " ms | " + benchmarkBudgets.queryMs + " ms | " +
+            verdict(ms, benchmarkBudgets.queryMs) + " |\n";
+    }
+
+    return report;
+}
+
+export function registerBenchmark(context: vscode.ExtensionContext, _bevyrlyIndex: BevyrlyIndex) {
+    let disposableBenchmark = vscode.commands.registerCommand('bevyrly.runBenchmark', async () => {
+        const size = await vscode.window.showInputBox({ value: '100000', prompt: 'Lines of synthetic Bevy code to index' });
+        if (!size || !(Number(size) > 0)) {
+            return;
+        }
+
+        const result = await vscode.window.withProgress({ location: vscode.ProgressLocation.Notification, title: "bevyrly: benchmarking" },
+            async () => runBenchmark(Number(size)));
+        const doc = await vscode.workspace.openTextDocument({ content: benchmarkReport(result), language: 'markdown' });
+        await vscode.window.showTextDocument(doc, { preview: false });
+    });
+
+    context.subscriptions.push(disposableBenchmark);
+}
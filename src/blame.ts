@@ -0,0 +1,81 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { git } from './diff';
+
+export interface BlameInfo {
+    author: string;
+    commit: string;
+    time: number;
+}
+
+// The most recent commit among the lines of a `git blame --porcelain` output.
+export function parseBlame(porcelain: string): BlameInfo | undefined {
+    let latest: BlameInfo | undefined = undefined;
+    let commit = "";
+    let author = "";
+    for (const line of porcelain.split("\n")) {
+        const header = line.match(/^([0-9a-f]{40}) \d+ \d+/);
+        if (header) {
+            commit = header[1];
+        } else if (line.startsWith("author ")) {
+            author = line.slice("author ".length);
+        } else if (line.startsWith("author-time ")) {
+            const time = Number(line.slice("author-time ".length));
+            if (!latest || time > latest.time) {
+                latest = { author, commit: commit.slice(0, 8), time };
+            }
+        }
+    }
+
+    return latest;
+}
+
+export async function blameSystems(bevyrlyIndex: BevyrlyIndex) {
+    bevyrlyIndex.blame.clear();
+    bevyrlyIndex.owners.clear();
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        const uri = vscode.Uri.parse(loc.src.filepath ?? "");
+        const folder = vscode.workspace.getWorkspaceFolder(uri);
+        if (!folder) continue;
+
+        const range = (loc.src.l(loc[0]) + 1) + "," + (loc.src.l(loc[1]) + 1);
+        try {
+            const info = parseBlame(await git(folder.uri.fsPath, ["blame", "--porcelain", "-L", range, "--", uri.fsPath]));
+            if (info) {
+                bevyrlyIndex.blame.set(system, info);
+                if (!bevyrlyIndex.owners.has(info.author)) {
+                    bevyrlyIndex.owners.set(info.author, new Set());
+                }
+                bevyrlyIndex.owners.get(info.author)?.add(system);
+            }
+        } catch {
+            // Untracked files and folders outside of git have no blame.
+        }
+    }
+    bevyrlyIndex.invalidateResults(new Set());
+}
+
+export function blameLabel(bevyrlyIndex: BevyrlyIndex, system: string): string {
+    const info = bevyrlyIndex.blame.get(system);
+    return info ? info.author + ", " + info.commit : "";
+}
+
+export function registerBlame(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    const enabled = () => vscode.workspace.getConfiguration('bevyrly').get<boolean>('blame') ?? false;
+    context.subscriptions.push(bevyrlyIndex.onDidIndex(() => {
+        if (enabled()) {
+            blameSystems(bevyrlyIndex);
+        }
+    }));
+    context.subscriptions.push(vscode.workspace.onDidChangeConfiguration(event => {
+        if (event.affectsConfiguration('bevyrly.blame')) {
+            if (enabled()) {
+                blameSystems(bevyrlyIndex);
+            } else {
+                bevyrlyIndex.blame.clear();
+                bevyrlyIndex.owners.clear();
+                bevyrlyIndex.invalidateResults(new Set());
+            }
+        }
+    }));
+}
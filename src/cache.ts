@@ -0,0 +1,352 @@
+import * as vscode from 'vscode';
+import { createHash } from 'crypto';
+import { brotliCompressSync, brotliDecompressSync } from 'zlib';
+import { BevyrlyIndex, QueryStorage, SystemSpan, startBevyrlyIndexing } from '.';
+
+// Bump whenever cached records change shape or the parser extracts something new, and add a migration from the previous
+// format below if old entries can be upgraded. Caches that can't be (or come from a newer bevyrly) are rebuilt from source.
+export const INDEX_FORMAT_VERSION = 2;
+
+type CachedStorage = Exclude<QueryStorage, "systems"> | "direct";
+
+const cachedStorages: CachedStorage[] = ["direct", "query", "mut_query", "res", "mut_res", "event_read", "event_write", "with", "without"];
+
+export interface CachedSystem {
+    name: string;
+    start: number;
+    end: number;
+    accesses: [CachedStorage, string][];
+}
+
+interface CachedFile {
+    hash: string;
+    systems: CachedSystem[];
+}
+
+interface CacheContents {
+    version: number;
+    files: { [filepath: string]: CachedFile };
+}
+
+// Upgrades a cache of format `n` to format `n + 1`. A gap in the chain means older caches are rebuilt instead.
+const cacheMigrations: Map<number, (contents: CacheContents) => CacheContents> = new Map([
+    // Format 2 changed how the cache is stored (JSON to compact binary), not its records.
+    [1, contents => ({ ...contents, version: 2 })],
+]);
+
+export function migrateCache(contents: CacheContents): CacheContents | undefined {
+    while (contents.version < INDEX_FORMAT_VERSION) {
+        const migrate = cacheMigrations.get(contents.version);
+        if (!migrate) return undefined;
+        contents = migrate(contents);
+    }
+    return contents.version == INDEX_FORMAT_VERSION ? contents : undefined;
+}
+
+export function contentHash(text: string): string {
+    return createHash("sha1").update(text).digest("hex");
+}
+
+// The cache file is a 16-byte header followed by a Brotli-compressed body:
+//
+//   header: "BVRLYIDX" magic (8 bytes), format version (u32 LE), uncompressed body length (u32 LE)
+//   body:   strings: count, then per string its UTF-8 byte length and bytes
+//           files:   count, then per file: path (string index), SHA-1 of its content (20 raw bytes), system count,
+//                    then per system: name (string index), start offset, length, access count,
+//                    then per access: storage (1 byte, index into `cachedStorages`), type name (string index)
+//
+// Every count, length, offset and string index in the body is an unsigned LEB128 varint.
+const magic = "BVRLYIDX";
+
+class Writer {
+    bytes: number[] = [];
+
+    varint(value: number) {
+        while (value >= 0x80) {
+            this.bytes.push((value & 0x7f) | 0x80);
+            value = Math.floor(value / 0x80);
+        }
+        this.bytes.push(value);
+    }
+
+    raw(bytes: Uint8Array) {
+        for (const byte of bytes) this.bytes.push(byte);
+    }
+}
+
+class Reader {
+    private _bytes: Uint8Array;
+    private _at: number = 0;
+
+    constructor(bytes: Uint8Array) {
+        this._bytes = bytes;
+    }
+
+    varint(): number {
+        let value = 0;
+        let scale = 1;
+        while (true) {
+            if (this._at >= this._bytes.length) throw new Error("truncated index cache");
+            const byte = this._bytes[this._at++];
+            value += (byte & 0x7f) * scale;
+            if (byte < 0x80) return value;
+            scale *= 0x80;
+        }
+    }
+
+    raw(length: number): Uint8Array {
+        if (this._at + length > this._bytes.length) throw new Error("truncated index cache");
+        this._at += length;
+        return this._bytes.subarray(this._at - length, this._at);
+    }
+}
+
+export function encodeCache(contents: CacheContents): Uint8Array {
+    let strings: Map<string, number> = new Map();
+    const intern = (text: string) => {
+        if (!strings.has(text)) strings.set(text, strings.size);
+        return strings.get(text) ?? 0;
+    };
+
+    let files = new Writer();
+    const entries = Object.entries(contents.files);
+    files.varint(entries.length);
+    for (const [filepath, file] of entries) {
+        files.varint(intern(filepath));
+        files.raw(Buffer.from(file.hash, "hex"));
+        files.varint(file.systems.length);
+        for (const system of file.systems) {
+            files.varint(intern(system.name));
+            files.varint(system.start);
+            files.varint(system.end - system.start);
+            files.varint(system.accesses.length);
+            for (const [storage, key] of system.accesses) {
+                files.bytes.push(cachedStorages.indexOf(storage));
+                files.varint(intern(key));
+            }
+        }
+    }
+
+    let body = new Writer();
+    body.varint(strings.size);
+    for (const text of strings.keys()) {
+        const bytes = new TextEncoder().encode(text);
+        body.varint(bytes.length);
+        body.raw(bytes);
+    }
+    body.raw(Uint8Array.from(files.bytes));
+
+    const raw = Uint8Array.from(body.bytes);
+    let header = Buffer.alloc(16);
+    header.write(magic, 0, "ascii");
+    header.writeUInt32LE(contents.version, 8);
+    header.writeUInt32LE(raw.length, 12);
+    return Buffer.concat([header, brotliCompressSync(raw)]);
+}
+
+// The format version in a cache file's header, or undefined if it isn't a bevyrly cache at all.
+export function cacheVersionOf(bytes: Uint8Array): number | undefined {
+    const buffer = Buffer.from(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+    return buffer.length >= 16 && buffer.toString("ascii", 0, 8) == magic ? buffer.readUInt32LE(8) : undefined;
+}
+
+// Returns undefined for anything that is not a cache of the current format version, or of one that migrates to it.
+export function decodeCache(bytes: Uint8Array): CacheContents | undefined {
+    const buffer = Buffer.from(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+    const version = cacheVersionOf(bytes);
+    if (version === undefined || version < 2 || version > INDEX_FORMAT_VERSION) {
+        return undefined;
+    }
+
+    const raw = brotliDecompressSync(buffer.subarray(16));
+    if (raw.length != buffer.readUInt32LE(12)) {
+        return undefined;
+    }
+
+    let reader = new Reader(raw);
+    let strings: string[] = [];
+    for (let count = reader.varint(); count > 0; count--) {
+        strings.push(new TextDecoder().decode(reader.raw(reader.varint())));
+    }
+
+    let contents: CacheContents = { version, files: {} };
+    for (let files = reader.varint(); files > 0; files--) {
+        const filepath = strings[reader.varint()];
+        const hash = Buffer.from(reader.raw(20)).toString("hex");
+        let systems: CachedSystem[] = [];
+        for (let count = reader.varint(); count > 0; count--) {
+            const name = strings[reader.varint()];
+            const start = reader.varint();
+            const end = start + reader.varint();
+            let accesses: [CachedStorage, string][] = [];
+            for (let access = reader.varint(); access > 0; access--) {
+                accesses.push([cachedStorages[reader.raw(1)[0]], strings[reader.varint()]]);
+            }
+            systems.push({ name, start, end, accesses });
+        }
+        contents.files[filepath] = { hash, systems };
+    }
+
+    return migrateCache(contents);
+}
+
+// Stands in for the jinx-rust source file of a cached system: its path, and 0-based lines of offsets into `text`.
+function sourceOf(text: string, filepath: string): SystemSpan["src"] {
+    let lineStarts = [0];
+    for (let i = 0; i < text.length; i++) {
+        if (text[i] == "\n") lineStarts.push(i + 1);
+    }
+
+    return {
+        filepath,
+        l(offset: number): number {
+            let [low, high] = [0, lineStarts.length - 1];
+            while (low < high) {
+                const mid = (low + high + 1) >> 1;
+                if (lineStarts[mid] <= offset) low = mid; else high = mid - 1;
+            }
+            return low;
+        },
+    };
+}
+
+// The functions parsed into `bevyrlyIndex` (usually a scratch index holding a single file), as cacheable records.
+export function recordSystems(bevyrlyIndex: BevyrlyIndex): CachedSystem[] {
+    let systems: CachedSystem[] = [];
+    for (const [name, loc] of bevyrlyIndex.locs) {
+        let accesses: [CachedStorage, string][] = [];
+        for (const storage of cachedStorages) {
+            for (const [key, users] of bevyrlyIndex[storage]) {
+                if (users.has(name)) accesses.push([storage, key]);
+            }
+        }
+        systems.push({ name, start: loc[0], end: loc[1], accesses });
+    }
+
+    return systems;
+}
+
+export function replaySystems(bevyrlyIndex: BevyrlyIndex, systems: CachedSystem[], text: string, filepath: string) {
+    const src = sourceOf(text, filepath);
+    for (const system of systems) {
+        bevyrlyIndex.locs.set(system.name, { 0: system.start, 1: system.end, src, getText: () => text.slice(system.start, system.end) });
+        for (const [storage, key] of system.accesses) {
+            if (storage == "direct") {
+                bevyrlyIndex.addDirect(system.name, key);
+            } else {
+                bevyrlyIndex.addQueryStorage(system.name, key, storage);
+            }
+        }
+    }
+}
+
+// Parsed systems per file, keyed by content hash, persisted in the extension's workspace storage between sessions.
+export class IndexCache {
+    private _file: vscode.Uri;
+    private _contents: CacheContents;
+    private _seen: Set<string> = new Set();
+    private _dirty: boolean = false;
+
+    constructor(file: vscode.Uri, contents: CacheContents, dirty: boolean = false) {
+        this._file = file;
+        this._contents = contents;
+        this._dirty = dirty;
+    }
+
+    static location(context: vscode.ExtensionContext): vscode.Uri {
+        return vscode.Uri.joinPath(context.storageUri ?? context.globalStorageUri, "index-cache.bin");
+    }
+
+    // Returns undefined when `bevyrly.indexCache` is off, and an empty cache when the stored one is missing, unreadable,
+    // or written by a different index format.
+    static async load(context: vscode.ExtensionContext): Promise<IndexCache | undefined> {
+        if (!(vscode.workspace.getConfiguration('bevyrly').get<boolean>('indexCache') ?? true)) {
+            return undefined;
+        }
+
+        const file = IndexCache.location(context);
+        const empty: CacheContents = { version: INDEX_FORMAT_VERSION, files: {} };
+        let bytes: Uint8Array | undefined = undefined;
+        try {
+            bytes = await vscode.workspace.fs.readFile(file);
+        } catch {
+            // Nothing cached yet.
+        }
+
+        // Format 1 kept the cache as JSON next to this file; it is migrated once and then removed.
+        const legacy = vscode.Uri.joinPath(file, "..", "index-cache.json");
+        let migrated: CacheContents | undefined = undefined;
+        try {
+            const json = JSON.parse(new TextDecoder().decode(await vscode.workspace.fs.readFile(legacy)));
+            migrated = bytes ? undefined : migrateCache(json);
+            await vscode.workspace.fs.delete(legacy);
+        } catch {
+            // Already gone.
+        }
+        if (migrated) {
+            return new IndexCache(file, migrated, true);
+        }
+        if (!bytes) {
+            return new IndexCache(file, empty);
+        }
+
+        let contents: CacheContents | undefined = undefined;
+        try {
+            contents = decodeCache(bytes);
+        } catch {
+            // Reported below.
+        }
+        if (!contents) {
+            const version = cacheVersionOf(bytes);
+            const problem = version === undefined ? "is unreadable"
+                : version > INDEX_FORMAT_VERSION ? "was written by a newer version of bevyrly (format " + version + ")"
+                    : "uses format " + version + ", which this version of bevyrly can't upgrade";
+            vscode.window.showInformationMessage("bevyrly's index cache " + problem + "; rebuilding it from source");
+        }
+        const version = contents?.version;
+        return new IndexCache(file, contents ?? empty, version !== undefined && version != cacheVersionOf(bytes));
+    }
+
+    lookup(filepath: string, hash: string): CachedSystem[] | undefined {
+        this._seen.add(filepath);
+        const entry = this._contents.files[filepath];
+        return entry && entry.hash == hash ? entry.systems : undefined;
+    }
+
+    store(filepath: string, hash: string, systems: CachedSystem[]) {
+        this._seen.add(filepath);
+        this._contents.files[filepath] = { hash, systems };
+        this._dirty = true;
+    }
+
+    // Writes the cache back, dropping entries for files that were not indexed this time (deleted or moved).
+    async save() {
+        for (const filepath of Object.keys(this._contents.files)) {
+            if (!this._seen.has(filepath)) {
+                delete this._contents.files[filepath];
+                this._dirty = true;
+            }
+        }
+
+        if (!this._dirty) {
+            return;
+        }
+
+        await vscode.workspace.fs.createDirectory(vscode.Uri.joinPath(this._file, ".."));
+        await vscode.workspace.fs.writeFile(this._file, encodeCache(this._contents));
+        this._dirty = false;
+    }
+}
+
+export function registerIndexCache(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableClear = vscode.commands.registerCommand('bevyrly.clearIndexCache', async () => {
+        try {
+            await vscode.workspace.fs.delete(IndexCache.location(context));
+        } catch {
+            // Nothing cached yet.
+        }
+        await startBevyrlyIndexing(context, bevyrlyIndex);
+        vscode.window.showInformationMessage("bevyrly cleared its index cache and reindexed the workspace");
+    });
+    context.subscriptions.push(disposableClear);
+}
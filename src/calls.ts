@@ -0,0 +1,43 @@
+import { QueryStorage } from '.';
+import { systemParameters } from './complexity';
+import { queryStateAccesses } from './dynamic';
+import { blankComments } from './registrations';
+
+export type ParameterAccess = [QueryStorage | "direct", string];
+
+function outerType(type: string): string {
+    return type.trim().match(/^(?:[\w:]*::)?(\w+)/)?.[1] ?? "";
+}
+
+// What a function's parameters access, references to system parameters included, since helpers take what the calling
+// system lends them: `&mut Commands`, `&Res<ImageAssets>`, `&mut EventWriter<Hit>` or `&Query<&mut Health, With<Ship>>`.
+export function parameterAccesses(source: string): ParameterAccess[] {
+    let accesses: ParameterAccess[] = [];
+    for (const [_, param] of systemParameters(source)) {
+        const type = param.replace(/^&\s*(?:'\w+\s+)?(?:mut\s+)?/, "").replace(/'\w+\s*,?\s*/g, "").trim();
+        const found = type.match(/^(?:[\w:]*::)?(\w+)\s*(?:<([\s\S]*)>)?$/);
+        if (!found) continue;
+        const argument = found[2] ?? "";
+        switch (found[1]) {
+            case "Commands": accesses.push(["direct", "Commands"]); break;
+            case "Res": case "NonSend": accesses.push(["res", outerType(argument)]); break;
+            case "ResMut": case "NonSendMut": accesses.push(["mut_res", outerType(argument)]); break;
+            case "EventReader": accesses.push(["event_read", outerType(argument)]); break;
+            case "EventWriter": accesses.push(["event_write", outerType(argument)]); break;
+            case "Query": accesses.push(...queryStateAccesses(argument)); break;
+        }
+    }
+    return accesses.filter(([_, type]) => /^[A-Z]/.test(type));
+}
+
+// Functions a body calls by name, as in `spawn_smoke(&mut commands, ..)` or `effects::spawn_smoke(..)`. Method calls
+// aren't followed: which `impl` they land in needs types bevyrly doesn't have.
+export function calledFunctions(source: string): string[] {
+    const text = blankComments(source);
+    const body = text.slice(text.indexOf("{"));
+    let called: Set<string> = new Set();
+    for (const found of body.matchAll(/(?:^|[^.\w:])(?:\w+\s*::\s*)*([a-z_]\w*)\s*(?:::\s*<[^>]*>\s*)?\(/g)) {
+        called.add(found[1]);
+    }
+    return Array.from(called);
+}
@@ -0,0 +1,50 @@
+import { BevyrlyIndex, QueryStorage } from '.';
+import { blankComments, matchingClose } from './registrations';
+
+// Cross-cutting concerns no single type name captures, each with the types that make a system part of it. What
+// `category:` query parts match against, e.g. `category:visibility` to audit everything that can show or hide things.
+export const builtinCategories: [string, string[]][] = [
+    ["visibility", ["Visibility", "InheritedVisibility", "ViewVisibility", "ComputedVisibility"]],
+    ["render_layers", ["RenderLayers"]],
+    ["camera", [
+        "Camera", "Camera2d", "Camera3d", "Camera2dBundle", "Camera3dBundle", "Projection", "OrthographicProjection",
+        "PerspectiveProjection",
+    ]],
+];
+
+// A system is in a category when it accesses one of the category's types (other than through `Without<T>`) or names
+// one in its body, as in `commands.entity(e).insert(Visibility::Hidden)` or spawning a `Camera2dBundle`.
+export function systemCategories(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("categories", () => {
+        let categories: Map<string, Set<string>> = new Map(builtinCategories.map(([category, _]) => [category, new Set()]));
+        let categoriesOf: Map<string, string[]> = new Map();
+        for (const [category, types] of builtinCategories) {
+            for (const type of types) {
+                categoriesOf.set(type, [...categoriesOf.get(type) ?? [], category]);
+            }
+        }
+
+        const storages: QueryStorage[] = ["query", "mut_query", "res", "mut_res", "event_read", "event_write", "with"];
+        for (const storage of storages) {
+            for (const [type, systems] of bevyrlyIndex[storage]) {
+                for (const category of categoriesOf.get(type) ?? []) {
+                    systems.forEach(system => categories.get(category)?.add(system));
+                }
+            }
+        }
+
+        const mention = new RegExp("\\b(" + Array.from(categoriesOf.keys()).join("|") + ")\\b", "g");
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            const text = blankComments(loc.getText());
+            const open = text.indexOf("(");
+            const body = open < 0 ? -1 : text.indexOf("{", matchingClose(text, open));
+            for (const found of body < 0 ? [] : text.slice(body).matchAll(mention)) {
+                for (const category of categoriesOf.get(found[1]) ?? []) {
+                    categories.get(category)?.add(system);
+                }
+            }
+        }
+
+        return categories;
+    });
+}
@@ -0,0 +1,421 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { TomlTable, parseToml } from './config';
+import { SystemRegistration, blankComments, matchingClose } from './registrations';
+
+// A `#[cfg(..)]` predicate.
+export type Cfg
+    = { kind: "all" | "any", items: Cfg[] }
+    | { kind: "not", item: Cfg }
+    | { kind: "option", key: string, value?: string };
+
+// Parses what's inside `cfg(..)`, e.g. `all(feature = "editor", not(debug_assertions))`.
+export function parseCfg(text: string): Cfg | undefined {
+    const tokens = text.match(/"(?:[^"\\]|\\.)*"|[A-Za-z_][\w:]*|[(),=]/g) ?? [];
+    let at = 0;
+    const parse = (): Cfg | undefined => {
+        const name = tokens[at++];
+        if (name === undefined || !/^[A-Za-z_]/.test(name)) return undefined;
+        if (tokens[at] == "(" && (name == "all" || name == "any" || name == "not")) {
+            at++;
+            let items: Cfg[] = [];
+            while (at < tokens.length && tokens[at] != ")") {
+                const item = parse();
+                if (!item) return undefined;
+                items.push(item);
+                if (tokens[at] == ",") at++;
+            }
+            at++;
+            return name == "not" ? (items.length == 1 ? { kind: "not", item: items[0] } : undefined) : { kind: name, items };
+        }
+        if (tokens[at] == "=") {
+            const value = tokens[at + 1];
+            at += 2;
+            return value?.startsWith('"') ? { kind: "option", key: name, value: value.slice(1, -1) } : undefined;
+        }
+        return { kind: "option", key: name };
+    };
+
+    const cfg = parse();
+    return cfg && at == tokens.length ? cfg : undefined;
+}
+
+export function cfgText(cfg: Cfg): string {
+    switch (cfg.kind) {
+        case "all": case "any": return cfg.kind + "(" + cfg.items.map(cfgText).join(", ") + ")";
+        case "not": return "not(" + cfgText(cfg.item) + ")";
+        case "option": return cfg.key + (cfg.value !== undefined ? " = \"" + cfg.value + "\"" : "");
+    }
+}
+
+// What a build has on: its features (undecided when undefined), and whether `debug_assertions` is. Other options
+// (`target_os`, ..) are unknown.
+export interface CfgEnvironment {
+    features: Set<string> | undefined;
+    debugAssertions: boolean;
+}
+
+// Whether a predicate holds in `environment`; undefined when that depends on options it doesn't decide.
+export function evalCfg(cfg: Cfg, environment: CfgEnvironment): boolean | undefined {
+    switch (cfg.kind) {
+        case "all": case "any": {
+            const values = cfg.items.map(item => evalCfg(item, environment));
+            const decisive = cfg.kind == "all" ? false : true;
+            if (values.includes(decisive)) return decisive;
+            return values.includes(undefined) ? undefined : !decisive;
+        }
+        case "not": {
+            const value = evalCfg(cfg.item, environment);
+            return value === undefined ? undefined : !value;
+        }
+        case "option":
+            if (cfg.key == "feature") return environment.features && cfg.value !== undefined ? environment.features.has(cfg.value) : undefined;
+            if (cfg.key == "debug_assertions") return environment.debugAssertions;
+            if (cfg.key == "test" || cfg.key == "doc" || cfg.key == "doctest") return false;
+            return undefined;
+    }
+}
+
+// Skips whitespace and further attributes from `at`, then finds the end of the item or statement starting there: its
+// `;`, or the `}` closing its first top-level block.
+function itemEnd(text: string, at: number): number {
+    let i = at;
+    while (i < text.length) {
+        while (i < text.length && /\s/.test(text[i])) i++;
+        if (text[i] != "#" || text[i + 1] != "[") break;
+        i = matchingClose(text, i + 1) + 1;
+        if (i <= 0) return text.length;
+    }
+
+    for (; i < text.length; i++) {
+        const c = text[i];
+        if (c == ";") return i;
+        if (c == "}") return i;
+        if (c == "(" || c == "[" || c == "{") {
+            const close = matchingClose(text, i);
+            if (close < 0) return text.length;
+            if (c == "{") {
+                // A block ends the item unless a method chain or `else` carries on after it.
+                const rest = text.slice(close + 1).match(/^\s*(\.|else\b|;)/);
+                if (!rest) return close;
+            }
+            i = close;
+        }
+    }
+    return text.length;
+}
+
+// Every `#[cfg(..)]` in a file with the [start, end] offsets of what it gates; `#![cfg(..)]` gates the whole file.
+export function parseCfgRanges(source: string): [number, number, Cfg][] {
+    const text = blankComments(source);
+    let ranges: [number, number, Cfg][] = [];
+    for (const found of text.matchAll(/#(!?)\[\s*cfg\s*\(/g)) {
+        const open = (found.index ?? 0) + found[0].length - 1;
+        const close = matchingClose(text, open);
+        const attribute = text.indexOf("]", close);
+        if (close < 0 || attribute < 0) continue;
+        const cfg = parseCfg(text.slice(open + 1, close));
+        if (!cfg) continue;
+
+        ranges.push(found[1] ? [0, text.length, cfg] : [found.index ?? 0, itemEnd(text, attribute + 1), cfg]);
+    }
+    return ranges;
+}
+
+// `#[cfg(..)] mod name;` declarations, which gate the files of those modules.
+function gatedModules(source: string): Map<string, Cfg[]> {
+    const text = blankComments(source);
+    let modules: Map<string, Cfg[]> = new Map();
+    for (const [start, end, cfg] of parseCfgRanges(source)) {
+        const declaration = text.slice(start, end + 1).match(/\bmod\s+(\w+)\s*;$/);
+        if (declaration) {
+            modules.set(declaration[1], [...modules.get(declaration[1]) ?? [], cfg]);
+        }
+    }
+    return modules;
+}
+
+function directoryOf(filepath: string): string {
+    return filepath.slice(0, filepath.lastIndexOf("/"));
+}
+
+// The directory holding a file's child modules: its own for `lib.rs`, `main.rs` and `mod.rs`, `name/` next to it
+// otherwise.
+function childDirectory(filepath: string): string {
+    return /\/(?:lib|main|mod)\.rs$/.test(filepath) ? directoryOf(filepath) : filepath.replace(/\.rs$/, "");
+}
+
+export interface CfgGates {
+    systems: Map<string, Cfg[]>;
+    registrations: Map<SystemRegistration, Cfg[]>;
+    // The gates of anything else at an offset in a file, e.g. a `configure_sets` call.
+    at: (filepath: string, offset: number) => Cfg[];
+}
+
+// The cfg predicates every system definition and registration sits behind: attributes on it or on what encloses it,
+// the file's own `#![cfg(..)]`, and those on the `mod` declarations leading to its file.
+export function cfgGates(bevyrlyIndex: BevyrlyIndex): CfgGates {
+    return bevyrlyIndex.derived("cfg-gates", () => {
+        let ranges: Map<string, [number, number, Cfg][]> = new Map();
+        let modules: Map<string, Map<string, Cfg[]>> = new Map();
+        // Files by the directory their child modules live in, to find the one declaring a module without a scan.
+        let declarers: Map<string, string[]> = new Map();
+        for (const [filepath, text] of bevyrlyIndex.sources) {
+            const children = childDirectory(filepath);
+            ranges.set(filepath, parseCfgRanges(text));
+            modules.set(children, new Map([...modules.get(children) ?? [], ...gatedModules(text)]));
+            declarers.set(children, [...declarers.get(children) ?? [], filepath]);
+        }
+
+        let files: Map<string, Cfg[]> = new Map();
+        const fileGates = (filepath: string, depth: number = 0): Cfg[] => {
+            if (files.has(filepath)) return files.get(filepath) ?? [];
+            const name = filepath.split("/").pop()?.replace(/\.rs$/, "") ?? "";
+            const [module, parent] = name == "mod"
+                ? [directoryOf(filepath).split("/").pop() ?? "", directoryOf(directoryOf(filepath))]
+                : [name, directoryOf(filepath)];
+            const declared = modules.get(parent)?.get(module) ?? [];
+            const declaring = declarers.get(parent)?.find(other => other != filepath);
+            const gates = [...declared, ...(declaring && depth < 16 ? fileGates(declaring, depth + 1) : [])];
+            files.set(filepath, gates);
+            return gates;
+        };
+        const gatesAt = (filepath: string, offset: number) => [
+            ...fileGates(filepath),
+            ...(ranges.get(filepath) ?? []).filter(([start, end, _]) => start <= offset && offset <= end).map(([_, __, cfg]) => cfg),
+        ];
+
+        let gates: CfgGates = { systems: new Map(), registrations: new Map(), at: gatesAt };
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            gates.systems.set(system, gatesAt(loc.src.filepath ?? "", loc[0]));
+        }
+        let lineStarts: Map<string, number[]> = new Map();
+        for (const reg of Array.from(bevyrlyIndex.registrations.values()).flat()) {
+            const text = bevyrlyIndex.sources.get(reg.filepath) ?? "";
+            if (!lineStarts.has(reg.filepath)) {
+                lineStarts.set(reg.filepath, [0, ...Array.from(text.matchAll(/\n/g), found => (found.index ?? 0) + 1)]);
+            }
+            const offset = lineStarts.get(reg.filepath)?.[reg.line] ?? 0;
+            const indent = /\s*/y;
+            indent.lastIndex = offset;
+            gates.registrations.set(reg, gatesAt(reg.filepath, offset + (indent.exec(text)?.[0].length ?? 0)));
+        }
+        return gates;
+    });
+}
+
+// A crate's `[features]`: what each enables, and which are on by default. Optional dependencies are features too,
+// unless a `dep:` entry hides them. The `[package].name` comes along, as type paths and span names use it.
+export interface CrateFeatures {
+    declared: Map<string, string[]>;
+    defaults: string[];
+    packageName?: string;
+}
+
+export function parseCrateFeatures(manifest: string): CrateFeatures {
+    const toml = parseToml(manifest);
+    let declared: Map<string, string[]> = new Map();
+    for (const [feature, enables] of Object.entries((toml["features"] ?? {}) as TomlTable)) {
+        declared.set(feature, Array.isArray(enables) ? enables.filter((item): item is string => typeof item == "string") : []);
+    }
+    const hidden = new Set(Array.from(declared.values()).flat().filter(item => item.startsWith("dep:")).map(item => item.slice(4)));
+    for (const [dependency, spec] of Object.entries((toml["dependencies"] ?? {}) as TomlTable)) {
+        if (typeof spec == "object" && !Array.isArray(spec) && (spec as TomlTable)["optional"] === true && !hidden.has(dependency) && !declared.has(dependency)) {
+            declared.set(dependency, []);
+        }
+    }
+    const packageName = ((toml["package"] ?? {}) as TomlTable)["name"];
+    return { declared, defaults: declared.get("default") ?? [], packageName: typeof packageName == "string" ? packageName : undefined };
+}
+
+// The package a source file belongs to, as the URI of the directory above its `src/` (or `examples/`, `benches/` or
+// `tests/`), or its own directory otherwise.
+export function crateDirectory(filepath: string): string {
+    const at = filepath.lastIndexOf("/src/");
+    if (at >= 0) return filepath.slice(0, at);
+    const target = filepath.match(/^(.*)\/(?:examples|benches|tests)\//);
+    return target ? target[1] : directoryOf(filepath);
+}
+
+export async function loadCrateFeatures(bevyrlyIndex: BevyrlyIndex, filepath: string) {
+    const crate = crateDirectory(filepath);
+    if (bevyrlyIndex.crateFeatures.has(crate)) return;
+    try {
+        const manifest = new TextDecoder().decode(await vscode.workspace.fs.readFile(vscode.Uri.parse(crate + "/Cargo.toml")));
+        bevyrlyIndex.crateFeatures.set(crate, parseCrateFeatures(manifest));
+    } catch {
+        // Without a readable manifest nothing is known about the crate's features; a build turns on only the requested ones.
+        bevyrlyIndex.crateFeatures.set(crate, undefined);
+    }
+}
+
+// Features requested for a query with `--features a,b` and `--no-default-features`, and `--release` for a build without
+// `debug_assertions`.
+export interface FeatureScope {
+    features: string[];
+    noDefaultFeatures: boolean;
+    release: boolean;
+}
+
+// Takes the feature flags out of a query: [query, scope], with no scope when none were given.
+export function splitFeatures(query: string): [string, FeatureScope | undefined] {
+    let scope: FeatureScope = { features: [], noDefaultFeatures: false, release: false };
+    let given = false;
+    let parts: string[] = [];
+    const words = query.split(" ");
+    for (let i = 0; i < words.length; i++) {
+        if (words[i] == "--features" && i + 1 < words.length) {
+            scope.features.push(...words[++i].split(",").filter(feature => feature.length > 0));
+        } else if (words[i].startsWith("--features=")) {
+            scope.features.push(...words[i].slice("--features=".length).split(",").filter(feature => feature.length > 0));
+        } else if (words[i] == "--no-default-features") {
+            scope.noDefaultFeatures = true;
+        } else if (words[i] == "--release") {
+            scope.release = true;
+        } else {
+            parts.push(words[i]);
+            continue;
+        }
+        given = true;
+    }
+    return [parts.join(" ").trim(), given ? scope : undefined];
+}
+
+function editDistance(a: string, b: string): number {
+    let row = Array.from({ length: b.length + 1 }, (_, i) => i);
+    for (let i = 1; i <= a.length; i++) {
+        let previous = row[0];
+        row[0] = i;
+        for (let j = 1; j <= b.length; j++) {
+            const current = row[j];
+            row[j] = Math.min(row[j] + 1, row[j - 1] + 1, previous + (a[i - 1] == b[j - 1] ? 0 : 1));
+            previous = current;
+        }
+    }
+    return row[b.length];
+}
+
+function cfgOptions(cfg: Cfg): { key: string, value?: string }[] {
+    switch (cfg.kind) {
+        case "all": case "any": return cfg.items.flatMap(cfgOptions);
+        case "not": return cfgOptions(cfg.item);
+        case "option": return [cfg];
+    }
+}
+
+function holds(cfg: Cfg, option: (key: string, value?: string) => boolean): boolean {
+    switch (cfg.kind) {
+        case "all": return cfg.items.every(item => holds(item, option));
+        case "any": return cfg.items.some(item => holds(item, option));
+        case "not": return !holds(cfg.item, option);
+        case "option": return option(cfg.key, cfg.value);
+    }
+}
+
+// Why no build of `crate` can satisfy `cfg`, or undefined when some can: it relies on features the manifest doesn't
+// declare, or contradicts itself (`all(a, not(a))`) given what features enable. Every other option is free to take
+// either value.
+export function unreachableCfg(cfg: Cfg, crate: CrateFeatures): string | undefined {
+    const options = cfgOptions(cfg);
+    const features = Array.from(new Set(options.filter(option => option.key == "feature").map(option => option.value ?? "")));
+    const declared = features.filter(feature => crate.declared.has(feature));
+    const others = Array.from(new Set(options.filter(option => option.key != "feature").map(option => option.key + "=" + (option.value ?? ""))));
+    if (declared.length + others.length > 14) return undefined;
+
+    // Enough to try every set of the features it names, closed under what they enable, since no build can differ
+    // from all of those on just these features.
+    for (let set = 0; set < 1 << declared.length; set++) {
+        const enabled = closedFeatures(crate, { features: declared.filter((_, i) => set & (1 << i)), noDefaultFeatures: true, release: false });
+        for (let rest = 0; rest < 1 << others.length; rest++) {
+            const option = (key: string, value?: string) => key == "feature"
+                ? enabled.has(value ?? "")
+                : (rest & (1 << others.indexOf(key + "=" + (value ?? "")))) != 0;
+            if (holds(cfg, option)) return undefined;
+        }
+    }
+
+    const undeclared = features.filter(feature => !crate.declared.has(feature));
+    if (undeclared.length == 0) return "it contradicts itself, given what each feature enables";
+    return undeclared.map(feature => {
+        const close = Array.from(crate.declared.keys()).find(known => editDistance(feature, known) <= 2);
+        return "feature `" + feature + "` isn't declared in Cargo.toml" + (close ? " (did you mean `" + close + "`?)" : "");
+    }).join(", ");
+}
+
+function closedFeatures(crate: CrateFeatures | undefined, scope: FeatureScope): Set<string> {
+    let features: Set<string> = new Set();
+    let pending = [...scope.features, ...(scope.noDefaultFeatures ? [] : crate?.defaults ?? [])];
+    while (pending.length > 0) {
+        const feature = pending.pop() as string;
+        if (feature.includes("/") || features.has(feature)) continue;
+        const name = feature.replace(/^dep:/, "");
+        features.add(name);
+        pending.push(...crate?.declared.get(name) ?? []);
+    }
+    return features;
+}
+
+// What `scope` turns on in the crate holding `filepath`, with the features each one enables in turn.
+export function cfgEnvironment(bevyrlyIndex: BevyrlyIndex, filepath: string, scope: FeatureScope): CfgEnvironment {
+    return { features: closedFeatures(bevyrlyIndex.crateFeatures.get(crateDirectory(filepath)), scope), debugAssertions: !scope.release };
+}
+
+// Whether a system is compiled into a build: its definition isn't cfg'd out, and when it's registered, at least one
+// registration isn't either. Predicates the build's environment doesn't decide count as on.
+function compiledIn(bevyrlyIndex: BevyrlyIndex, system: string, environment: (filepath: string) => CfgEnvironment): boolean {
+    const gates = cfgGates(bevyrlyIndex);
+    const enabled = (filepath: string, cfgs: Cfg[]) => cfgs.every(cfg => evalCfg(cfg, environment(filepath)) !== false);
+    if (!enabled(bevyrlyIndex.locs.get(system)?.src.filepath ?? "", gates.systems.get(system) ?? [])) return false;
+
+    const registrations = bevyrlyIndex.registrations.get(system) ?? [];
+    return registrations.length == 0 || registrations.some(reg => enabled(reg.filepath, gates.registrations.get(reg) ?? []));
+}
+
+export function inFeatureScope(bevyrlyIndex: BevyrlyIndex, system: string, scope: FeatureScope): boolean {
+    return compiledIn(bevyrlyIndex, system, filepath => cfgEnvironment(bevyrlyIndex, filepath, scope));
+}
+
+// Whether a system can be compiled into a debug build and into a release one, whatever the features.
+export function debugBuilds(bevyrlyIndex: BevyrlyIndex, system: string): { debug: boolean, release: boolean } {
+    return {
+        debug: compiledIn(bevyrlyIndex, system, () => ({ features: undefined, debugAssertions: true })),
+        release: compiledIn(bevyrlyIndex, system, () => ({ features: undefined, debugAssertions: false })),
+    };
+}
+
+// Ranges of `text` only compiled into debug builds or only into release ones: `#[cfg(..)]` items and statements that
+// `debug_assertions` decides, and `if cfg!(debug_assertions) { .. }` blocks (or `!cfg!(..)` ones) with their `else`.
+export function debugRanges(text: string): [number, number, "debug" | "release"][] {
+    let ranges: [number, number, "debug" | "release"][] = [];
+    for (const [start, end, cfg] of parseCfgRanges(text)) {
+        const debug = evalCfg(cfg, { features: undefined, debugAssertions: true });
+        const release = evalCfg(cfg, { features: undefined, debugAssertions: false });
+        if (release === false && debug !== false) ranges.push([start, end, "debug"]);
+        if (debug === false && release !== false) ranges.push([start, end, "release"]);
+    }
+
+    const blanked = blankComments(text);
+    for (const found of blanked.matchAll(/\bif\s+(!?)\s*cfg!\s*\(\s*debug_assertions\s*\)\s*\{/g)) {
+        const open = (found.index ?? 0) + found[0].length - 1;
+        let close = matchingClose(blanked, open);
+        if (close < 0) continue;
+        ranges.push([open, close, found[1] ? "release" : "debug"]);
+
+        // Every `else` (or `else if ..`) block after it only runs in the other build.
+        for (let rest = blanked.slice(close + 1).match(/^\s*else\b[^{;]*\{/); rest; rest = blanked.slice(close + 1).match(/^\s*else\b[^{;]*\{/)) {
+            const elseOpen = close + rest[0].length;
+            close = matchingClose(blanked, elseOpen);
+            if (close < 0) break;
+            ranges.push([elseOpen, close, found[1] ? "debug" : "release"]);
+        }
+    }
+    return ranges;
+}
+
+// What `query` finds in a build with the feature flags it carries, and the query without them: [systems, layout, query].
+// Every entry point that runs a typed query goes through here, so the flags never reach the query parser.
+export function featureScopedGet(bevyrlyIndex: BevyrlyIndex, query: string): [string[], "short" | "long", string] {
+    const [parts, scope] = splitFeatures(query);
+    const [found, long] = bevyrlyIndex.get(parts);
+    return [scope ? found.filter(system => inFeatureScope(bevyrlyIndex, system, scope)) : found, long, parts];
+}
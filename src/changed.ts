@@ -0,0 +1,54 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { git } from './diff';
+import { parseQueryPart, queryParts } from './symbols';
+
+// The files git reports as changed in each workspace folder, as the URIs the index keys its sources by: uncommitted
+// and untracked files for `working-tree`, and everything differing from `<rev>` (uncommitted changes included) for
+// `since:<rev>`. Folders outside git contribute nothing.
+async function changedFiles(key: string): Promise<Set<string>> {
+    const revision = key == "working-tree" ? "HEAD" : key.slice("since:".length);
+    let files: Set<string> = new Set();
+    for (const folder of vscode.workspace.workspaceFolders ?? []) {
+        let listed: string[];
+        try {
+            listed = [
+                ...(await git(folder.uri.fsPath, ["diff", "--name-only", "--relative", revision, "--"])).split("\n"),
+                ...(await git(folder.uri.fsPath, ["ls-files", "--others", "--exclude-standard"])).split("\n"),
+            ];
+        } catch {
+            continue;
+        }
+        for (const file of listed.filter(file => file.length > 0)) {
+            files.add(vscode.Uri.joinPath(folder.uri, file).toString());
+        }
+    }
+    return files;
+}
+
+// Asks git about the `changed:` parts of `query` bevyrly has no answer for yet. Queries only read what was resolved
+// before they ran, so everything running queries from user input awaits this first. Answers last until the next reindex.
+export async function resolveChanged(bevyrlyIndex: BevyrlyIndex, query: string) {
+    let resolved = false;
+    for (const part of queryParts(query.replace(/^:/, ""))) {
+        const [meaning, _, key] = parseQueryPart(part);
+        if (meaning != "changed" || bevyrlyIndex.changed.has(key)) continue;
+        if (key != "working-tree" && !/^since:\S+$/.test(key)) {
+            vscode.window.showWarningMessage("bevyrly: `" + part + "` should be `changed:working-tree` or `changed:since <rev>`");
+            continue;
+        }
+
+        const files = await changedFiles(key);
+        let systems: Set<string> = new Set();
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            if (files.has(loc.src.filepath ?? "")) systems.add(system);
+        }
+        bevyrlyIndex.changed.set(key, systems);
+        resolved = true;
+    }
+
+    // Results cached while a part was unresolved matched nothing.
+    if (resolved) {
+        bevyrlyIndex.invalidateResults(new Set());
+    }
+}
@@ -0,0 +1,113 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { assetSpawns, assetTypeUses, assetUses } from './assets';
+import { systemCategories } from './categories';
+import { systemTags } from './tags';
+import { stateGates, stateWriters } from './states';
+import { parameterPositions } from './params';
+import { gizmoGroups } from './gizmos';
+import { QueryMeaning, parseQueryPart, querySymbolTable } from './symbols';
+
+// Known names for a query part asking for `meaning`, i.e. the symbol table of that kind.
+export function querySymbols(bevyrlyIndex: BevyrlyIndex, meaning: QueryMeaning | undefined): string[] {
+    let maps: Map<string, Set<string>>[];
+    switch (meaning) {
+        case "query": case "mut_query": case "with": case "without":
+            maps = [bevyrlyIndex.query, bevyrlyIndex.mut_query, bevyrlyIndex.with, bevyrlyIndex.without]; break;
+        case "res": case "mut_res": maps = [bevyrlyIndex.res, bevyrlyIndex.mut_res]; break;
+        case "event_read": case "event_write": maps = [bevyrlyIndex.event_read, bevyrlyIndex.event_write]; break;
+        case "schedule": maps = [bevyrlyIndex.schedules()]; break;
+        case "owner": maps = [bevyrlyIndex.owners]; break;
+        case "asset": maps = [assetUses(bevyrlyIndex)]; break;
+        case "asset_spawn": maps = [assetSpawns(bevyrlyIndex)]; break;
+        case "asset_type": maps = [assetTypeUses(bevyrlyIndex)]; break;
+        case "category": maps = [systemCategories(bevyrlyIndex)]; break;
+        case "tag": maps = [systemTags(bevyrlyIndex)]; break;
+        case "state": maps = [stateGates(bevyrlyIndex)]; break;
+        case "next_state": maps = [stateWriters(bevyrlyIndex)]; break;
+        case "changed": maps = [new Map([["working-tree", new Set()], ["since:", new Set()]]), bevyrlyIndex.changed]; break;
+        case "param": case "first": case "last": maps = [parameterPositions(bevyrlyIndex, meaning)]; break;
+        case "gizmo_group": maps = [gizmoGroups(bevyrlyIndex)]; break;
+        case "res_condition": maps = [bevyrlyIndex.conditionResources(), bevyrlyIndex.res, bevyrlyIndex.mut_res]; break;
+        default: maps = [bevyrlyIndex.any]; break;
+    }
+
+    return Array.from(new Set(maps.flatMap(map => Array.from(map.keys())))).filter(key => key !== undefined).sort();
+}
+
+// Completions for the last (space separated) part of `query`, as full replacements of it.
+export function queryCompletions(bevyrlyIndex: BevyrlyIndex, query: string): string[] {
+    const part = query.split(" ").pop() ?? "";
+    const [meaning, symbol, name] = parseQueryPart(part);
+    const typed = name.toLowerCase();
+    return querySymbols(bevyrlyIndex, meaning)
+        .filter(known => known.toLowerCase().includes(typed))
+        .map(known => symbol + known);
+}
+
+// A query prompt that suggests symbols for the part being typed. Picking a suggestion completes the part;
+// accepting the first item runs the query as typed.
+export function promptQuery(bevyrlyIndex: BevyrlyIndex, value: string, placeholder: string): Promise<string | undefined> {
+    return new Promise(resolve => {
+        const input = vscode.window.createQuickPick();
+        input.value = value;
+        input.placeholder = placeholder;
+        input.matchOnDescription = false;
+
+        const update = () => {
+            const head = input.value.slice(0, input.value.length - (input.value.split(" ").pop() ?? "").length);
+            const run = { label: input.value, description: "Run query", alwaysShow: true };
+            const completions = input.value.endsWith(" ") ? [] : queryCompletions(bevyrlyIndex, input.value)
+                .slice(0, 50)
+                .map(completion => ({ label: head + completion, description: completion, alwaysShow: true }));
+            input.items = [run, ...completions];
+        };
+
+        let result: string | undefined = undefined;
+        input.onDidChangeValue(update);
+        input.onDidAccept(() => {
+            const picked = input.activeItems[0];
+            if (picked && picked.description != "Run query") {
+                input.value = picked.label + " ";
+                update();
+                return;
+            }
+
+            result = input.value.trim();
+            input.hide();
+        });
+        input.onDidHide(() => {
+            input.dispose();
+            resolve(result && result.length > 0 ? result : undefined);
+        });
+
+        update();
+        input.show();
+    });
+}
+
+class QueryCompletionProvider implements vscode.CompletionItemProvider {
+    private _bevyrlyIndex: BevyrlyIndex;
+
+    constructor(bevyrlyIndex: BevyrlyIndex) {
+        this._bevyrlyIndex = bevyrlyIndex;
+    }
+
+    provideCompletionItems(document: vscode.TextDocument, position: vscode.Position): vscode.CompletionItem[] {
+        const line = document.lineAt(position.line).text.slice(0, position.character);
+        const part = line.split(" ").pop() ?? "";
+        const range = new vscode.Range(position.translate(0, -part.length), position);
+        return queryCompletions(this._bevyrlyIndex, line).map(completion => {
+            let item = new vscode.CompletionItem(completion, vscode.CompletionItemKind.Reference);
+            item.range = range;
+            return item;
+        });
+    }
+}
+
+export function registerCompletion(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    const triggers = Array.from(new Set(querySymbolTable().map(([symbol, _]) => symbol.slice(-1))));
+    context.subscriptions.push(vscode.languages.registerCompletionItemProvider(
+        { notebookType: 'bevyrly-notebook' }, new QueryCompletionProvider(bevyrlyIndex), ...triggers
+    ));
+}
@@ -0,0 +1,112 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { blankComments, matchingClose, splitTopLevel } from './registrations';
+import { escapeHtml } from './extension';
+
+export interface SystemComplexity {
+    system: string;
+    params: number;
+    queries: number;
+    mutable: number;
+    lines: number;
+}
+
+// Returns the `name: Type` parameters of the first function signature in `source`.
+export function systemParameters(source: string): [string, string][] {
+    const text = blankComments(source);
+    const signature = text.match(/fn\s+\w+\s*(<[^(]*>)?\s*\(/);
+    if (!signature || signature.index === undefined) return [];
+
+    const open = signature.index + signature[0].length - 1;
+    const close = matchingClose(text, open);
+    return splitTopLevel(text.slice(open + 1, close)).map(([param, _]) => {
+        const colon = param.indexOf(":");
+        if (colon < 0) return [param.trim(), ""];
+        return [param.slice(0, colon).replace(/\bmut\b/, "").trim(), param.slice(colon + 1).trim()];
+    });
+}
+
+export function systemComplexity(bevyrlyIndex: BevyrlyIndex): SystemComplexity[] {
+    let result: SystemComplexity[] = [];
+    for (const system of bevyrlyIndex.systems.keys()) {
+        const text = bevyrlyIndex.locs.get(system)?.getText() ?? "";
+        const params = systemParameters(text);
+        const accesses = bevyrlyIndex.accessesOf(system);
+
+        result.push({
+            system,
+            params: params.length,
+            queries: params.filter(([_, type]) => /^Query\s*</.test(type)).length,
+            mutable: (accesses.get("mut_query")?.length ?? 0) + (accesses.get("mut_res")?.length ?? 0) +
+                (accesses.get("event_write")?.length ?? 0),
+            lines: text.split("\n").length,
+        });
+    }
+
+    return result;
+}
+
+const complexityColumns: [keyof SystemComplexity, string][] = [
+    ["params", "Parameters"],
+    ["queries", "Queries"],
+    ["mutable", "Mutable accesses"],
+    ["lines", "Body lines"],
+];
+
+function percentile(values: number[], value: number): number {
+    return values.length <= 1 ? 0 : values.filter(other => other < value).length / (values.length - 1);
+}
+
+const dashboardScript = `
+document.querySelectorAll("th").forEach((header, column) => header.addEventListener("click", () => {
+    const body = document.querySelector("tbody");
+    const rows = Array.from(body.rows);
+    const descending = header.dataset.order != "desc";
+    rows.sort((a, b) => {
+        const x = a.cells[column].dataset.value, y = b.cells[column].dataset.value;
+        const order = isNaN(x) ? x.localeCompare(y) : x - y;
+        return descending ? -order : order;
+    });
+    document.querySelectorAll("th").forEach(other => delete other.dataset.order);
+    header.dataset.order = descending ? "desc" : "asc";
+    rows.forEach(row => body.appendChild(row));
+}));
+`;
+
+// Each metric cell is colored by its percentile among all systems, so outliers stand out in every column.
+export function complexityDashboard(complexity: SystemComplexity[]): string {
+    let values: Map<keyof SystemComplexity, number[]> = new Map();
+    for (const [key, _] of complexityColumns) {
+        values.set(key, complexity.map(entry => entry[key] as number));
+    }
+
+    const rows = complexity
+        .sort((a, b) => b.lines - a.lines)
+        .map(entry => "<tr><td data-value='" + escapeHtml(entry.system) + "'><code>" + escapeHtml(entry.system) + "</code></td>" +
+            complexityColumns.map(([key, _]) => {
+                const value = entry[key] as number;
+                const hue = Math.round(120 * (1 - percentile(values.get(key) ?? [], value)));
+                return "<td data-value='" + value + "' style='background: hsla(" + hue + ", 70%, 45%, 0.6)'>" + value + "</td>";
+            }).join("") + "</tr>");
+
+    return "<!DOCTYPE html><html><head><style>" +
+        "table { border-collapse: collapse; } td, th { border: 1px solid #555; padding: 2px 8px; } " +
+        "td { text-align: right; } th { cursor: pointer; }" +
+        "</style></head><body><h1>Bevyrly system complexity</h1>" +
+        "<table><thead><tr><th>System</th>" + complexityColumns.map(([_, label]) => "<th>" + label + "</th>").join("") +
+        "</tr></thead><tbody>" + rows.join("\n") + "</tbody></table>" +
+        "<script>" + dashboardScript + "</script></body></html>";
+}
+
+export function registerComplexity(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableComplexity = vscode.commands.registerCommand('bevyrly.showComplexity', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const panel = vscode.window.createWebviewPanel('bevyrlyComplexity', 'Bevyrly Complexity', vscode.ViewColumn.One, {
+            enableScripts: true
+        });
+        panel.webview.html = complexityDashboard(systemComplexity(bevyrlyIndex));
+    });
+
+    context.subscriptions.push(disposableComplexity);
+}
@@ -0,0 +1,540 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, startBevyrlyIndexing, whenIndexed } from '.';
+import { Finding } from './diagnostics';
+import { showBevyrlyResultsAsTextDocument } from './text_document';
+import { contentHash } from './cache';
+import { defaultSymbolTable, derivedMeanings, parseQueryPartWith } from './symbols';
+
+export type TomlValue = string | number | boolean | TomlValue[] | TomlTable;
+export interface TomlTable { [key: string]: TomlValue }
+
+// Enough of TOML for `bevyrly.toml`: comments, `[table]` and `[[array]]` headers with dotted keys, and `key = value`
+// pairs whose values are strings, numbers, booleans, (possibly multi-line) arrays of them, or the inline tables
+// `Cargo.toml` uses for dependencies. Throws on anything else.
+export function parseToml(text: string): TomlTable {
+    let root: TomlTable = {};
+    let current = root;
+    let at = 0;
+
+    const fail = (message: string): never => {
+        throw new Error(message + " on line " + (text.slice(0, at).split("\n").length));
+    };
+    const skip = (newlines: boolean) => {
+        while (at < text.length) {
+            if (text[at] == "#") {
+                while (at < text.length && text[at] != "\n") at++;
+            } else if (text[at] == " " || text[at] == "\t" || text[at] == "\r" || (newlines && text[at] == "\n")) {
+                at++;
+            } else {
+                break;
+            }
+        }
+    };
+    const key = (): string[] => {
+        let parts: string[] = [];
+        while (true) {
+            skip(false);
+            if (text[at] == '"' || text[at] == "'") {
+                parts.push(value() as string);
+            } else {
+                const bare = text.slice(at).match(/^[A-Za-z0-9_-]+/);
+                if (!bare) fail("expected a key");
+                parts.push(bare![0]);
+                at += bare![0].length;
+            }
+            skip(false);
+            if (text[at] != ".") return parts;
+            at++;
+        }
+    };
+    const value = (): TomlValue => {
+        skip(false);
+        if (text.startsWith('"', at)) {
+            let result = "";
+            at++;
+            while (at < text.length && text[at] != '"') {
+                if (text[at] == "\n") fail("unterminated string");
+                if (text[at] == "\\") {
+                    at++;
+                    const escaped: { [c: string]: string } = { n: "\n", t: "\t", r: "\r", '"': '"', "\\": "\\" };
+                    if (!(text[at] in escaped)) fail("unsupported escape");
+                    result += escaped[text[at++]];
+                } else {
+                    result += text[at++];
+                }
+            }
+            at++;
+            return result;
+        } else if (text[at] == "'") {
+            const end = text.indexOf("'", at + 1);
+            if (end < 0 || text.slice(at, end).includes("\n")) fail("unterminated string");
+            const result = text.slice(at + 1, end);
+            at = end + 1;
+            return result;
+        } else if (text[at] == "[") {
+            let items: TomlValue[] = [];
+            at++;
+            skip(true);
+            while (text[at] != "]") {
+                items.push(value());
+                skip(true);
+                if (text[at] == ",") {
+                    at++;
+                    skip(true);
+                } else if (text[at] != "]") {
+                    fail("expected , or ] in array");
+                }
+            }
+            at++;
+            return items;
+        } else if (text[at] == "{") {
+            let entries: TomlTable = {};
+            at++;
+            skip(false);
+            while (text[at] != "}") {
+                const path = key();
+                if (text[at] != "=") fail("expected =");
+                at++;
+                const parent = path.length > 1 ? tableIn(entries, path.slice(0, -1), fail) : entries;
+                parent[path[path.length - 1]] = value();
+                skip(false);
+                if (text[at] == ",") {
+                    at++;
+                    skip(false);
+                } else if (text[at] != "}") {
+                    fail("expected , or } in inline table");
+                }
+            }
+            at++;
+            return entries;
+        }
+
+        const scalar = text.slice(at).match(/^(true|false|[+-]?\d[\d_]*(\.\d+)?)/);
+        if (!scalar) fail("expected a value");
+        at += scalar![0].length;
+        return scalar![1] == "true" ? true : scalar![1] == "false" ? false : Number(scalar![1].replace(/_/g, ""));
+    };
+    const table = (path: string[], append: boolean): TomlTable => {
+        let target = root;
+        for (const [i, part] of path.entries()) {
+            const last = i == path.length - 1;
+            if (last && append) {
+                if (!(part in target)) target[part] = [];
+                if (!Array.isArray(target[part])) fail("`" + part + "` is not an array of tables");
+                const entry: TomlTable = {};
+                (target[part] as TomlValue[]).push(entry);
+                return entry;
+            }
+
+            if (!(part in target)) target[part] = {};
+            let next = target[part];
+            if (Array.isArray(next)) next = next[next.length - 1];
+            if (typeof next != "object" || Array.isArray(next)) fail("`" + part + "` is not a table");
+            target = next as TomlTable;
+        }
+        return target;
+    };
+
+    while (true) {
+        skip(true);
+        if (at >= text.length) break;
+
+        if (text[at] == "[") {
+            const append = text[at + 1] == "[";
+            at += append ? 2 : 1;
+            const path = key();
+            if (!text.startsWith(append ? "]]" : "]", at)) fail("unclosed table header");
+            at += append ? 2 : 1;
+            current = table(path, append);
+        } else {
+            const path = key();
+            if (text[at] != "=") fail("expected =");
+            at++;
+            const parent = path.length > 1 ? tableIn(current, path.slice(0, -1), fail) : current;
+            parent[path[path.length - 1]] = value();
+        }
+
+        skip(false);
+        if (at < text.length && text[at] != "\n") fail("expected the end of the line");
+    }
+
+    return root;
+}
+
+function tableIn(table: TomlTable, path: string[], fail: (message: string) => never): TomlTable {
+    for (const part of path) {
+        if (!(part in table)) table[part] = {};
+        if (typeof table[part] != "object" || Array.isArray(table[part])) fail("`" + part + "` is not a table");
+        table = table[part] as TomlTable;
+    }
+    return table;
+}
+
+export type LintLevel = "error" | "warning" | "info" | "hint" | "off";
+
+export interface SavedQuery {
+    name: string;
+    query: string;
+}
+
+// Tags `tag` onto the systems defined in files matching `paths` (globs relative to the workspace folder) and onto those
+// whose names match `systems` (globs such as `*_damage`).
+export interface TagRule {
+    tag: string;
+    paths: string[];
+    systems: string[];
+}
+
+export interface ProjectConfig {
+    // Globs relative to the workspace folder. Without `include`, everything under `bevyrly.sourceFolder` is indexed.
+    include: string[];
+    exclude: string[];
+    // How many calls deep systems take on the accesses of the helper functions they call; 0 turns that off.
+    helperDepth: number;
+    lints: Map<string, LintLevel>;
+    queries: SavedQuery[];
+    // Components that mark entities for bulk despawn (e.g. `GameObject`).
+    cleanupMarkers: string[];
+    // System name prefixes mapped to the state their systems belong to: `menu_ = "MainMenu"`.
+    statePrefixes: Map<string, string>;
+    // What every event type's name ends with (e.g. `Event`), if the project has a convention.
+    eventSuffix?: string;
+    // Marker components mapped to the enum value meaning the same thing: `Player = "Side::Player"`.
+    concepts: Map<string, string>;
+    // The Bevy version of every crate in the folder, unless `crateBevyVersions` or Cargo.lock says otherwise.
+    bevyVersion?: string;
+    // Per-crate overrides from `[bevy.crates]`, keyed by package (or crate directory) name.
+    crateBevyVersions: Map<string, string>;
+    // System parameter types whose accesses bevyrly cannot see (e.g. a `SystemParam` derived by a macro from another
+    // crate), mapped to what they access, written as query parts: `Ctx = ["Commands", "#Time", "$Score"]`.
+    wrappers: Map<string, string[]>;
+    tags: TagRule[];
+}
+
+export function defaultProjectConfig(): ProjectConfig {
+    return { include: [], exclude: [], helperDepth: 1, lints: new Map(), queries: [], cleanupMarkers: [], statePrefixes: new Map(), concepts: new Map(), crateBevyVersions: new Map(), wrappers: new Map(), tags: [] };
+}
+
+function strings(value: TomlValue | undefined, name: string): string[] {
+    if (value === undefined) return [];
+    if (!Array.isArray(value) || value.some(item => typeof item != "string")) {
+        throw new Error("`" + name + "` must be an array of strings");
+    }
+    return value as string[];
+}
+
+export function projectConfigFrom(toml: TomlTable): ProjectConfig {
+    let config = defaultProjectConfig();
+    const index = (toml["index"] ?? {}) as TomlTable;
+    config.include = strings(index["include"], "index.include");
+    config.exclude = strings(index["exclude"], "index.exclude");
+    if (index["helper-depth"] !== undefined) {
+        if (typeof index["helper-depth"] != "number" || !Number.isInteger(index["helper-depth"]) || index["helper-depth"] < 0) {
+            throw new Error("`index.helper-depth` must be a whole number, 0 or more");
+        }
+        config.helperDepth = index["helper-depth"];
+    }
+
+    for (const [code, level] of Object.entries((toml["lints"] ?? {}) as TomlTable)) {
+        if (!["error", "warning", "info", "hint", "off"].includes(level as string)) {
+            throw new Error("`lints." + code + "` must be one of error, warning, info, hint, or off");
+        }
+        config.lints.set(code, level as LintLevel);
+    }
+
+    for (const query of (toml["queries"] ?? []) as TomlTable[]) {
+        if (typeof query["query"] != "string") {
+            throw new Error("every [[queries]] entry needs a `query` string");
+        }
+        config.queries.push({ name: typeof query["name"] == "string" ? query["name"] : query["query"], query: query["query"] });
+    }
+
+    for (const [wrapper, accesses] of Object.entries((toml["wrappers"] ?? {}) as TomlTable)) {
+        const parts = strings(accesses, "wrappers." + wrapper);
+        for (const part of parts) {
+            const meaning = parseQueryPartWith(defaultSymbolTable, part)[0];
+            if (meaning !== undefined && derivedMeanings.includes(meaning)) {
+                throw new Error("`wrappers." + wrapper + "` can only list accesses, not `" + part + "`");
+            }
+        }
+        config.wrappers.set(wrapper, parts);
+    }
+
+    for (const rule of (toml["tags"] ?? []) as TomlTable[]) {
+        if (typeof rule["tag"] != "string" || /\s/.test(rule["tag"])) {
+            throw new Error("every [[tags]] entry needs a `tag` string without spaces");
+        }
+        config.tags.push({ tag: rule["tag"], paths: strings(rule["paths"], "tags.paths"), systems: strings(rule["systems"], "tags.systems") });
+    }
+
+    const conventions = (toml["conventions"] ?? {}) as TomlTable;
+    config.cleanupMarkers = [
+        ...strings(((toml["cleanup"] ?? {}) as TomlTable)["markers"], "cleanup.markers"),
+        ...strings(conventions["cleanup-markers"], "conventions.cleanup-markers"),
+    ];
+    for (const [prefix, state] of Object.entries((conventions["state-prefixes"] ?? {}) as TomlTable)) {
+        if (typeof state != "string") {
+            throw new Error("`conventions.state-prefixes." + prefix + "` must be a state name");
+        }
+        config.statePrefixes.set(prefix, state);
+    }
+    for (const [marker, value] of Object.entries((conventions["concepts"] ?? {}) as TomlTable)) {
+        if (typeof value != "string" || !/^\w+::\w+$/.test(value)) {
+            throw new Error("`conventions.concepts." + marker + "` must be an enum value like \"Side::Player\"");
+        }
+        config.concepts.set(marker, value);
+    }
+    const suffix = conventions["event-suffix"];
+    if (suffix !== undefined && typeof suffix != "string") {
+        throw new Error("`conventions.event-suffix` must be a string");
+    }
+    config.eventSuffix = suffix;
+    const bevy = (toml["bevy"] ?? {}) as TomlTable;
+    if (bevy["version"] !== undefined && parseBevyVersion(bevy["version"]) === undefined) {
+        throw new Error("`bevy.version` must be a version like \"0.14\"");
+    }
+    config.bevyVersion = bevy["version"] as string | undefined;
+    for (const [crate, version] of Object.entries((bevy["crates"] ?? {}) as TomlTable)) {
+        if (parseBevyVersion(version) === undefined) {
+            throw new Error("`bevy.crates." + crate + "` must be a version like \"0.13\"");
+        }
+        config.crateBevyVersions.set(crate, version as string);
+    }
+    return config;
+}
+
+// `0.13`, `0.13.2` and `=0.13` as [major, minor]; patch releases never change what bevyrly recognizes.
+export function parseBevyVersion(version: TomlValue | undefined): [number, number] | undefined {
+    const found = typeof version == "string" ? version.trim().match(/^[=^~]?\s*(\d+)\.(\d+)/) : null;
+    return found ? [Number(found[1]), Number(found[2])] : undefined;
+}
+
+export function bevyVersionBefore(version: string | undefined, major: number, minor: number): boolean {
+    const parsed = parseBevyVersion(version);
+    return parsed !== undefined && (parsed[0] < major || (parsed[0] == major && parsed[1] < minor));
+}
+
+// The Bevy version each package in a Cargo.lock resolved, keyed by package name. A lock holding several versions of
+// bevy names the one each package uses in its dependency (`"bevy 0.13.2"`); crates only depending on the `bevy_*`
+// subcrates get their version from `bevy_ecs` or `bevy_app`.
+export function parseCargoLock(text: string): Map<string, string> {
+    const packages = (parseToml(text)["package"] ?? []) as TomlTable[];
+    const bevyCrates = ["bevy", "bevy_ecs", "bevy_app"];
+    let only: Map<string, string> = new Map();
+    for (const name of bevyCrates) {
+        const versions = packages.filter(entry => entry["name"] == name).map(entry => entry["version"] as string);
+        if (versions.length == 1) only.set(name, versions[0]);
+    }
+
+    let versions: Map<string, string> = new Map();
+    for (const entry of packages) {
+        const dependencies = (entry["dependencies"] ?? []) as string[];
+        for (const name of bevyCrates) {
+            const dependency = dependencies.find(dependency => dependency == name || dependency.startsWith(name + " "));
+            const version = dependency?.split(" ")[1] ?? (dependency ? only.get(name) : undefined);
+            if (version) {
+                versions.set(entry["name"] as string, version);
+                break;
+            }
+        }
+    }
+    return versions;
+}
+
+// Which Bevy a workspace folder's crates are on: `[bevy.crates]` first, then `[bevy] version`, then Cargo.lock.
+// `readFile` reads a path relative to the folder (from disk or from a git revision), and is asked for Cargo.lock and
+// for the Cargo.toml of each crate to learn its package name.
+export class BevyVersions {
+    private _config: ProjectConfig;
+    private _readFile: (path: string) => Promise<string | undefined>;
+    private _lock: Promise<Map<string, string>> | undefined = undefined;
+    private _crates: Map<string, Promise<string | undefined>> = new Map();
+
+    constructor(config: ProjectConfig, readFile: (path: string) => Promise<string | undefined>) {
+        this._config = config;
+        this._readFile = readFile;
+    }
+
+    // The version for a source file at `path` (relative to the folder), which belongs to the crate above its `src/`.
+    async of(path: string): Promise<string | undefined> {
+        const at = ("/" + path).lastIndexOf("/src/");
+        const directory = at > 0 ? path.slice(0, at - 1) : "";
+        if (!this._crates.has(directory)) {
+            this._crates.set(directory, this._resolve(directory));
+        }
+        return this._crates.get(directory);
+    }
+
+    private async _resolve(directory: string): Promise<string | undefined> {
+        let name = directory.split("/").pop() ?? "";
+        try {
+            const manifest = parseToml(await this._readFile((directory ? directory + "/" : "") + "Cargo.toml") ?? "");
+            name = (((manifest["package"] ?? {}) as TomlTable)["name"] as string | undefined) ?? name;
+        } catch {
+            // Without a readable manifest, the directory name stands in for the package name.
+        }
+
+        const override = this._config.crateBevyVersions.get(name) ?? this._config.crateBevyVersions.get(directory.split("/").pop() ?? "");
+        if (override || this._config.bevyVersion) {
+            return override ?? this._config.bevyVersion;
+        }
+
+        if (!this._lock) {
+            this._lock = this._readFile("Cargo.lock").then(text => {
+                try {
+                    return parseCargoLock(text ?? "");
+                } catch {
+                    return new Map();
+                }
+            });
+        }
+        return (await this._lock).get(name);
+    }
+}
+
+// bevyrly.toml -> the hash of the content last warned about, so a broken config warns once, not on every reindex.
+let warnedConfigs: Map<string, string> = new Map();
+
+// Reads `bevyrly.toml` from the root of `folder`. A missing file means defaults; a broken one is reported and ignored.
+export async function loadProjectConfig(folder: vscode.Uri): Promise<ProjectConfig> {
+    const file = vscode.Uri.joinPath(folder, "bevyrly.toml");
+    let text: string;
+    try {
+        text = new TextDecoder().decode(await vscode.workspace.fs.readFile(file));
+    } catch {
+        return defaultProjectConfig();
+    }
+
+    try {
+        const config = projectConfigFrom(parseToml(text));
+        warnedConfigs.delete(file.toString());
+        return config;
+    } catch (error: any) {
+        if (warnedConfigs.get(file.toString()) != contentHash(text)) {
+            warnedConfigs.set(file.toString(), contentHash(text));
+            vscode.window.showWarningMessage("bevyrly ignored " + file.fsPath + ": " + error.message);
+        }
+        return defaultProjectConfig();
+    }
+}
+
+// `**` spans directories, `*` and `?` stay within one, and `{a,b}` picks an alternative.
+export function globToRegExp(glob: string): RegExp {
+    let pattern = "";
+    // How many `{..}` groups are open; `,` separates alternatives only inside one.
+    let depth = 0;
+    for (let i = 0; i < glob.length; i++) {
+        const c = glob[i];
+        if (c == "*" && glob[i + 1] == "*") {
+            pattern += glob[i + 2] == "/" ? "(?:.*/)?" : ".*";
+            i += glob[i + 2] == "/" ? 2 : 1;
+        } else if (c == "*") {
+            pattern += "[^/]*";
+        } else if (c == "?") {
+            pattern += "[^/]";
+        } else if (c == "{") {
+            pattern += "(?:";
+            depth++;
+        } else if (c == "}" && depth > 0) {
+            pattern += ")";
+            depth--;
+        } else if (c == "," && depth > 0) {
+            pattern += "|";
+        } else {
+            pattern += c.replace(/[.+^$(){}|[\]\\]/g, "\\$&");
+        }
+    }
+    return new RegExp("^" + pattern + "$");
+}
+
+export function matchesAny(globs: string[], path: string): boolean {
+    return globs.some(glob => globToRegExp(glob.replace(/^\.?\//, "")).test(path));
+}
+
+// One pattern of a `.gitignore`, scoped to the directory (path) it was found in.
+export interface IgnoreRule {
+    base: string;
+    pattern: RegExp;
+    negated: boolean;
+    directoryOnly: boolean;
+}
+
+// Patterns without an inner slash match at any depth below `base`, the rest are relative to it; `!` re-includes.
+export function parseGitignore(text: string, base: string): IgnoreRule[] {
+    let rules: IgnoreRule[] = [];
+    for (let line of text.split("\n")) {
+        line = line.replace(/\r$/, "").replace(/(?<!\\)\s+$/, "");
+        if (line.length == 0 || line.startsWith("#")) continue;
+
+        const negated = line.startsWith("!");
+        if (negated) line = line.slice(1);
+        line = line.replace(/^\\([#!])/, "$1");
+        const directoryOnly = line.endsWith("/");
+        if (directoryOnly) line = line.slice(0, -1);
+        const anchored = line.includes("/");
+        line = line.replace(/^\//, "");
+        if (line.length == 0) continue;
+
+        rules.push({ base, pattern: globToRegExp(anchored ? line : "**/" + line), negated, directoryOnly });
+    }
+    return rules;
+}
+
+// The last matching rule decides, as in git. Directories are pruned whole, so a file is never re-included from inside
+// an ignored directory.
+export function isIgnored(rules: IgnoreRule[], path: string, directory: boolean): boolean {
+    let ignored = false;
+    for (const rule of rules) {
+        if ((rule.directoryOnly && !directory) || !path.startsWith(rule.base + "/")) continue;
+        if (rule.pattern.test(path.slice(rule.base.length + 1))) {
+            ignored = !rule.negated;
+        }
+    }
+    return ignored;
+}
+
+// Files that say they are generated (`// @generated`, "automatically generated", "do not edit") in their first lines.
+export function isGeneratedSource(text: string): boolean {
+    const head = text.split("\n", 10).join("\n");
+    return /@generated|automatically generated|auto-generated|do not edit/i.test(head);
+}
+
+export function projectConfigOf(bevyrlyIndex: BevyrlyIndex, filepath: string): ProjectConfig {
+    const folder = vscode.workspace.getWorkspaceFolder(vscode.Uri.parse(filepath));
+    return (folder && bevyrlyIndex.configs.get(folder.uri.toString())) || defaultProjectConfig();
+}
+
+// The level configured for a finding's lint in its folder's `bevyrly.toml`, `warning` by default.
+export function lintLevel(bevyrlyIndex: BevyrlyIndex, finding: Finding): LintLevel {
+    return projectConfigOf(bevyrlyIndex, finding.filepath).lints.get(finding.code) ?? "warning";
+}
+
+export function registerProjectConfig(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    const watcher = vscode.workspace.createFileSystemWatcher("**/bevyrly.toml");
+    const reload = () => {
+        if (bevyrlyIndex.hasStarted) {
+            startBevyrlyIndexing(context, bevyrlyIndex);
+        }
+    };
+    watcher.onDidChange(reload);
+    watcher.onDidCreate(reload);
+    watcher.onDidDelete(reload);
+    context.subscriptions.push(watcher);
+
+    let disposableSaved = vscode.commands.registerCommand('bevyrly.runSavedQuery', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+        const saved = Array.from(bevyrlyIndex.configs.values()).flatMap(config => config.queries);
+        if (saved.length == 0) {
+            vscode.window.showInformationMessage("No saved queries; add [[queries]] entries to bevyrly.toml");
+            return;
+        }
+
+        const picked = await vscode.window.showQuickPick(saved.map(query => ({ label: query.name, description: query.query, query })), {
+            title: 'Saved queries',
+        });
+        if (picked) {
+            showBevyrlyResultsAsTextDocument(picked.query.query);
+        }
+    });
+    context.subscriptions.push(disposableSaved);
+}
@@ -0,0 +1,91 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { blankComments, matchingClose } from './registrations';
+
+type AccessKind = "mutable" | "read" | "filter" | "event_read" | "event_write";
+
+const accessPatterns: [AccessKind, RegExp][] = [
+    ["mutable", /&\s*mut\s+([A-Z]\w*)/g],
+    ["mutable", /\b(?:ResMut|NonSendMut)\s*<\s*([A-Z]\w*)/g],
+    ["read", /&\s*(?!mut\b)([A-Z]\w*)/g],
+    ["read", /\b(?:Res|NonSend)\s*<\s*([A-Z]\w*)/g],
+    ["filter", /\b(?:With|Without|Changed|Added)\s*<\s*([A-Z]\w*)/g],
+    ["event_read", /\bEventReader\s*<\s*(?:'\w+\s*,\s*)?([A-Z]\w*)/g],
+    ["event_write", /\bEventWriter\s*<\s*(?:'\w+\s*,\s*)?([A-Z]\w*)/g],
+];
+
+const accessColors: [AccessKind, string][] = [
+    ["mutable", "rgba(230, 80, 80, 0.9)"],
+    ["read", "rgba(80, 200, 120, 0.9)"],
+    ["filter", "rgba(150, 150, 150, 0.9)"],
+    ["event_read", "rgba(90, 160, 240, 0.9)"],
+    ["event_write", "rgba(230, 160, 60, 0.9)"],
+];
+
+// Returns [kind, start, end] offsets of every accessed type in the parameter lists of the given systems.
+export function accessRanges(source: string, systems: Set<string>): [AccessKind, number, number][] {
+    const text = blankComments(source);
+    let ranges: [AccessKind, number, number][] = [];
+    const signatures = /fn\s+(\w+)\s*(<[^(]*>)?\s*\(/g;
+    let signature;
+    while ((signature = signatures.exec(text)) !== null) {
+        if (!systems.has(signature[1])) continue;
+
+        const open = signature.index + signature[0].length - 1;
+        const close = matchingClose(text, open);
+        if (close < 0) continue;
+
+        const params = text.slice(open, close);
+        for (const [kind, pattern] of accessPatterns) {
+            const matcher = new RegExp(pattern.source, "g");
+            let found;
+            while ((found = matcher.exec(params)) !== null) {
+                const end = open + found.index + found[0].length;
+                ranges.push([kind, end - found[1].length, end]);
+            }
+        }
+    }
+
+    return ranges;
+}
+
+export function registerDecorations(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let decorationTypes: Map<AccessKind, vscode.TextEditorDecorationType> = new Map();
+    for (const [kind, color] of accessColors) {
+        decorationTypes.set(kind, vscode.window.createTextEditorDecorationType({
+            borderWidth: '0 0 2px 0',
+            borderStyle: 'solid',
+            borderColor: color,
+        }));
+    }
+
+    const decorate = (editor: vscode.TextEditor | undefined) => {
+        if (!editor || editor.document.languageId != "rust") {
+            return;
+        }
+
+        const enabled = vscode.workspace.getConfiguration('bevyrly').get<boolean>('decorations') ?? true;
+        const ranges = enabled ? accessRanges(editor.document.getText(), new Set(bevyrlyIndex.systems.keys())) : [];
+        for (const [kind, decorationType] of decorationTypes) {
+            editor.setDecorations(decorationType, ranges
+                .filter(([other, _start, _end]) => other == kind)
+                .map(([_, start, end]) => new vscode.Range(editor.document.positionAt(start), editor.document.positionAt(end))));
+        }
+    };
+
+    context.subscriptions.push(...decorationTypes.values());
+    context.subscriptions.push(vscode.window.onDidChangeActiveTextEditor(decorate));
+    context.subscriptions.push(bevyrlyIndex.onDidIndex(() => decorate(vscode.window.activeTextEditor)));
+    context.subscriptions.push(vscode.workspace.onDidChangeTextDocument(event => {
+        if (event.document == vscode.window.activeTextEditor?.document) {
+            decorate(vscode.window.activeTextEditor);
+        }
+    }));
+    context.subscriptions.push(vscode.workspace.onDidChangeConfiguration(event => {
+        if (event.affectsConfiguration('bevyrly.decorations')) {
+            decorate(vscode.window.activeTextEditor);
+        }
+    }));
+
+    decorate(vscode.window.activeTextEditor);
+}
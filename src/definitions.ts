@@ -0,0 +1,60 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { assetFields } from './assets';
+import { parseQueryPart } from './symbols';
+
+// Resolves a system name, a type name with or without its query prefix (`*Transform`, `<DamageEvent`), or an asset
+// collection field (`ImageAssets.smoke`) to where it is declared.
+export function definitionOf(bevyrlyIndex: BevyrlyIndex, name: string): vscode.Location | undefined {
+    const loc = bevyrlyIndex.locs.get(name);
+    if (loc) {
+        return new vscode.Location(vscode.Uri.parse(loc.src.filepath ?? ""), new vscode.Position(loc.src.l(loc[0]), 0));
+    }
+
+    const field = assetFields(bevyrlyIndex).find(field => field.collection + "." + field.field == parseQueryPart(name)[2]);
+    if (field) {
+        return new vscode.Location(vscode.Uri.parse(field.filepath), new vscode.Position(field.line, 0));
+    }
+
+    const type = parseQueryPart(name)[2].replace(/<.*$/, "");
+    const definition = bevyrlyIndex.types.get(type);
+    if (definition) {
+        const [filepath, line] = definition;
+        return new vscode.Location(vscode.Uri.parse(filepath), new vscode.Position(line, 0));
+    }
+
+    return undefined;
+}
+
+// `file:///path/to/file.rs:line`, as used by the links in notebook outputs.
+export function definitionLink(bevyrlyIndex: BevyrlyIndex, name: string): string | undefined {
+    const location = definitionOf(bevyrlyIndex, name);
+    return location ? location.uri.toString() + ":" + (location.range.start.line + 1) : undefined;
+}
+
+class BevyrlyDefinitionProvider implements vscode.DefinitionProvider {
+    private _bevyrlyIndex: BevyrlyIndex;
+
+    constructor(bevyrlyIndex: BevyrlyIndex) {
+        this._bevyrlyIndex = bevyrlyIndex;
+    }
+
+    provideDefinition(document: vscode.TextDocument, position: vscode.Position, _token: vscode.CancellationToken): vscode.Location | undefined {
+        const range = document.getWordRangeAtPosition(position, /[A-Za-z_]\w*\.[A-Za-z_]\w*/) ??
+            document.getWordRangeAtPosition(position, /[A-Za-z_]\w*/);
+        if (!range) {
+            return undefined;
+        }
+
+        return definitionOf(this._bevyrlyIndex, document.getText(range));
+    }
+}
+
+// Query result documents, notebook cells and exported Graphviz files. Plain Rust sources are left to rust-analyzer.
+export function registerDefinitions(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    context.subscriptions.push(vscode.languages.registerDefinitionProvider([
+        { scheme: 'bevyrly' },
+        { scheme: 'vscode-notebook-cell', language: 'rust' },
+        { pattern: '**/*.{dot,gv}' },
+    ], new BevyrlyDefinitionProvider(bevyrlyIndex)));
+}
@@ -0,0 +1,802 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, QueryStorage } from '.';
+import { PluginProvision, SystemRegistration, blankComments, lineAt, matchingClose, parseDerives } from './registrations';
+import { escapeHtml, provenanceOf } from './extension';
+import { LintLevel, lintLevel, projectConfigOf } from './config';
+import { parseSpawns } from './archetypes';
+import { statesOf } from './states';
+import { parseDynamicAccesses } from './dynamic';
+import { systemParameters } from './complexity';
+import { assetExtensions, parseAssetLoads } from './assets';
+import { gizmoGroups } from './gizmos';
+import { RenderLayerSite, renderLayerSites } from './layers';
+import { accessLabels } from './report';
+import { cfgGates, cfgText, crateDirectory, debugBuilds, debugRanges, unreachableCfg } from './cfg';
+
+export type LintCode = "orphan-system" | "missing-event" | "missing-resource" | "event-name" | "state-prefix" | "missing-cleanup-marker"
+    | "cross-crate-event" | "exclusive-system" | "ungated-event-system" | "double-event-handling" | "unawaited-asset"
+    | "missing-gizmo-group" | "render-layer-mismatch" | "duplicate-concept" | "unreachable-cfg"
+    | "debug-only-event";
+
+export interface Finding {
+    code: LintCode;
+    message: string;
+    filepath: string;
+    line: number;
+    // The system or type the finding is about, for quickfixes. Messages quote it first, in backticks.
+    subject: string;
+    // Other places that make up the finding, shown as related information.
+    related?: { message: string, filepath: string, line: number }[];
+    // What the finding was decided on, for `bevyrly: Explain Finding` and tools reading `lint()` from the API. Passes
+    // that don't fill it in get the subject system's accesses and registrations.
+    why?: FindingWhy;
+}
+
+export interface FindingWhy {
+    accesses: { system: string, storage: QueryStorage, name: string }[];
+    registrations: SystemRegistration[];
+    // The `.before`/`.after` edges and sets of those registrations, none of which link the systems of an ordering lint.
+    ordering: { system: string, kind: "before" | "after" | "in_set", other: string }[];
+}
+
+// The accesses of `system` as a finding's `why` lists them, only those to `names` when given.
+function accessesOf(bevyrlyIndex: BevyrlyIndex, system: string, names?: string[]): FindingWhy["accesses"] {
+    return accessLabels.flatMap(([storage, _]) => Array.from(bevyrlyIndex[storage].entries())
+        .filter(([name, systems]) => systems.has(system) && (!names || names.includes(name.replace(/<.*$/, ""))))
+        .map(([name, _]) => ({ system, storage, name })));
+}
+
+function orderingOf(registrations: SystemRegistration[]): FindingWhy["ordering"] {
+    return registrations.flatMap(reg => [
+        ...reg.before.map(other => ({ system: reg.system, kind: "before" as const, other })),
+        ...reg.after.map(other => ({ system: reg.system, kind: "after" as const, other })),
+        ...reg.sets.map(other => ({ system: reg.system, kind: "in_set" as const, other })),
+    ]);
+}
+
+function why(accesses: FindingWhy["accesses"], registrations: SystemRegistration[]): FindingWhy {
+    return { accesses, registrations, ordering: orderingOf(registrations) };
+}
+
+export function subjectOf(diagnostic: vscode.Diagnostic): string | undefined {
+    return diagnostic.message.match(/`([^`]+)`/)?.[1];
+}
+
+// Inputs several lints read are derived once per index and shared, so adding a lint doesn't repeat the scans.
+export function blankedSources(bevyrlyIndex: BevyrlyIndex): Map<string, string> {
+    return bevyrlyIndex.derived("blanked-sources", () => {
+        let blanked: Map<string, string> = new Map();
+        for (const [filepath, text] of bevyrlyIndex.sources) {
+            blanked.set(filepath, blankComments(text));
+        }
+        return blanked;
+    });
+}
+
+// How often each identifier occurs outside comments, across all sources.
+export function identifierCounts(bevyrlyIndex: BevyrlyIndex): Map<string, number> {
+    return bevyrlyIndex.derived("identifier-counts", () => {
+        let counts: Map<string, number> = new Map();
+        for (const text of blankedSources(bevyrlyIndex).values()) {
+            for (const identifier of text.match(/\b[A-Za-z_]\w*\b/g) ?? []) {
+                counts.set(identifier, (counts.get(identifier) ?? 0) + 1);
+            }
+        }
+        return counts;
+    });
+}
+
+// `#[test]` functions and anything behind `#[cfg(test)]`, which only the test harness runs.
+function isTestFunction(bevyrlyIndex: BevyrlyIndex, system: string): boolean {
+    const loc = bevyrlyIndex.locs.get(system);
+    const text = bevyrlyIndex.sources.get(loc?.src.filepath ?? "") ?? "";
+    const attributes = text.slice(0, loc?.[0] ?? 0).match(/((?:#\[[^\]]*\]\s*)*)(?:pub(?:\([^)]*\))?\s+)?$/)?.[1] ?? "";
+    return /#\[\s*(?:\w+::)*test\s*\]/.test(attributes + (loc?.getText() ?? "").split("fn")[0]) ||
+        (cfgGates(bevyrlyIndex).systems.get(system) ?? []).some(cfg => cfg.kind == "option" && cfg.key == "test");
+}
+
+// Systems that are never registered with `add_systems` and never mentioned anywhere but their own definition, so
+// they aren't helpers called from other systems or registered in some way bevyrly doesn't parse. Only functions with
+// system parameters count, and neither `main` nor tests do.
+export function findOrphanSystems(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        if (bevyrlyIndex.registrations.has(system) || /\bself\b/.test(loc.getText().split("{")[0])) continue;
+        if (system == "main" || (bevyrlyIndex.systems.get(system)?.size ?? 0) == 0 || isTestFunction(bevyrlyIndex, system)) continue;
+        if ((identifierCounts(bevyrlyIndex).get(system) ?? 0) > 1) continue;
+
+        findings.push({
+            code: "orphan-system",
+            message: "`" + system + "` looks like a system but is never registered with add_systems",
+            filepath: loc.src.filepath ?? "",
+            line: loc.src.l(loc[0]),
+            subject: system,
+        });
+    }
+
+    return findings;
+}
+
+// `commands.insert_resource(level)` names a variable rather than the type, so follow it back to its `let`.
+function insertedResourceVariables(bevyrlyIndex: BevyrlyIndex): Set<string> {
+    let types: Set<string> = new Set();
+    for (const text of blankedSources(bevyrlyIndex).values()) {
+        const pattern = /\binsert_resource\s*\(\s*([a-z_]\w*)\s*\)/g;
+        let found;
+        while ((found = pattern.exec(text)) !== null) {
+            const binding = new RegExp("\\blet\\s+(?:mut\\s+)?" + found[1] + "\\s*(?::\\s*([A-Z]\\w*)[^=]*)?=\\s*([A-Z]\\w*)", "g");
+            let declared;
+            while ((declared = binding.exec(text.slice(0, found.index))) !== null) {
+                types.add(declared[1] ?? declared[2]);
+            }
+        }
+    }
+
+    return types;
+}
+
+const missingCodes: Record<PluginProvision["kind"], LintCode> = {
+    event: "missing-event",
+    resource: "missing-resource",
+    gizmo_group: "missing-gizmo-group",
+};
+
+// Events, resources and gizmo config groups that systems use but nothing adds to the app. Only types declared in the workspace are
+// checked, since Bevy's own (`Time`, `Assets<T>`, ...) are added by its plugins.
+export function findMissingRegistrations(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    const provided = new Set(bevyrlyIndex.provisions.map(provision => provision.kind + ":" + provision.name.replace(/<.*$/, "")));
+    const inserted = insertedResourceVariables(bevyrlyIndex);
+
+    const check = (kind: PluginProvision["kind"], maps: Map<string, Set<string>>[], message: string) => {
+        const names = new Set(maps.flatMap(map => Array.from(map.keys())).map(name => name.replace(/<.*$/, "")));
+        for (const name of Array.from(names).sort()) {
+            const definition = bevyrlyIndex.types.get(name);
+            if (!definition || provided.has(kind + ":" + name) || (kind == "resource" && inserted.has(name))) continue;
+
+            const [filepath, line] = definition;
+            const users = Array.from(new Set(maps.flatMap(map => Array.from(map.entries()))
+                .filter(([key, _]) => key.replace(/<.*$/, "") == name).flatMap(([_, systems]) => Array.from(systems)))).sort();
+            findings.push({
+                code: missingCodes[kind], message: "`" + name + "` " + message, filepath, line, subject: name,
+                why: why(users.flatMap(system => accessesOf(bevyrlyIndex, system, [name])), []),
+            });
+        }
+    };
+
+    check("event", [bevyrlyIndex.event_read, bevyrlyIndex.event_write], "is used by systems but never added with add_event");
+    check("resource", [bevyrlyIndex.res, bevyrlyIndex.mut_res], "is used by systems but never added with init_resource or insert_resource");
+    check("gizmo_group", [gizmoGroups(bevyrlyIndex)], "is a gizmo config group systems use but never added with init_gizmo_group");
+    return findings;
+}
+
+// Event types whose name breaks the folder's `conventions.event-suffix`.
+export function findEventNames(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [filepath, text] of bevyrlyIndex.sources) {
+        const suffix = projectConfigOf(bevyrlyIndex, filepath).eventSuffix;
+        if (!suffix) continue;
+
+        for (const type of parseDerives(text)) {
+            if (!type.derives.includes("Event") || type.name.endsWith(suffix)) continue;
+            findings.push({
+                code: "event-name",
+                message: "`" + type.name + "` is an event but its name doesn't end with " + suffix,
+                filepath,
+                line: lineAt(text, type.offset),
+                subject: type.name,
+            });
+        }
+    }
+
+    return findings;
+}
+
+function mentionsState(text: string, state: string): boolean {
+    return new RegExp("\\b" + state.replace(/[.*+?^${}()|[\]\\]/g, "\\$&") + "\\b").test(text);
+}
+
+// Systems named with one of `conventions.state-prefixes` that are registered without their state: neither gated by a
+// run condition naming it (`in_state(MainMenu)`), nor by a sub-state or computed state derived from it, nor in one of its
+// `OnEnter`/`OnExit` schedules.
+export function findStatePrefixes(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        const prefixes = projectConfigOf(bevyrlyIndex, loc.src.filepath ?? "").statePrefixes;
+        for (const [prefix, state] of prefixes) {
+            if (!system.startsWith(prefix)) continue;
+            for (const reg of bevyrlyIndex.registrations.get(system) ?? []) {
+                if (mentionsState(reg.schedule, state) || reg.conditions.some(condition => mentionsState(condition, state))) continue;
+                if (statesOf(bevyrlyIndex, reg).some(gate => mentionsState(gate, state))) continue;
+                findings.push({
+                    code: "state-prefix",
+                    message: "`" + system + "` is named like a " + state + " system but is registered in " + reg.schedule + " without in_state(" + state + ")",
+                    filepath: reg.filepath,
+                    line: reg.line,
+                    subject: system,
+                    why: why([], [reg]),
+                });
+            }
+        }
+    }
+
+    return findings;
+}
+
+// Entities spawned on entering a state that carry none of `conventions.cleanup-markers` (nor `StateScoped`), so
+// nothing despawns them when the state is left.
+export function findMissingCleanupMarkers(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        const markers = projectConfigOf(bevyrlyIndex, loc.src.filepath ?? "").cleanupMarkers;
+        if (markers.length == 0) continue;
+        const schedules = bevyrlyIndex.schedulesOf(system).filter(schedule => schedule.startsWith("OnEnter"));
+        if (schedules.length == 0) continue;
+
+        // Spawns in the helpers it calls are its own as far as cleanup goes.
+        for (const spawner of [system, ...bevyrlyIndex.calls.get(system) ?? []]) {
+            const spawnerLoc = bevyrlyIndex.locs.get(spawner);
+            if (!spawnerLoc) continue;
+            for (const site of parseSpawns(spawnerLoc.getText())) {
+                if (site.components.some(component => markers.includes(component) || component == "StateScoped")) continue;
+                findings.push({
+                    code: "missing-cleanup-marker",
+                    message: "`" + system + "` spawns (" + site.components.join(", ") + ")" + (spawner != system ? " through " + spawner : "") +
+                        " in " + schedules[0] + " without a cleanup marker (" + markers.join(", ") + ")",
+                    filepath: spawnerLoc.src.filepath ?? "",
+                    line: spawnerLoc.src.l(spawnerLoc[0] + site.offset),
+                    subject: system,
+                });
+            }
+        }
+    }
+
+    return findings;
+}
+
+// Whether two registrations are explicitly ordered, in either direction: one names the other (or a set the other is
+// in) in `.before`/`.after`, `.chain()` links them, or they share a set, whose configuration then orders them.
+function explicitlyOrdered(a: SystemRegistration, b: SystemRegistration): boolean {
+    const names = (reg: SystemRegistration) => [reg.system, ...reg.sets];
+    return a.sets.some(set => b.sets.includes(set)) ||
+        [...a.before, ...a.after].some(other => names(b).includes(other)) ||
+        [...b.before, ...b.after].some(other => names(a).includes(other));
+}
+
+// Event readers registered in the same schedule as a writer from another crate, with nothing ordering the two. Whether
+// the reader sees an event this frame or the next then depends on the executor, an implicit contract between crates.
+export function findCrossCrateEvents(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [event, readers] of bevyrlyIndex.event_read) {
+        for (const reader of readers) {
+            const readerCrate = provenanceOf(bevyrlyIndex, reader);
+            for (const writer of bevyrlyIndex.event_write.get(event) ?? []) {
+                const writerCrate = provenanceOf(bevyrlyIndex, writer);
+                if (writer == reader || writerCrate == readerCrate) continue;
+
+                const pairs = (bevyrlyIndex.registrations.get(reader) ?? []).flatMap(readerReg =>
+                    (bevyrlyIndex.registrations.get(writer) ?? [])
+                        .filter(writerReg => writerReg.schedule == readerReg.schedule)
+                        .map((writerReg): [SystemRegistration, SystemRegistration] => [readerReg, writerReg]));
+                if (pairs.length == 0 || pairs.some(([readerReg, writerReg]) => explicitlyOrdered(readerReg, writerReg))) continue;
+
+                const [readerReg, writerReg] = pairs[0];
+                findings.push({
+                    code: "cross-crate-event",
+                    message: "`" + reader + "` (" + readerCrate + ") reads " + event + " written by " + writer + " (" + writerCrate +
+                        ") in " + readerReg.schedule + ", with no ordering or shared set between them",
+                    filepath: readerReg.filepath,
+                    line: readerReg.line,
+                    subject: reader,
+                    related: [{ message: writer + " writes " + event + " here", filepath: writerReg.filepath, line: writerReg.line }],
+                    why: why([...accessesOf(bevyrlyIndex, reader, [event]), ...accessesOf(bevyrlyIndex, writer, [event])], [readerReg, writerReg]),
+                });
+            }
+        }
+    }
+
+    return findings;
+}
+
+// What only an exclusive system can do: reach components and resources by id or reflection, insert resources by
+// value, or run schedules and systems. Handing the world to other code is checked per system, by parameter name.
+const exclusiveOnly = new RegExp([
+    "\\b(?:insert_resource|insert_non_send_resource|init_resource|init_non_send_resource|run_schedule|schedule_scope|try_run_schedule)\\b",
+    "\\b(?:run_system\\w*|register_system\\w*|flush|clear_entities|clear_trackers|increment_change_tick)\\b",
+    "\\w+_by_id\\b", "\\b(?:ComponentId|TypeId|AppTypeRegistry|ReflectComponent|ReflectResource|UnsafeWorldCell)\\b",
+    "\\.\\s*(?:as_unsafe_world_cell|cell|archetypes|components|storages|bundles|entities)\\s*\\(",
+].join("|"));
+
+const parallelParams: { [storage: string]: (type: string) => string } = {
+    query: type => "Query<&" + type + ">",
+    mut_query: type => "Query<&mut " + type + ">",
+    res: type => "Res<" + type + ">",
+    mut_res: type => "ResMut<" + type + ">",
+    event_write: type => "EventWriter<" + type + ">",
+};
+
+// Registered systems taking `&mut World` whose body only reads and writes components and resources it names, spawns,
+// despawns and inserts components: work Queries, Res/ResMut and Commands can do in parallel with other systems. An
+// exclusive system runs alone, serializing its schedule around it.
+export function findExclusiveSystems(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        const registration = (bevyrlyIndex.registrations.get(system) ?? [])[0];
+        if (!registration) continue;
+        const text = blankComments(loc.getText());
+        const params = systemParameters(text);
+        const world = params.find(([_, type]) => /^&\s*mut\s+(?:[\w:]*::)?World$/.test(type));
+        if (!world || !/^\w+$/.test(world[0])) continue;
+
+        const body = text.slice(text.indexOf("{", text.indexOf(")")));
+        const passed = new RegExp("\\w\\s*\\(\\s*(?:&\\s*(?:mut\\s+)?\\*?\\s*)?" + world[0] + "\\s*[,)]");
+        if (exclusiveOnly.test(body) || passed.test(body) || !new RegExp("\\b" + world[0] + "\\b").test(body)) continue;
+
+        let suggested: string[] = [];
+        for (const [storage, type] of parseDynamicAccesses(text).get(system) ?? []) {
+            const param = parallelParams[storage]?.(type);
+            if (param && !suggested.includes(param)) suggested.push(param);
+        }
+        if (/\.\s*(?:spawn\w*|despawn\w*|insert|insert_bundle|remove)\s*(?:::\s*<|\()/.test(body)) {
+            suggested.push("Commands");
+        }
+        suggested.push(...params.filter(param => param !== world).map(([_, type]) => type));
+
+        findings.push({
+            code: "exclusive-system",
+            message: "`" + system + "` takes &mut World but does nothing Queries, resources and Commands can't; as an exclusive system it " +
+                "runs alone in " + registration.schedule + (suggested.length > 0 ? ". Consider taking " + suggested.join(", ") + " instead" : ""),
+            filepath: loc.src.filepath ?? "",
+            line: loc.src.l(loc[0]),
+            subject: system,
+            why: why((parseDynamicAccesses(text).get(system) ?? []).map(([storage, name]) => ({ system, storage, name })), bevyrlyIndex.registrations.get(system) ?? []),
+        });
+    }
+
+    return findings;
+}
+
+// Registered systems whose whole body is a loop over their one `EventReader`. They do nothing in frames without events,
+// yet run every frame unless a condition on the event, usually `on_event`, gates them.
+export function findUngatedEventSystems(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        const registrations = bevyrlyIndex.registrations.get(system) ?? [];
+        if (registrations.length == 0) continue;
+        const text = blankComments(loc.getText());
+        const readers = systemParameters(text)
+            .map(([name, type]): [string, string | undefined] =>
+                [name, type.match(/^EventReader\s*<\s*(?:'\w+\s*,\s*)*(?:[\w:]*::)?(\w+)/)?.[1]])
+            .filter(([_, event]) => event !== undefined);
+        if (readers.length != 1) continue;
+
+        const [reader, event] = readers[0];
+        const open = text.indexOf("{", matchingClose(text, text.indexOf("(")));
+        const body = text.slice(open + 1, matchingClose(text, open)).trim();
+        const loop = body.match(new RegExp("^for\\s+[\\s\\S]+?\\s+in\\s+(?:&\\s*(?:mut\\s+)?)?" + reader + "\\s*(?:\\.\\s*(?:read|iter)\\s*\\(\\s*\\))?\\s*\\{"));
+        if (!loop || matchingClose(body, loop[0].length - 1) != body.length - 1) continue;
+
+        for (const reg of registrations) {
+            if (reg.conditions.some(condition => new RegExp("\\bon_event\\b|\\b" + event + "\\b").test(condition))) continue;
+            findings.push({
+                code: "ungated-event-system",
+                message: "`" + system + "` only handles `" + event + "` events but runs every frame in " + reg.schedule +
+                    "; gate it with .run_if(on_event::<" + event + ">())",
+                filepath: reg.filepath,
+                line: reg.line,
+                subject: system,
+                why: why(accessesOf(bevyrlyIndex, system), [reg]),
+            });
+        }
+    }
+
+    return findings;
+}
+
+// Whether two registrations can run in the same frames as far as states go: one isn't gated on a state, or they
+// share one.
+function sameStates(bevyrlyIndex: BevyrlyIndex, a: SystemRegistration, b: SystemRegistration): boolean {
+    const [first, second] = [statesOf(bevyrlyIndex, a), statesOf(bevyrlyIndex, b)];
+    return first.length == 0 || second.length == 0 || first.some(state => second.includes(state));
+}
+
+// Pairs of systems that both read an event and both mutate a component, in the same schedule with nothing ordering
+// them: the `resolve_damage` pattern written twice, so every `DamageEvent` takes its `Health` twice. Registrations gated
+// on states that can't both hold (no state in common) don't run in the same frame and are left alone.
+export function findDoubleEventHandling(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [event, readers] of bevyrlyIndex.event_read) {
+        const sorted = Array.from(readers).sort();
+        for (const [i, first] of sorted.entries()) {
+            for (const second of sorted.slice(i + 1)) {
+                const mutated = Array.from(bevyrlyIndex.mut_query.entries())
+                    .filter(([_, systems]) => systems.has(first) && systems.has(second))
+                    .map(([component, _]) => component)
+                    .sort();
+                if (mutated.length == 0) continue;
+
+                const pairs = (bevyrlyIndex.registrations.get(second) ?? []).flatMap(secondReg =>
+                    (bevyrlyIndex.registrations.get(first) ?? [])
+                        .filter(firstReg => firstReg.schedule == secondReg.schedule)
+                        .filter(firstReg => sameStates(bevyrlyIndex, firstReg, secondReg))
+                        .map((firstReg): [SystemRegistration, SystemRegistration] => [secondReg, firstReg]));
+                if (pairs.length == 0 || pairs.some(([secondReg, firstReg]) => explicitlyOrdered(secondReg, firstReg))) continue;
+
+                const [secondReg, firstReg] = pairs[0];
+                findings.push({
+                    code: "double-event-handling",
+                    message: "`" + second + "` and " + first + " both read " + event + " and mutate " + mutated.join(", ") + " in " +
+                        secondReg.schedule + ", unordered; each event may be applied twice",
+                    filepath: secondReg.filepath,
+                    line: secondReg.line,
+                    subject: second,
+                    related: [{ message: first + " also handles " + event + " here", filepath: firstReg.filepath, line: firstReg.line }],
+                    why: why([second, first].flatMap(system => accessesOf(bevyrlyIndex, system, [event, ...mutated])), [secondReg, firstReg]),
+                });
+            }
+        }
+    }
+
+    return findings;
+}
+
+
+// What waiting for a load looks like: a `LoadState` check, an `AssetEvent` reader, or a bevy_asset_loader loading state.
+const loadCheck = /\b(?:LoadState|RecursiveDependencyLoadState|load_state|get_load_state|is_loaded\w*|AssetEvent|LoadingState)\b/;
+
+// Systems that unwrap `Assets<T>::get(..)` for an asset type whose `asset_server.load(..)` is requested under the same
+// states, with no load check in the system or its run conditions. A simplified, static version of the "asset not yet
+// loaded" panic: nothing makes the asset exist by the time the consumer first runs.
+export function findUnawaitedAssets(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    const extensions = assetExtensions(bevyrlyIndex);
+    let loads: Map<string, [string, number][]> = new Map();
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        for (const [type, _, offset] of parseAssetLoads(loc.getText(), extensions)) {
+            if (type) loads.set(type, [...loads.get(type) ?? [], [system, loc.src.l(loc[0] + offset)]]);
+        }
+    }
+
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        const text = blankComments(loc.getText());
+        if (loadCheck.test(text)) continue;
+        for (const [name, param] of systemParameters(text)) {
+            const type = param.match(/^(?:Res|ResMut)\s*<\s*(?:'\w+\s*,\s*)?Assets\s*<\s*(?:[\w:]*::)?(\w+)\s*>\s*>$/)?.[1];
+            if (!type || !loads.has(type)) continue;
+            if (!new RegExp("\\b" + name + "\\s*\\.\\s*get(?:_mut)?\\s*\\([^;]*?\\)\\s*\\.\\s*(?:unwrap|expect)\\b").test(text)) continue;
+
+            for (const reg of bevyrlyIndex.registrations.get(system) ?? []) {
+                if (reg.conditions.some(condition => loadCheck.test(condition))) continue;
+                const loader = (loads.get(type) ?? []).find(([loader, _]) => loader != system &&
+                    (bevyrlyIndex.registrations.get(loader) ?? []).some(loaderReg => sameStates(bevyrlyIndex, loaderReg, reg)));
+                if (!loader) continue;
+
+                findings.push({
+                    code: "unawaited-asset",
+                    message: "`" + system + "` unwraps Assets<" + type + ">::get but " + loader[0] + " loads " + type +
+                        " under the same states, and nothing checks its LoadState first",
+                    filepath: reg.filepath,
+                    line: reg.line,
+                    subject: system,
+                    related: [{ message: loader[0] + " loads " + type + " here", filepath: bevyrlyIndex.locs.get(loader[0])?.src.filepath ?? "", line: loader[1] }],
+                    why: why(accessesOf(bevyrlyIndex, system, ["Assets", type]),
+                        [reg, ...(bevyrlyIndex.registrations.get(loader[0]) ?? []).filter(loaderReg => sameStates(bevyrlyIndex, loaderReg, reg))]),
+                });
+            }
+        }
+    }
+
+    return findings;
+}
+
+// Render layers that entities are spawned on but no camera renders, cameras rendering layers nothing is spawned on, and
+// children spawned on other layers than their parent: usually a UI element left on layer 0, or a camera's
+// `RenderLayers` forgotten. Findings about a whole layer sit on its first spawn.
+export function findRenderLayerMismatches(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    const sites = renderLayerSites(bevyrlyIndex);
+    if (!sites.some(site => site.camera)) return findings;
+
+    const onLayer = (camera: boolean, layer: number) => sites.filter(site => site.camera == camera && (site.all || site.layers.includes(layer)));
+    const layers = Array.from(new Set(sites.flatMap(site => site.layers))).sort((a, b) => a - b);
+    for (const layer of layers) {
+        const [cameras, entities] = [onLayer(true, layer), onLayer(false, layer)];
+        if (cameras.length > 0 && entities.length > 0) continue;
+
+        const [first, ...rest] = cameras.length > 0 ? cameras : entities;
+        findings.push({
+            code: "render-layer-mismatch",
+            message: cameras.length > 0
+                ? "`" + first.system + "` spawns a camera rendering layer " + layer + ", but nothing rendered is spawned on it"
+                : "`" + first.system + "` spawns entities on render layer " + layer + ", but no camera renders it" +
+                    (rest.length > 0 ? " (" + rest.length + " more spawn" + (rest.length == 1 ? "" : "s") + ")" : ""),
+            filepath: first.filepath,
+            line: first.line,
+            subject: first.system,
+            related: rest.map(site => ({ message: site.system + " also spawns on layer " + layer, filepath: site.filepath, line: site.line })),
+        });
+    }
+
+    // `RenderLayers` isn't inherited, so children spawned on other layers than their parent render apart from it.
+    for (const site of sites.filter(site => site.parent && !site.camera && !site.all && !site.parent.all)) {
+        const parent = site.parent as RenderLayerSite;
+        if (site.layers.join(",") == parent.layers.join(",")) continue;
+        findings.push({
+            code: "render-layer-mismatch",
+            message: "`" + site.system + "` spawns a child on render layer " + site.layers.join(", ") + " under a parent on layer " +
+                parent.layers.join(", ") + "; RenderLayers isn't inherited",
+            filepath: site.filepath,
+            line: site.line,
+            subject: site.system,
+            related: [{ message: "the parent is spawned here", filepath: parent.filepath, line: parent.line }],
+        });
+    }
+
+    return findings;
+}
+
+// Whether a system body compares against or matches on the enum value, rather than just assigning it.
+function checksValue(text: string, value: string): boolean {
+    const path = "(?:\\w+::)*" + value.replace("::", "\\s*::\\s*") + "\\b";
+    return new RegExp("(?:[!=]=\\s*&?\\s*" + path + "|" + path + "\\s*(?:[!=]=|=>|\\|)|\\blet\\s+" + path + "|matches!\\s*\\([^;]*?" + path + ")").test(text);
+}
+
+// With `conventions.concepts`, systems telling a concept apart inconsistently: those checking both the marker component
+// and the enum value meaning the same thing, and those using only the mechanism fewer systems use. An entity carrying
+// just one of the two slips past the systems checking the other.
+export function findDuplicateConcepts(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    let uses: Map<string, { marker: string[], value: string[], both: string[] }> = new Map();
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        const text = blankComments(loc.getText());
+        for (const [marker, value] of projectConfigOf(bevyrlyIndex, loc.src.filepath ?? "").concepts) {
+            const byMarker = [bevyrlyIndex.query, bevyrlyIndex.mut_query, bevyrlyIndex.with, bevyrlyIndex.without]
+                .some(map => map.get(marker)?.has(system));
+            const byValue = checksValue(text, value);
+            if (!byMarker && !byValue) continue;
+
+            const concept = uses.get(marker + "=" + value) ?? { marker: [], value: [], both: [] };
+            (byMarker && byValue ? concept.both : byMarker ? concept.marker : concept.value).push(system);
+            uses.set(marker + "=" + value, concept);
+        }
+    }
+
+    for (const [key, concept] of uses) {
+        const [marker, value] = key.split("=");
+        const finding = (system: string, message: string): Finding => {
+            const loc = bevyrlyIndex.locs.get(system);
+            return {
+                code: "duplicate-concept", message: "`" + system + "` " + message, filepath: loc?.src.filepath ?? "", line: loc ? loc.src.l(loc[0]) : 0, subject: system,
+                why: why(accessesOf(bevyrlyIndex, system, [marker, value.split("::")[0]]), bevyrlyIndex.registrations.get(system) ?? []),
+            };
+        };
+        for (const system of concept.both) {
+            findings.push(finding(system, "checks both the " + marker + " marker and " + value + ", which mean the same thing"));
+        }
+        if (concept.marker.length > concept.value.length) {
+            concept.value.forEach(system => findings.push(finding(system, "checks " + value + " where " + concept.marker.length +
+                " other systems use the " + marker + " marker")));
+        } else if (concept.value.length > concept.marker.length) {
+            concept.marker.forEach(system => findings.push(finding(system, "uses the " + marker + " marker where " + concept.value.length +
+                " other systems check " + value)));
+        }
+    }
+
+    return findings;
+}
+
+// Registrations behind a `#[cfg(..)]` no build of their crate enables, which quietly leave those systems out of every
+// build. Crates without a readable Cargo.toml are skipped, as their features are unknown.
+export function findUnreachableRegistrations(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [reg, cfgs] of cfgGates(bevyrlyIndex).registrations) {
+        const crate = bevyrlyIndex.crateFeatures.get(crateDirectory(reg.filepath));
+        if (!crate) continue;
+        for (const cfg of cfgs) {
+            const reason = unreachableCfg(cfg, crate);
+            if (!reason) continue;
+            findings.push({
+                code: "unreachable-cfg",
+                message: "`" + reg.system + "` is registered behind `#[cfg(" + cfgText(cfg) + ")]`, which no build enables: " + reason,
+                filepath: reg.filepath,
+                line: reg.line,
+                subject: reg.system,
+                why: why(accessesOf(bevyrlyIndex, reg.system), [reg]),
+            });
+            break;
+        }
+    }
+
+    return findings;
+}
+
+// Which builds `system` can write `event` in: those it's compiled into, narrowed by where its sends through an
+// `EventWriter<E>` parameter sit. A system whose every send is under `#[cfg(debug_assertions)]` or in an
+// `if cfg!(debug_assertions)` block only writes in debug builds.
+function eventWriteBuilds(bevyrlyIndex: BevyrlyIndex, system: string, event: string): { debug: boolean, release: boolean } {
+    const builds = debugBuilds(bevyrlyIndex, system);
+    const text = blankComments(bevyrlyIndex.locs.get(system)?.getText() ?? "");
+    const writers = Array.from(text.matchAll(new RegExp("\\b(\\w+)\\s*:\\s*(?:mut\\s+)?EventWriter\\s*<\\s*(?:'\\w+\\s*,\\s*)?" + event.replace(/\W/g, "\\$&") + "\\s*>", "g")))
+        .map(found => found[1]);
+    const sends = writers.flatMap(writer => Array.from(text.matchAll(new RegExp("\\b" + writer + "\\s*\\.\\s*(?:send|write)\\w*\\s*\\(", "g"))))
+        .map(found => found.index ?? 0);
+    if (sends.length == 0) return builds;
+
+    const ranges = debugRanges(text);
+    const only = (at: number) => ranges.find(([start, end]) => start <= at && at <= end)?.[2];
+    return {
+        debug: builds.debug && sends.some(at => only(at) != "release"),
+        release: builds.release && sends.some(at => only(at) != "debug"),
+    };
+}
+
+// Events only written in debug builds but read by systems that are in release builds too, or the other way around:
+// whatever the readers do then only happens in one kind of build.
+export function findDebugOnlyEvents(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    let findings: Finding[] = [];
+    for (const [event, writers] of bevyrlyIndex.event_write) {
+        const written = Array.from(writers).map(writer => eventWriteBuilds(bevyrlyIndex, writer, event));
+        if (written.length == 0 || !written.some(builds => builds.debug || builds.release)) continue;
+        const missing = !written.some(builds => builds.release) ? "release" : !written.some(builds => builds.debug) ? "debug" : undefined;
+        if (!missing) continue;
+
+        for (const reader of bevyrlyIndex.event_read.get(event) ?? []) {
+            if (writers.has(reader) || !debugBuilds(bevyrlyIndex, reader)[missing]) continue;
+            const loc = bevyrlyIndex.locs.get(reader);
+            if (!loc) continue;
+            findings.push({
+                code: "debug-only-event",
+                message: "`" + reader + "` reads " + event + ", which is only written in " + (missing == "release" ? "debug" : "release") +
+                    " builds (by " + Array.from(writers).sort().join(", ") + "); in " + missing + " builds it never sees one",
+                filepath: loc.src.filepath ?? "",
+                line: loc.src.l(loc[0]),
+                subject: reader,
+                why: why([reader, ...writers].flatMap(system => accessesOf(bevyrlyIndex, system, [event])), [reader, ...writers].flatMap(system => bevyrlyIndex.registrations.get(system) ?? [])),
+            });
+        }
+    }
+
+    return findings;
+}
+
+// Every lint is an independent pass over the index; each one's findings are cached on their own and merged in order.
+const lintPasses: [string, (bevyrlyIndex: BevyrlyIndex) => Finding[]][] = [
+    ["orphan-systems", findOrphanSystems],
+    ["missing-registrations", findMissingRegistrations],
+    ["event-names", findEventNames],
+    ["state-prefixes", findStatePrefixes],
+    ["cleanup-markers", findMissingCleanupMarkers],
+    ["cross-crate-events", findCrossCrateEvents],
+    ["exclusive-systems", findExclusiveSystems],
+    ["ungated-event-systems", findUngatedEventSystems],
+    ["double-event-handling", findDoubleEventHandling],
+    ["unawaited-assets", findUnawaitedAssets],
+    ["render-layers", findRenderLayerMismatches],
+    ["duplicate-concepts", findDuplicateConcepts],
+    ["unreachable-cfgs", findUnreachableRegistrations],
+    ["debug-only-events", findDebugOnlyEvents],
+];
+
+export function findAll(bevyrlyIndex: BevyrlyIndex): Finding[] {
+    return bevyrlyIndex.derived("lints", () =>
+        lintPasses.flatMap(([name, pass]) => bevyrlyIndex.derived("lint:" + name, () => pass(bevyrlyIndex)))
+            .map(finding => finding.why ? finding : {
+                ...finding,
+                why: why(accessesOf(bevyrlyIndex, finding.subject), bevyrlyIndex.registrations.get(finding.subject) ?? []),
+            }));
+}
+
+const severities: { [level in Exclude<LintLevel, "off">]: vscode.DiagnosticSeverity } = {
+    error: vscode.DiagnosticSeverity.Error,
+    warning: vscode.DiagnosticSeverity.Warning,
+    info: vscode.DiagnosticSeverity.Information,
+    hint: vscode.DiagnosticSeverity.Hint,
+};
+
+const sarifLevels: { [level in Exclude<LintLevel, "off">]: string } = {
+    error: "error",
+    warning: "warning",
+    info: "note",
+    hint: "note",
+};
+
+// The findings as a SARIF 2.1.0 log, for GitHub code scanning and other tools that show lints inline on pull requests.
+// Levels follow bevyrly.toml's `[lints]` like the diagnostics do, and paths are relative to the workspace.
+export function sarifLog(bevyrlyIndex: BevyrlyIndex): object {
+    const location = (filepath: string, line: number) => ({
+        physicalLocation: {
+            artifactLocation: { uri: vscode.workspace.asRelativePath(vscode.Uri.parse(filepath), false), uriBaseId: "%SRCROOT%" },
+            region: { startLine: line + 1 },
+        },
+    });
+    const findings = findAll(bevyrlyIndex).map(finding => [finding, lintLevel(bevyrlyIndex, finding)] as const)
+        .filter(([_, level]) => level != "off");
+    const rules = Array.from(new Set(findings.map(([finding, _]) => finding.code))).sort();
+
+    return {
+        $schema: "https://json.schemastore.org/sarif-2.1.0.json",
+        version: "2.1.0",
+        runs: [{
+            tool: { driver: { name: "bevyrly", informationUri: "https://github.com/devlike-code/bevyrly", rules: rules.map(code => ({ id: code })) } },
+            results: findings.map(([finding, level]) => ({
+                ruleId: finding.code,
+                ruleIndex: rules.indexOf(finding.code),
+                level: sarifLevels[level as Exclude<LintLevel, "off">],
+                message: { text: finding.message },
+                locations: [location(finding.filepath, finding.line)],
+                relatedLocations: (finding.related ?? []).map((related, id) => ({
+                    id, ...location(related.filepath, related.line), message: { text: related.message },
+                })),
+            })),
+        }],
+    };
+}
+
+// A finding's `why`, with each part in its own expandable section.
+export function explanationView(finding: Finding): string {
+    const why = finding.why ?? { accesses: [], registrations: [], ordering: [] };
+    const section = (title: string, items: string[]) => "<details open><summary>" + title + " (" + items.length + ")</summary><ul>" +
+        items.map(item => "<li>" + item + "</li>").join("") + "</ul></details>";
+    const code = (text: string) => "<code>" + escapeHtml(text) + "</code>";
+
+    return "<!DOCTYPE html><html><body><h1>" + escapeHtml(finding.code) + "</h1><p>" + escapeHtml(finding.message) + "</p>" +
+        section("Accesses", why.accesses.map(access => code(access.system) + " " + escapeHtml(access.storage) + " " + code(access.name))) +
+        section("Registrations", why.registrations.map(reg => code(reg.system) + " in " + code(reg.schedule) +
+            (reg.conditions.length > 0 ? " if " + reg.conditions.map(code).join(", ") : "") +
+            " <small>" + escapeHtml(reg.filepath) + ":" + (reg.line + 1) + "</small>")) +
+        section("Ordering", why.ordering.map(edge => code(edge.system) + " " + edge.kind + " " + code(edge.other))) +
+        "<details><summary>JSON</summary><pre>" + escapeHtml(JSON.stringify(why, null, 2)) + "</pre></details></body></html>";
+}
+
+export function registerDiagnostics(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    const collection = vscode.languages.createDiagnosticCollection('bevyrly');
+    context.subscriptions.push(collection);
+
+    // Run from the quickfix every bevyrly diagnostic has, with the diagnostic's file, line and code.
+    context.subscriptions.push(vscode.commands.registerCommand('bevyrly.explainFinding', (filepath: string, line: number, code: string) => {
+        const finding = findAll(bevyrlyIndex).find(finding => vscode.Uri.parse(finding.filepath).toString() == filepath && finding.line == line && finding.code == code);
+        if (!finding) {
+            vscode.window.showInformationMessage("bevyrly: That finding no longer applies");
+            return;
+        }
+
+        const panel = vscode.window.createWebviewPanel('bevyrlyFinding', 'Bevyrly: ' + finding.code, vscode.ViewColumn.Beside, {});
+        panel.webview.html = explanationView(finding);
+    }));
+
+    const refresh = () => {
+        collection.clear();
+        if (!(vscode.workspace.getConfiguration('bevyrly').get<boolean>('diagnostics') ?? true)) {
+            return;
+        }
+
+        let byFile: Map<string, vscode.Diagnostic[]> = new Map();
+        for (const finding of findAll(bevyrlyIndex)) {
+            const level = lintLevel(bevyrlyIndex, finding);
+            if (level == "off") continue;
+
+            let diagnostic = new vscode.Diagnostic(new vscode.Range(finding.line, 0, finding.line, Number.MAX_SAFE_INTEGER),
+                finding.message, severities[level]);
+            diagnostic.source = "bevyrly";
+            diagnostic.code = finding.code;
+            diagnostic.relatedInformation = finding.related?.map(related => new vscode.DiagnosticRelatedInformation(
+                new vscode.Location(vscode.Uri.parse(related.filepath), new vscode.Position(related.line, 0)), related.message));
+
+            if (!byFile.has(finding.filepath)) {
+                byFile.set(finding.filepath, []);
+            }
+            byFile.get(finding.filepath)?.push(diagnostic);
+        }
+
+        for (const [filepath, diagnostics] of byFile) {
+            collection.set(vscode.Uri.parse(filepath), diagnostics);
+        }
+    };
+
+    context.subscriptions.push(bevyrlyIndex.onDidIndex(refresh));
+    context.subscriptions.push(vscode.workspace.onDidChangeConfiguration(event => {
+        if (event.affectsConfiguration('bevyrly.diagnostics')) {
+            refresh();
+        }
+    }));
+}
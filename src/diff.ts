@@ -0,0 +1,138 @@
+import * as vscode from 'vscode';
+import { execFile } from 'child_process';
+import { BevyrlyIndex, indexSourceFile, whenIndexed } from '.';
+import { accessLabels } from './report';
+import { findAmbiguities } from './ambiguity';
+import { BevyVersions, defaultProjectConfig, parseToml, projectConfigFrom } from './config';
+
+export function git(cwd: string, args: string[]): Promise<string> {
+    return new Promise((resolve, reject) => {
+        execFile("git", args, { cwd, maxBuffer: 64 * 1024 * 1024 }, (error, stdout) => {
+            if (error) {
+                reject(error);
+            } else {
+                resolve(stdout);
+            }
+        });
+    });
+}
+
+export async function indexRevision(folder: vscode.Uri, src: string, revision: string): Promise<BevyrlyIndex> {
+    let bevyrlyIndex = new BevyrlyIndex();
+    const files = (await git(folder.fsPath, ["ls-tree", "-r", "--name-only", revision, "--", src]))
+        .split("\n")
+        .filter(file => file.endsWith(".rs"));
+
+    // Wrapper types and Bevy versions are as of the revision too, so a diff shows what that revision would have had.
+    let config = defaultProjectConfig();
+    try {
+        config = projectConfigFrom(parseToml(await git(folder.fsPath, ["show", revision + ":bevyrly.toml"])));
+    } catch {
+        // No (valid) bevyrly.toml in that revision.
+    }
+    const versions = new BevyVersions(config, path => git(folder.fsPath, ["show", revision + ":" + path]).catch(() => undefined));
+
+    for (const file of files) {
+        const text = await git(folder.fsPath, ["show", revision + ":" + file]);
+        indexSourceFile(bevyrlyIndex, text, vscode.Uri.joinPath(folder, file).toString(), undefined, await versions.of(file));
+    }
+    bevyrlyIndex.applyWrappers(folder.toString(), config);
+    bevyrlyIndex.applyHelperCalls(folder.toString(), config.helperDepth);
+
+    bevyrlyIndex.isInitialized = true;
+    return bevyrlyIndex;
+}
+
+function eventsOf(bevyrlyIndex: BevyrlyIndex): Set<string> {
+    return new Set([...bevyrlyIndex.event_read.keys(), ...bevyrlyIndex.event_write.keys()]);
+}
+
+export function diffIndices(base: BevyrlyIndex, head: BevyrlyIndex, title: string): string {
+    let lines = ["# Bevyrly diff: " + title, ""];
+    const section = (heading: string, items: string[]) => {
+        if (items.length > 0) {
+            lines.push("## " + heading, "", ...items.map(item => "- " + item), "");
+        }
+    };
+
+    const baseSystems = new Set(base.systems.keys());
+    const headSystems = new Set(head.systems.keys());
+    section("Added systems", Array.from(headSystems).filter(system => !baseSystems.has(system)).sort().map(system => "`" + system + "`"));
+    section("Removed systems", Array.from(baseSystems).filter(system => !headSystems.has(system)).sort().map(system => "`" + system + "`"));
+
+    let changed: string[] = [];
+    for (const system of Array.from(headSystems).filter(system => baseSystems.has(system)).sort()) {
+        const before = base.accessesOf(system);
+        const after = head.accessesOf(system);
+        let changes: string[] = [];
+        for (const [storage, label] of accessLabels) {
+            const was = before.get(storage) ?? [];
+            const now = after.get(storage) ?? [];
+            const added = now.filter(key => !was.includes(key)).map(key => "+`" + key + "`");
+            const removed = was.filter(key => !now.includes(key)).map(key => "-`" + key + "`");
+            if (added.length + removed.length > 0) {
+                changes.push(label.toLowerCase() + " " + [...added, ...removed].join(" "));
+            }
+        }
+        if (changes.length > 0) {
+            changed.push("`" + system + "`: " + changes.join("; "));
+        }
+    }
+    section("Changed accesses", changed);
+
+    const baseEvents = eventsOf(base);
+    const headEvents = eventsOf(head);
+    section("New events", Array.from(headEvents).filter(event => !baseEvents.has(event)).sort().map(event => "`" + event + "`"));
+    section("Removed events", Array.from(baseEvents).filter(event => !headEvents.has(event)).sort().map(event => "`" + event + "`"));
+
+    const key = (amb: { schedule: string, first: string, second: string }) => amb.schedule + "|" + amb.first + "|" + amb.second;
+    const baseAmbiguities = new Set(findAmbiguities(base).map(key));
+    const headAmbiguities = findAmbiguities(head);
+    section("New ambiguities", headAmbiguities
+        .filter(amb => !baseAmbiguities.has(key(amb)))
+        .map(amb => "`" + amb.first + "` and `" + amb.second + "` in `" + amb.schedule + "` on " + amb.conflicts.join(", ")));
+
+    if (lines.length == 2) {
+        lines.push("No ECS changes.");
+    }
+
+    return lines.join("\n");
+}
+
+export function registerDiff(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableDiff = vscode.commands.registerCommand('bevyrly.diffRevisions', async () => {
+        const folder = vscode.workspace.workspaceFolders?.[0]?.uri;
+        if (!folder) {
+            return;
+        }
+
+        const range = await vscode.window.showInputBox({
+            value: 'HEAD',
+            prompt: 'Revision to compare against the working tree, or `base..head` to compare two revisions'
+        });
+
+        if (!range) {
+            return;
+        }
+
+        const src = vscode.workspace.getConfiguration('bevyrly', folder).get<string>('sourceFolder') ?? "src";
+        const [baseRevision, headRevision] = range.split("..");
+        try {
+            const base = await indexRevision(folder, src, baseRevision);
+            let head = bevyrlyIndex;
+            if (headRevision) {
+                head = await indexRevision(folder, src, headRevision);
+            } else {
+                await whenIndexed(context, bevyrlyIndex);
+            }
+
+            const content = diffIndices(base, head, headRevision ? range : baseRevision + " vs working tree");
+            const doc = await vscode.workspace.openTextDocument({ content, language: 'markdown' });
+            await vscode.window.showTextDocument(doc, { preview: false });
+        } catch (error: any) {
+            vscode.window.showErrorMessage("bevyrly diff failed: " + error.message);
+        }
+    });
+
+    context.subscriptions.push(disposableDiff);
+}
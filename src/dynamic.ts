@@ -0,0 +1,82 @@
+import { QueryStorage } from '.';
+import { blankComments, matchingClose, splitTopLevel } from './registrations';
+
+// Parameters that give a system dynamic access to components and resources, which its signature does not show.
+const dynamicParams = /(?:&\s*(?:mut\s+)?World\b|\b(?:EntityRef|EntityMut|EntityWorldMut|FilteredEntityRef|FilteredEntityMut)\b|\bDeferredWorld\b)/;
+
+const turbofish = (method: string) => new RegExp("\\.\\s*(" + method + ")\\s*::\\s*<", "g");
+
+// The turbofish argument of a call, from the offset of its `<`.
+function typeArgument(text: string, open: number): string {
+    let depth = 0;
+    for (let i = open; i < text.length; i++) {
+        if (text[i] == "<") depth++;
+        else if (text[i] == ">" && text[i - 1] != "-" && --depth == 0) return text.slice(open + 1, i);
+    }
+    return "";
+}
+
+function outerType(type: string): string {
+    return type.trim().match(/^(?:[\w:]*::)?(\w+)/)?.[1] ?? "";
+}
+
+// What `world.query::<(&A, &mut B)>()` and `world.query_filtered::<&A, With<B>>()` access.
+export function queryStateAccesses(argument: string): [QueryStorage, string][] {
+    let accesses: [QueryStorage, string][] = [];
+    const items = splitTopLevel(argument).flatMap(([item, _]) => {
+        const tuple = item.trim().match(/^\(([\s\S]*)\)$/);
+        return tuple ? splitTopLevel(tuple[1]).map(([sub, _]) => sub) : [item];
+    });
+    for (const item of items) {
+        const trimmed = item.trim();
+        const filter = trimmed.match(/^(With|Without)\s*<([\s\S]*)>$/);
+        if (filter) {
+            splitTopLevel(filter[2].replace(/^\(|\)$/g, "")).forEach(([type, _]) =>
+                accesses.push([filter[1] == "With" ? "with" : "without", outerType(type)]));
+        } else if (trimmed.startsWith("&")) {
+            const mutable = /^&\s*mut\b/.test(trimmed);
+            accesses.push([mutable ? "mut_query" : "query", outerType(trimmed.replace(/^&\s*(?:mut\b)?/, ""))]);
+        }
+    }
+    return accesses.filter(([_, type]) => /^[A-Z]/.test(type));
+}
+
+// Component and resource accesses made through the world or an entity in the bodies of functions taking `&mut World`,
+// `&World`, `DeferredWorld` or an `EntityRef`/`EntityMut`: `entity.get_mut::<Health>()`, `world.resource::<Score>()`,
+// `world.query::<&mut Health>()` and the like. Exclusive systems would otherwise hide everything they touch.
+export function parseDynamicAccesses(source: string): Map<string, [QueryStorage, string][]> {
+    const text = blankComments(source);
+    let accesses: Map<string, [QueryStorage, string][]> = new Map();
+    const pattern = /\bfn\s+(\w+)\s*(?:<[^(]*>)?\s*\(/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        const open = found.index + found[0].length - 1;
+        const close = matchingClose(text, open);
+        const brace = close < 0 ? -1 : text.indexOf("{", close);
+        if (brace < 0 || /;/.test(text.slice(close, brace)) || !dynamicParams.test(text.slice(open, close))) continue;
+        const body = text.slice(brace, matchingClose(text, brace) + 1);
+
+        let dynamic: [QueryStorage, string][] = [];
+        const add = (method: string, storage: (method: string) => QueryStorage | undefined) => {
+            for (const call of body.matchAll(turbofish(method))) {
+                const type = outerType(typeArgument(body, (call.index ?? 0) + call[0].length - 1));
+                const kind = storage(call[1]);
+                if (kind && /^[A-Z]/.test(type)) dynamic.push([kind, type]);
+            }
+        };
+        add("get_mut|take|remove", () => "mut_query");
+        add("get|get_ref|contains|get_change_ticks", method => method == "contains" ? "with" : "query");
+        add("resource_mut|get_resource_mut|remove_resource|resource_scope|non_send_resource_mut", () => "mut_res");
+        add("resource|get_resource|contains_resource|non_send_resource|is_resource_changed", () => "res");
+        add("send_event|send_event_default|send_event_batch", () => "event_write");
+        for (const call of body.matchAll(turbofish("query|query_filtered"))) {
+            dynamic.push(...queryStateAccesses(typeArgument(body, (call.index ?? 0) + call[0].length - 1)));
+        }
+
+        if (dynamic.length > 0) {
+            accesses.set(found[1], dynamic);
+        }
+    }
+
+    return accesses;
+}
@@ -0,0 +1,44 @@
+import { blankComments } from './registrations';
+import { systemParameters } from './complexity';
+
+// SystemParams of widely used third-party crates, with what they access as query parts, so that systems taking them
+// leave no holes in the analysis. A `[wrappers]` entry of the same name in bevyrly.toml replaces the built-in one.
+export const ecosystemParams: Map<string, string[]> = new Map([
+    // bevy_egui: the egui context of each window, and the textures registered with egui.
+    ["EguiContexts", ["*EguiContext", "$EguiUserTextures"]],
+    // bevy_trauma_shake: adds trauma to every `Shake`.
+    ["Shakes", ["*Shake"]],
+]);
+
+// bevy_spatial trees, the resources `SpatialAccess` is implemented for. They are generic over the marker component of
+// the entities they track, and usually named through an alias such as `type NNTree = KDTree2<Enemy>;`.
+export const spatialTrees = ["KDTree2", "KDTree3", "KDTree3A"];
+
+export function parseTypeAliases(source: string): Map<string, string> {
+    let aliases: Map<string, string> = new Map();
+    for (const found of blankComments(source).matchAll(/\btype\s+([A-Z]\w*)\s*=\s*([^;]+);/g)) {
+        aliases.set(found[1], found[2].trim());
+    }
+    return aliases;
+}
+
+// What a system's ecosystem parameters access, as query parts: the built-in accesses of `ecosystemParams` not replaced
+// by `wrappers`, and `#KDTree2` (or `$KDTree2`) with the tracked marker for each spatial tree resource.
+export function ecosystemAccesses(text: string, aliases: Map<string, string>, wrappers: Map<string, string[]>): string[] {
+    let parts: string[] = [];
+    for (const [_, param] of systemParameters(text)) {
+        const name = param.match(/^(?:[\w:]*::)?(\w+)/)?.[1] ?? "";
+        if (ecosystemParams.has(name) && !wrappers.has(name)) {
+            parts.push(...ecosystemParams.get(name) ?? []);
+        }
+
+        const resource = param.match(/^(?:Option\s*<\s*)?(Res|ResMut)\s*<\s*(?:'\w+\s*,\s*)?(?:[\w:]*::)?(\w+)/);
+        if (!resource) continue;
+        const type = aliases.get(resource[2]) ?? param.slice(param.indexOf(resource[2]));
+        const tree = type.match(/^(?:[\w:]*::)?(\w+)\s*<\s*(?:[\w:]*::)?(\w+)/);
+        if (tree && spatialTrees.includes(tree[1])) {
+            parts.push((resource[1] == "ResMut" ? "$" : "#") + tree[1], tree[2]);
+        }
+    }
+    return parts;
+}
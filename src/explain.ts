@@ -0,0 +1,129 @@
+import { BevyrlyIndex } from '.';
+import { parameterAccesses } from './calls';
+import { projectConfigOf } from './config';
+import { parseDynamicAccesses } from './dynamic';
+import { ecosystemAccesses, parseTypeAliases } from './ecosystem';
+import { defaultSymbolTable, parseQueryPart, parseQueryPartWith, queryParts } from './symbols';
+
+export interface PartExplanation {
+    part: string;
+    // What the part's symbol asks for, or "text" for a plain part matched against every access.
+    meaning: string;
+    matched: boolean;
+    // The keys the part matched in this system, or why it matched none.
+    detail: string;
+    // Where the matched accesses came from when not the system's own parameters: a `[wrappers]` entry, an ecosystem
+    // parameter or type alias, a helper function it calls, or the body's world accesses.
+    via: string[];
+}
+
+export interface SystemExplanation {
+    system: string;
+    matched: boolean;
+    parts: PartExplanation[];
+}
+
+// Takes `--explain` (optionally followed by a system name) out of a query: [query, explain, system].
+export function splitExplain(query: string): [string, boolean, string | undefined] {
+    const parts = query.split(" ");
+    const at = parts.indexOf("--explain");
+    if (at < 0) return [query, false, undefined];
+
+    const system = parts[at + 1] !== undefined && /^[A-Za-z_]\w*$/.test(parts[at + 1]) && !parseQueryPart(parts[at + 1])[0]
+        ? parts[at + 1] : undefined;
+    parts.splice(at, system ? 2 : 1);
+    return [parts.join(" ").trim(), true, system];
+}
+
+// Where `system`'s accesses named by `part` came from, other than its signature.
+function provenance(bevyrlyIndex: BevyrlyIndex, system: string, part: string, keys: string[]): string[] {
+    const loc = bevyrlyIndex.locs.get(system);
+    if (!loc) return [];
+    const [meaning] = parseQueryPart(part);
+    const sameAccess = (other: string) => {
+        const [otherMeaning, _, name] = parseQueryPartWith(defaultSymbolTable, other);
+        return (meaning === undefined || otherMeaning == meaning) && keys.includes(name);
+    };
+
+    let via: string[] = [];
+    const config = projectConfigOf(bevyrlyIndex, loc.src.filepath ?? "");
+    for (const [wrapper, parts] of config.wrappers) {
+        if (bevyrlyIndex.direct.get(wrapper)?.has(system) && parts.some(sameAccess)) {
+            via.push("the `" + wrapper + "` wrapper in bevyrly.toml");
+        }
+    }
+
+    let aliases: Map<string, string> = new Map();
+    for (const text of bevyrlyIndex.sources.values()) {
+        parseTypeAliases(text).forEach((type, alias) => aliases.set(alias, type));
+    }
+    if (ecosystemAccesses(loc.getText(), aliases, config.wrappers).some(sameAccess)) {
+        const alias = Array.from(aliases.keys()).find(alias => new RegExp("\\b" + alias + "\\b").test(loc.getText()));
+        via.push(alias ? "the `" + alias + "` type alias (" + aliases.get(alias) + ")" : "a known ecosystem parameter");
+    }
+
+    for (const helper of bevyrlyIndex.calls.get(system) ?? []) {
+        const text = bevyrlyIndex.locs.get(helper)?.getText() ?? "";
+        if ([...parameterAccesses(text), ...parseDynamicAccesses(text).get(helper) ?? []].some(([storage, name]) => (meaning === undefined || storage == meaning) && keys.includes(name))) {
+            via.push("a call to `" + helper + "`");
+        }
+    }
+
+    if ((parseDynamicAccesses(loc.getText()).get(system) ?? []).some(([storage, name]) => (meaning === undefined || storage == meaning) && keys.includes(name))) {
+        via.push("world or entity accesses in its body");
+    }
+    return via;
+}
+
+function explainPart(bevyrlyIndex: BevyrlyIndex, part: string, system: string): PartExplanation {
+    const [meaning, symbol, ident] = parseQueryPart(part);
+    const [map, name] = bevyrlyIndex.queryPart(part);
+    const described = meaning ?? "text";
+
+    const candidates = Array.from(map.keys()).filter(key => key !== undefined && key.includes(name));
+    const own = candidates.filter(key => map.get(key)?.has(system));
+    if (own.length > 0) {
+        return { part, meaning: described, matched: true, detail: "matched " + own.join(", "), via: provenance(bevyrlyIndex, system, part, own) };
+    }
+
+    let detail: string;
+    if (meaning == "changed" && !bevyrlyIndex.changed.has(ident)) {
+        detail = "git hasn't been asked about `" + ident + "` yet";
+    } else if (candidates.length == 0) {
+        detail = "nothing in the index contains `" + name + "`" + (meaning ? " as " + meaning + " (`" + symbol + "`)" : "") +
+            "; check the spelling and the symbol";
+    } else {
+        const has = Array.from(map.entries()).filter(([key, systems]) => key !== undefined && systems.has(system)).map(([key]) => key);
+        detail = "`" + name + "` matches " + candidates.slice(0, 5).join(", ") + (candidates.length > 5 ? ", ..." : "") +
+            ", none of them this system's" + (has.length > 0 ? "; its own " + described + " keys are " + has.slice(0, 5).join(", ") : "");
+    }
+    return { part, meaning: described, matched: false, detail, via: [] };
+}
+
+// Per system, which parts of `query` matched and why the others didn't: for `system` when given, otherwise for every
+// system matching at least one part, the near misses first.
+export function explainQuery(bevyrlyIndex: BevyrlyIndex, query: string, system?: string): SystemExplanation[] {
+    const parts = queryParts(query.replace(/^:/, "").trim()).filter(part => part.length > 0);
+    if (system && !bevyrlyIndex.locs.has(system)) return [];
+    const candidates = system ? [system] : Array.from(bevyrlyIndex.locs.keys())
+        .filter(candidate => parts.some(part => bevyrlyIndex.matches(part, candidate)));
+
+    return candidates
+        .map(candidate => {
+            const explained = parts.map(part => explainPart(bevyrlyIndex, part, candidate));
+            return { system: candidate, matched: explained.every(part => part.matched), parts: explained };
+        })
+        .sort((a, b) => Number(a.matched) - Number(b.matched) ||
+            b.parts.filter(part => part.matched).length - a.parts.filter(part => part.matched).length || a.system.localeCompare(b.system));
+}
+
+export function explanationLines(explanations: SystemExplanation[], system?: string): string[] {
+    if (explanations.length == 0) {
+        return [system ? "No system is named " + system : "No system matches any part of the query"];
+    }
+    return explanations.flatMap(explanation => [
+        explanation.system + ": " + (explanation.matched ? "found" : "not found"),
+        ...explanation.parts.map(part => "  " + (part.matched ? "✓ " : "✗ ") + part.part + " (" + part.meaning + "): " + part.detail +
+            (part.via.length > 0 ? ", through " + part.via.join(", ") : "")),
+    ]);
+}
@@ -0,0 +1,323 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { promptQuery } from './completion';
+import { expandLinkFromName, expandModuleFromName, expandModuleFromPath } from './extension';
+import { queryMatches } from './results';
+import { systemId } from './ids';
+import { resolveChanged } from './changed';
+import { featureScopedGet } from './cfg';
+import { sarifLog } from './diagnostics';
+
+export interface ExportedSystem {
+    id: string;
+    name: string;
+    crate: string;
+    module: string;
+    file: string;
+    line: number;
+    doc: string;
+    registrations: { schedule: string, conditions: string[] }[];
+    accesses: { [storage: string]: string[] };
+}
+
+const exportColumns = ["system", "crate", "module", "file", "line", "matched accesses", "schedule"];
+
+function escapeField(field: string, separator: string): string {
+    if (field.includes(separator) || field.includes('"') || field.includes("\n")) {
+        return '"' + field.replace(/"/g, '""') + '"';
+    }
+
+    return field;
+}
+
+export function exportSystems(bevyrlyIndex: BevyrlyIndex): ExportedSystem[] {
+    let result: ExportedSystem[] = [];
+    for (const system of Array.from(bevyrlyIndex.systems.keys()).sort()) {
+        const [crate, module] = expandModuleFromName(bevyrlyIndex, system) ?? ["", ""];
+        const location = expandLinkFromName(bevyrlyIndex, system)?.[1] ?? ":";
+
+        result.push({
+            id: systemId(bevyrlyIndex, system),
+            name: system,
+            crate,
+            module,
+            file: location.slice(0, location.lastIndexOf(":")),
+            line: Number(location.slice(location.lastIndexOf(":") + 1)),
+            doc: bevyrlyIndex.docs.get(system) ?? "",
+            registrations: (bevyrlyIndex.registrations.get(system) ?? [])
+                .map(reg => ({ schedule: reg.schedule, conditions: reg.conditions })),
+            accesses: Object.fromEntries(bevyrlyIndex.accessesOf(system)),
+        });
+    }
+
+    return result;
+}
+
+export function exportQueryResults(bevyrlyIndex: BevyrlyIndex, query: string, separator: string): string {
+    let rows = [exportColumns];
+    for (const match of queryMatches(bevyrlyIndex, query)) {
+        rows.push([
+            match.system,
+            match.crate,
+            match.module,
+            match.location.slice(0, match.location.lastIndexOf(":")),
+            String(match.line),
+            match.matched.join(" "),
+            match.schedules.join("; "),
+        ]);
+    }
+
+    return rows.map(row => row.map(field => escapeField(field, separator)).join(separator)).join("\n") + "\n";
+}
+
+// Paths are relative to `fromCrate`: `crate::` inside it, the crate name (as an identifier) outside of it.
+export function systemPath(bevyrlyIndex: BevyrlyIndex, system: string, fromCrate: string): string {
+    const [crate, module] = expandModuleFromName(bevyrlyIndex, system) ?? ["", ""];
+    const name = system.replace(/<.*$/, "");
+    if (crate == fromCrate && module.length == 0) {
+        return name;
+    }
+
+    const root = crate == fromCrate ? "crate" : crate.replace(/-/g, "_");
+    return [root, module, name].filter(segment => segment.length > 0).join("::");
+}
+
+export function registrationSnippet(bevyrlyIndex: BevyrlyIndex, systems: string[], schedule: string, chain: boolean, fromCrate: string): string {
+    const paths = systems.map(system => systemPath(bevyrlyIndex, system, fromCrate));
+    if (paths.length == 1) {
+        return "app.add_systems(" + schedule + ", " + paths[0] + ");\n";
+    }
+
+    return "app.add_systems(\n    " + schedule + ",\n    (\n" + paths.map(path => "        " + path + ",\n").join("") +
+        "    )" + (chain ? "\n        .chain()" : "") + ",\n);\n";
+}
+
+function pascalCase(name: string): string {
+    return name.split("_").map(word => word.charAt(0).toUpperCase() + word.slice(1)).join("");
+}
+
+// Rebuilds the registrations of `systems` inside a new plugin, keeping schedules, run conditions, sets, and every
+// `.before`/`.after`, whether against moved systems, ones left behind, or sets. The original `add_systems` sites are
+// listed so they can be removed by hand.
+export function pluginSnippet(bevyrlyIndex: BevyrlyIndex, systems: string[], name: string): string {
+    let bySchedule: Map<string, string[]> = new Map();
+    let sites: string[] = [];
+    const pathOf = (system: string) => {
+        const [_, module] = expandModuleFromName(bevyrlyIndex, system) ?? ["", ""];
+        return ["crate", module, system].filter(segment => segment.length > 0).join("::");
+    };
+    // Ordering targets are systems (by path) or sets (as written). An edge `.chain()` recorded on both of two moved
+    // systems is only emitted once, as the `.after` of the later one.
+    const targetOf = (other: string) => bevyrlyIndex.locs.has(other) ? pathOf(other) : other;
+    const ordersAfter = (other: string, system: string) => systems.includes(other) &&
+        (bevyrlyIndex.registrations.get(other) ?? []).some(reg => reg.after.includes(system));
+
+    for (const system of systems) {
+        for (const reg of bevyrlyIndex.registrations.get(system) ?? []) {
+            let entry = pathOf(system);
+            entry += reg.conditions.map(condition => ".run_if(" + condition + ")").join("");
+            entry += reg.sets.map(set => ".in_set(" + set + ")").join("");
+            entry += reg.after.map(other => ".after(" + targetOf(other) + ")").join("");
+            entry += reg.before.filter(other => !ordersAfter(other, system)).map(other => ".before(" + targetOf(other) + ")").join("");
+
+            if (!bySchedule.has(reg.schedule)) {
+                bySchedule.set(reg.schedule, []);
+            }
+            bySchedule.get(reg.schedule)?.push(entry);
+            sites.push(vscode.Uri.parse(reg.filepath).path.split('/src/').pop() + ":" + (reg.line + 1) + " " + system);
+        }
+    }
+
+    let calls: string[] = [];
+    for (const [schedule, entries] of bySchedule) {
+        calls.push(entries.length == 1
+            ? ".add_systems(" + schedule + ", " + entries[0] + ")"
+            : ".add_systems(\n                " + schedule + ",\n                (\n" +
+            entries.map(entry => "                    " + entry + ",\n").join("") + "                ),\n            )");
+    }
+
+    return [
+        "use bevy::prelude::*;",
+        "",
+        ...(sites.length > 0 ? ["// Moved from:", ...sites.map(site => "// - " + site), ""] : []),
+        "pub struct " + name + ";",
+        "",
+        "impl Plugin for " + name + " {",
+        "    fn build(&self, app: &mut App) {",
+        "        app" + calls.map(call => "\n            " + call).join("") + ";",
+        "    }",
+        "}",
+        "",
+    ].join("\n");
+}
+
+export interface ProfilerName {
+    system: string;
+    name: string;
+    span: string;
+    file: string;
+    line: number;
+    accesses: { [storage: string]: string[] };
+}
+
+// Bevy names system spans after `std::any::type_name` of the function, i.e. its full path from the crate root.
+export function profilerNames(bevyrlyIndex: BevyrlyIndex): ProfilerName[] {
+    return exportSystems(bevyrlyIndex).map(system => {
+        const name = [system.crate.replace(/-/g, "_"), system.module, system.name.replace(/<.*$/, "")]
+            .filter(segment => segment.length > 0)
+            .join("::");
+        return {
+            system: system.name,
+            name,
+            span: "system{name=\"" + name + "\"}",
+            file: system.file,
+            line: system.line,
+            accesses: system.accesses,
+        };
+    });
+}
+
+export function registerExport(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableExport = vscode.commands.registerCommand('bevyrly.exportResults', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const query = await promptQuery(bevyrlyIndex, bevyrlyIndex.lastQuery, '&Transform *Vel E');
+
+        if (!query) {
+            return;
+        }
+
+        const folder = vscode.workspace.workspaceFolders?.[0]?.uri;
+        const target = await vscode.window.showSaveDialog({
+            defaultUri: folder ? vscode.Uri.joinPath(folder, "bevyrly.csv") : undefined,
+            filters: { "CSV": ["csv"], "TSV": ["tsv"] }
+        });
+
+        if (target) {
+            const separator = target.path.endsWith(".tsv") ? "\t" : ",";
+            await resolveChanged(bevyrlyIndex, query);
+            const contents = exportQueryResults(bevyrlyIndex, query, separator);
+            await vscode.workspace.fs.writeFile(target, new TextEncoder().encode(contents));
+            vscode.window.showInformationMessage("Exported bevyrly results to " + target.path);
+        }
+    });
+
+    context.subscriptions.push(disposableExport);
+
+    let disposableSnippet = vscode.commands.registerCommand('bevyrly.copyAsRegistration', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const query = await promptQuery(bevyrlyIndex, bevyrlyIndex.lastQuery, '&Transform *Vel E');
+
+        if (!query) {
+            return;
+        }
+
+        await resolveChanged(bevyrlyIndex, query);
+        const picked = await vscode.window.showQuickPick(featureScopedGet(bevyrlyIndex, query)[0].sort().map(system => ({
+            label: system,
+            description: expandLinkFromName(bevyrlyIndex, system)?.[1],
+            picked: true
+        })), { title: 'Systems to register', canPickMany: true });
+
+        if (!picked || picked.length == 0) {
+            return;
+        }
+
+        const systems = picked.map(item => item.label);
+        const known = Array.from(new Set(systems.flatMap(system => bevyrlyIndex.schedulesOf(system))));
+        const schedule = await vscode.window.showQuickPick(known.length > 0 ? known : ["Update"], { title: 'Schedule' });
+        if (!schedule) {
+            return;
+        }
+
+        let chain = false;
+        if (systems.length > 1) {
+            const ordering = await vscode.window.showQuickPick(["chain", "unordered"], { title: 'Ordering' });
+            if (!ordering) {
+                return;
+            }
+            chain = ordering == "chain";
+        }
+
+        const editor = vscode.window.activeTextEditor;
+        const fromCrate = editor ? expandModuleFromPath(bevyrlyIndex, editor.document.uri.toString())[0] : "";
+        await vscode.env.clipboard.writeText(registrationSnippet(bevyrlyIndex, systems, schedule, chain, fromCrate));
+        vscode.window.showInformationMessage("Copied an add_systems registration for " + systems.length + " systems");
+    });
+
+    context.subscriptions.push(disposableSnippet);
+
+    let disposablePlugin = vscode.commands.registerCommand('bevyrly.generatePlugin', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const query = await promptQuery(bevyrlyIndex, bevyrlyIndex.lastQuery, '&Transform *Vel E');
+        if (!query) {
+            return;
+        }
+
+        await resolveChanged(bevyrlyIndex, query);
+        const picked = await vscode.window.showQuickPick(featureScopedGet(bevyrlyIndex, query)[0].sort().map(system => ({
+            label: system,
+            description: expandLinkFromName(bevyrlyIndex, system)?.[1],
+            picked: true
+        })), { title: 'Systems to move into the plugin', canPickMany: true });
+
+        if (!picked || picked.length == 0) {
+            return;
+        }
+
+        const systems = picked.map(item => item.label);
+        const modules = systems.map(system => (expandModuleFromName(bevyrlyIndex, system) ?? ["", ""])[1]);
+        const common = modules.every(module => module == modules[0]) ? modules[0].split("::").pop() ?? "" : "";
+        const name = await vscode.window.showInputBox({
+            value: (common.length > 0 ? pascalCase(common) : "New") + "Plugin",
+            prompt: 'Plugin name'
+        });
+
+        if (!name) {
+            return;
+        }
+
+        const doc = await vscode.workspace.openTextDocument({ content: pluginSnippet(bevyrlyIndex, systems, name), language: 'rust' });
+        await vscode.window.showTextDocument(doc, { preview: false });
+    });
+
+    context.subscriptions.push(disposablePlugin);
+
+    let disposableProfiler = vscode.commands.registerCommand('bevyrly.exportProfilerNames', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const folder = vscode.workspace.workspaceFolders?.[0]?.uri;
+        const target = await vscode.window.showSaveDialog({
+            defaultUri: folder ? vscode.Uri.joinPath(folder, "bevyrly-spans.json") : undefined,
+            filters: { "JSON": ["json"] }
+        });
+
+        if (target) {
+            const contents = JSON.stringify(profilerNames(bevyrlyIndex), null, 2);
+            await vscode.workspace.fs.writeFile(target, new TextEncoder().encode(contents));
+            vscode.window.showInformationMessage("Exported bevyrly profiler names to " + target.path);
+        }
+    });
+
+    context.subscriptions.push(disposableProfiler);
+
+    let disposableSarif = vscode.commands.registerCommand('bevyrly.exportSarif', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const folder = vscode.workspace.workspaceFolders?.[0]?.uri;
+        const target = await vscode.window.showSaveDialog({
+            defaultUri: folder ? vscode.Uri.joinPath(folder, "bevyrly.sarif") : undefined,
+            filters: { "SARIF": ["sarif", "json"] }
+        });
+
+        if (target) {
+            const contents = JSON.stringify(sarifLog(bevyrlyIndex), null, 2);
+            await vscode.workspace.fs.writeFile(target, new TextEncoder().encode(contents));
+            vscode.window.showInformationMessage("Exported bevyrly lint findings to " + target.path);
+        }
+    });
+
+    context.subscriptions.push(disposableSarif);
+}
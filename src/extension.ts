@@ -1,13 +1,45 @@
 import * as vscode from 'vscode';
 import { Uri } from 'vscode';
-import { BevyrlyIndex, startBevyrlyIndexing } from './index';
+import { BevyrlyIndex, SystemSpan, startBevyrlyIndexing } from './index';
+import { crateDirectory } from './cfg';
 import { registerTextDocument } from './text_document';
 import { registerNotebookDocument } from './notebook_document';
-import { Loc } from 'jinx-rust';
+import { registerExport } from './export';
+import { registerReport } from './report';
+import { registerGraph } from './graph';
+import { registerAmbiguities } from './ambiguity';
+import { registerArchetypes } from './archetypes';
+import { registerComplexity } from './complexity';
+import { registerDiff } from './diff';
+import { registerDecorations } from './decorations';
+import { registerCodeLenses } from './lenses';
+import { registerHover } from './hover';
+import { registerSystemTree } from './tree';
+import { registerTimeline } from './timeline';
+import { registerDefinitions } from './definitions';
+import { registerReferences } from './references';
+import { registerCodeActions } from './actions';
+import { registerDiagnostics } from './diagnostics';
+import { registerRename } from './rename';
+import { registerRemote } from './remote';
+import { registerCompletion } from './completion';
+import { registerBlame } from './blame';
+import { registerIndexCache } from './cache';
+import { registerBenchmark } from './bench';
+import { registerStats } from './stats';
+import { registerReindexOnChange } from './watch';
+import { registerProjectConfig } from './config';
+import { registerSystemLinks } from './ids';
+import { registerQueryHistory } from './history';
+import { registerSessions } from './sessions';
+import { registerQuerySymbols, registerSyntaxHelp } from './symbols';
+import { BevyrlyApi, createApi } from './api';
+import { orderingSummary } from './results';
+import { tagsOf } from './tags';
 
 let bevyrlyIndex: BevyrlyIndex = new BevyrlyIndex();
 
-export function expandLinkFromName(bevyrlyIndex: BevyrlyIndex, system: string): [Loc, string] | undefined {
+export function expandLinkFromName(bevyrlyIndex: BevyrlyIndex, system: string): [SystemSpan, string] | undefined {
     let loc = bevyrlyIndex.locs.get(system);
     if (loc) {
         const start = loc.src.l(loc[0]) + 1;
@@ -17,6 +49,58 @@ export function expandLinkFromName(bevyrlyIndex: BevyrlyIndex, system: string):
     return undefined;
 }
 
+// The crate and module path of a file: the crate named by its Cargo.toml's `[package].name` (or its directory), and the
+// module by the path below `src/`. Examples, benches and integration tests are crates of their own, named after their
+// file (or directory); any other file counts as a module named after itself in the nearest package.
+export function expandModuleFromPath(bevyrlyIndex: BevyrlyIndex, filepath: string): [string, string] {
+    const path = vscode.Uri.parse(filepath).path;
+    const at = path.lastIndexOf("/src/");
+    // The nearest Cargo.toml bevyrly has read, so a `[lib] path = "lib/.."` override still finds its package.
+    let directory = crateDirectory(filepath);
+    for (let up = directory; at < 0 && up.includes("/"); up = up.slice(0, up.lastIndexOf("/"))) {
+        if (bevyrlyIndex.crateFeatures.get(up)?.packageName) {
+            directory = up;
+            break;
+        }
+    }
+    const crate = bevyrlyIndex.crateFeatures.get(directory)?.packageName ?? directory.split("/").pop() ?? "";
+    const modulePath = (segments: string[]) => segments.filter((segment, i) =>
+        !(i == segments.length - 1 && (segment == "mod" || (segments.length == 1 && (segment == "main" || segment == "lib"))))).join("::");
+    if (at >= 0) {
+        return [crate, modulePath(path.slice(at + "/src/".length).replace(/\.rs$/, "").split("/"))];
+    }
+
+    const target = path.match(/\/(?:examples|benches|tests)\/([^/]+?)(?:\.rs|\/(.*))$/);
+    if (target) {
+        return [target[1], modulePath((target[2] ?? "main.rs").replace(/\.rs$/, "").split("/"))];
+    }
+    return [crate, modulePath([path.split("/").pop()?.replace(/\.rs$/, "") ?? ""])];
+}
+
+// The crate a system comes from, prefixed with its workspace folder when several roots are open.
+export function provenanceOf(bevyrlyIndex: BevyrlyIndex, system: string): string {
+    const filepath = bevyrlyIndex.locs.get(system)?.src.filepath ?? "";
+    const [crate, _] = expandModuleFromPath(bevyrlyIndex, filepath);
+    const folders = vscode.workspace.workspaceFolders ?? [];
+    if (folders.length > 1) {
+        const folder = vscode.workspace.getWorkspaceFolder(vscode.Uri.parse(filepath));
+        if (folder && folder.name != crate) {
+            return folder.name + "/" + crate;
+        }
+    }
+
+    return crate;
+}
+
+export function expandModuleFromName(bevyrlyIndex: BevyrlyIndex, system: string): [string, string] | undefined {
+    let loc = bevyrlyIndex.locs.get(system);
+    if (loc) {
+        return expandModuleFromPath(bevyrlyIndex, loc.src.filepath ?? "");
+    }
+
+    return undefined;
+}
+
 export function expandSystemFromName(bevyrlyIndex: BevyrlyIndex, system: string, withLink: boolean = true): string {
     let content = "";
     let loc = bevyrlyIndex.locs.get(system);
@@ -24,7 +108,15 @@ export function expandSystemFromName(bevyrlyIndex: BevyrlyIndex, system: string,
         if (withLink) {
             const start = loc.src.l(loc[0]);
             const end = loc.src.l(loc[1]);
-            content += "\n/* " + vscode.Uri.parse(loc.src.filepath?.replace("file:///", "") ?? "").path.split('/src/').pop() + ":" + start + "-" + end + " */\n";
+            content += "\n/* " + provenanceOf(bevyrlyIndex, system) + ": " + vscode.Uri.parse(loc.src.filepath?.replace("file:///", "") ?? "").path.split('/src/').pop() + ":" + start + "-" + end + " */\n";
+            const ordering = orderingSummary(bevyrlyIndex, system);
+            if (ordering.length > 0) {
+                content += "/* " + ordering + " */\n";
+            }
+            const tags = tagsOf(bevyrlyIndex, system);
+            if (tags.length > 0) {
+                content += "/* tags: " + tags.join(", ") + " */\n";
+            }
         }
         content += loc.getText();
     }
@@ -32,11 +124,60 @@ export function expandSystemFromName(bevyrlyIndex: BevyrlyIndex, system: string,
     return content;
 }
 
-export function activate(context: vscode.ExtensionContext) {
+export async function revealSystem(bevyrlyIndex: BevyrlyIndex, system: string) {
+    let loc = bevyrlyIndex.locs.get(system);
+    if (loc) {
+        const position = new vscode.Position(loc.src.l(loc[0]), 0);
+        const doc = await vscode.workspace.openTextDocument(vscode.Uri.parse(loc.src.filepath ?? ""));
+        await vscode.window.showTextDocument(doc, { selection: new vscode.Range(position, position), preview: false });
+    }
+}
+
+export function escapeHtml(text: string): string {
+    return text.replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;").replace(/'/g, "&#39;");
+}
+
+export function activate(context: vscode.ExtensionContext): BevyrlyApi {
     context.subscriptions.push(vscode.commands.registerCommand('bevyrly.start', () => {
         startBevyrlyIndexing(context, bevyrlyIndex);
     }));
 
+    context.subscriptions.push(vscode.commands.registerCommand('bevyrly.cancelIndexing', () => {
+        bevyrlyIndex.indexing?.source.cancel();
+    }));
+
     registerTextDocument(context, bevyrlyIndex);
     registerNotebookDocument(context, bevyrlyIndex);
+    registerExport(context, bevyrlyIndex);
+    registerReport(context, bevyrlyIndex);
+    registerGraph(context, bevyrlyIndex);
+    registerAmbiguities(context, bevyrlyIndex);
+    registerArchetypes(context, bevyrlyIndex);
+    registerComplexity(context, bevyrlyIndex);
+    registerDiff(context, bevyrlyIndex);
+    registerDecorations(context, bevyrlyIndex);
+    registerCodeLenses(context, bevyrlyIndex);
+    registerHover(context, bevyrlyIndex);
+    registerSystemTree(context, bevyrlyIndex);
+    registerTimeline(context, bevyrlyIndex);
+    registerDefinitions(context, bevyrlyIndex);
+    registerReferences(context, bevyrlyIndex);
+    registerCodeActions(context, bevyrlyIndex);
+    registerDiagnostics(context, bevyrlyIndex);
+    registerRename(context, bevyrlyIndex);
+    registerRemote(context, bevyrlyIndex);
+    registerCompletion(context, bevyrlyIndex);
+    registerBlame(context, bevyrlyIndex);
+    registerIndexCache(context, bevyrlyIndex);
+    registerBenchmark(context, bevyrlyIndex);
+    registerStats(context, bevyrlyIndex);
+    registerReindexOnChange(context, bevyrlyIndex);
+    registerProjectConfig(context, bevyrlyIndex);
+    registerSystemLinks(context, bevyrlyIndex);
+    registerQueryHistory(context, bevyrlyIndex);
+    registerSessions(context, bevyrlyIndex);
+    registerSyntaxHelp(context);
+    registerQuerySymbols(context, bevyrlyIndex);
+
+    return createApi(context, bevyrlyIndex);
 }
@@ -0,0 +1,46 @@
+import { BevyrlyIndex } from '.';
+import { systemParameters } from './complexity';
+import { blankComments } from './registrations';
+
+// Plain `Gizmos` draws with the default config group.
+export const defaultGizmoGroup = "DefaultGizmoConfigGroup";
+
+// The config group of a `Gizmos<..>` parameter type, if it is one.
+export function gizmoGroupOf(type: string): string | undefined {
+    const found = type.match(/^Gizmos\s*(?:<\s*(?:'\w+\s*,\s*)*(?:[\w:]*::)?(\w+)?[^>]*>)?$/);
+    return found ? found[1] ?? defaultGizmoGroup : undefined;
+}
+
+// Gizmo config group → the systems drawing with `Gizmos<Group>` or changing its config through
+// `GizmoConfigStore::config_mut::<Group>()`, plus "GizmoAsset" for the systems building retained gizmos. Every group
+// added with `init_gizmo_group` is a key, used or not. What `gizmos:` query parts match against, so debug drawing can
+// be audited one group at a time.
+export function gizmoGroups(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("gizmo-groups", () => {
+        let groups: Map<string, Set<string>> = new Map();
+        const add = (group: string, system: string) => {
+            if (!groups.has(group)) {
+                groups.set(group, new Set());
+            }
+            groups.get(group)?.add(system);
+        };
+
+        for (const provision of bevyrlyIndex.provisions.filter(provision => provision.kind == "gizmo_group")) {
+            groups.set(provision.name, groups.get(provision.name) ?? new Set());
+        }
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            const text = blankComments(loc.getText());
+            for (const [_, type] of systemParameters(text)) {
+                const group = gizmoGroupOf(type);
+                if (group) add(group, system);
+            }
+            for (const found of text.matchAll(/\.\s*config(?:_mut)?\s*::\s*<\s*(?:[\w:]*::)?(\w+)\s*>/g)) {
+                add(found[1], system);
+            }
+            if (/\bGizmoAsset\b/.test(text)) {
+                add("GizmoAsset", system);
+            }
+        }
+        return groups;
+    });
+}
@@ -0,0 +1,220 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { parseTransitions } from './states';
+import { tagsOf } from './tags';
+
+function quote(text: string): string {
+    return '"' + text.replace(/"/g, '\\"').replace(/\n/g, "\\n") + '"';
+}
+
+export function pluginsOf(bevyrlyIndex: BevyrlyIndex, system: string): string[] {
+    return Array.from(new Set((bevyrlyIndex.registrations.get(system) ?? []).map(reg => reg.plugin || "App")));
+}
+
+// Systems are clustered by the plugin that registers them, and connected writer -> reader for every event.
+// Edges that cross plugin boundaries are highlighted, since they are the coupling between plugins. Tagged systems list
+// their tags under their name.
+export function generateGraph(bevyrlyIndex: BevyrlyIndex): string {
+    let clusters: Map<string, string[]> = new Map();
+    for (const system of Array.from(bevyrlyIndex.systems.keys()).sort()) {
+        const plugin = pluginsOf(bevyrlyIndex, system)[0] ?? "Unregistered";
+        if (!clusters.has(plugin)) {
+            clusters.set(plugin, []);
+        }
+
+        clusters.get(plugin)?.push(system);
+    }
+
+    let lines = ["digraph bevyrly {", "    rankdir=LR;", "    node [shape=box];"];
+    let clusterId = 0;
+    for (const [plugin, systems] of clusters) {
+        lines.push("    subgraph cluster_" + clusterId++ + " {");
+        lines.push("        label=" + quote(plugin) + ";");
+        for (const system of systems) {
+            const tags = tagsOf(bevyrlyIndex, system);
+            lines.push("        " + quote(system) + (tags.length > 0 ? " [label=" + quote(system + "\n[" + tags.join(", ") + "]") + "]" : "") + ";");
+        }
+        lines.push("    }");
+    }
+
+    for (const [event, writers] of bevyrlyIndex.event_write) {
+        for (const writer of writers) {
+            for (const reader of bevyrlyIndex.event_read.get(event) ?? []) {
+                const crossing = pluginsOf(bevyrlyIndex, writer)[0] != pluginsOf(bevyrlyIndex, reader)[0];
+                lines.push("    " + quote(writer) + " -> " + quote(reader) + " [label=" + quote(event) +
+                    (crossing ? ", color=red, penwidth=2" : "") + "];");
+            }
+        }
+    }
+
+    lines.push("}");
+    return lines.join("\n");
+}
+
+// Plugins are nodes listing the systems, events and resources they add to the app; an edge from one plugin to
+// another lists what the first one's systems use that the second one provides.
+export function generatePluginDiagram(bevyrlyIndex: BevyrlyIndex): string {
+    let provider: Map<string, string> = new Map();
+    let provided: Map<string, string[]> = new Map();
+    const provide = (plugin: string, item: string) => {
+        if (!provided.has(plugin)) {
+            provided.set(plugin, []);
+        }
+        provided.get(plugin)?.push(item);
+    };
+
+    for (const provision of bevyrlyIndex.provisions) {
+        const plugin = provision.plugin || "App";
+        const key = provision.kind + ":" + provision.name;
+        if (!provider.has(key)) {
+            provider.set(key, plugin);
+        }
+        provide(plugin, provision.kind + " " + provision.name);
+    }
+
+    let systemCounts: Map<string, number> = new Map();
+    let requires: Map<string, Set<string>> = new Map();
+    for (const system of bevyrlyIndex.systems.keys()) {
+        const accesses = bevyrlyIndex.accessesOf(system);
+        const used = [
+            ...[...(accesses.get("event_read") ?? []), ...(accesses.get("event_write") ?? [])].map(name => "event:" + name),
+            ...[...(accesses.get("res") ?? []), ...(accesses.get("mut_res") ?? [])].map(name => "resource:" + name),
+        ];
+
+        for (const plugin of pluginsOf(bevyrlyIndex, system)) {
+            systemCounts.set(plugin, (systemCounts.get(plugin) ?? 0) + 1);
+            for (const key of used) {
+                const owner = provider.get(key);
+                if (owner !== undefined && owner != plugin) {
+                    const edge = plugin + "\u0000" + owner;
+                    if (!requires.has(edge)) {
+                        requires.set(edge, new Set());
+                    }
+                    requires.get(edge)?.add(key.slice(key.indexOf(":") + 1));
+                }
+            }
+        }
+    }
+
+    let lines = ["digraph plugins {", "    rankdir=LR;", "    node [shape=box];"];
+    const plugins = Array.from(new Set([...systemCounts.keys(), ...provided.keys()])).sort();
+    for (const plugin of plugins) {
+        const items = (provided.get(plugin) ?? []).sort();
+        const label = [plugin, (systemCounts.get(plugin) ?? 0) + " systems", ...items].join("\n");
+        lines.push("    " + quote(plugin) + " [label=" + quote(label) + "];");
+    }
+
+    for (const [edge, items] of requires) {
+        const [from, to] = edge.split("\u0000");
+        lines.push("    " + quote(from) + " -> " + quote(to) + " [label=" + quote(Array.from(items).sort().join("\n")) + "];");
+    }
+
+    lines.push("}");
+    return lines.join("\n");
+}
+
+// States are nodes listing their OnEnter/OnExit systems; `NextState::set` calls are edges labeled with the
+// system that makes them, starting from the state the system is gated on with `in_state` (or any state).
+export function generateStateDiagram(bevyrlyIndex: BevyrlyIndex): string {
+    let lines = ["digraph states {", "    compound=true;", "    node [shape=box, style=rounded];"];
+    const nodeOf = (key: string) => key.includes("::") || (bevyrlyIndex.states.get(key) ?? []).length == 0 ? key : key + "::*";
+    for (const [state, variants] of bevyrlyIndex.states) {
+        // Sub-states and computed states are drawn as a cluster, with a dashed edge from each state they derive from.
+        const sources = bevyrlyIndex.stateSources.get(state);
+        if (sources) {
+            lines.push("    subgraph " + quote("cluster_" + state) + " {", "        label=" + quote(state) + ";");
+        }
+        for (const variant of variants.length > 0 ? variants : [undefined]) {
+            const name = variant ? state + "::" + variant : state;
+            let label = name;
+            for (const hook of ["OnEnter", "OnExit"]) {
+                const systems = Array.from(bevyrlyIndex.registrations.values())
+                    .flat()
+                    .filter(reg => reg.schedule == hook + "(" + name + ")")
+                    .map(reg => reg.system);
+                if (systems.length > 0) {
+                    label += "\n" + hook + ": " + Array.from(new Set(systems)).join(", ");
+                }
+            }
+            lines.push((sources ? "        " : "    ") + quote(name) + " [label=" + quote(label) + "];");
+        }
+        if (sources) {
+            const first = variants.length > 0 ? state + "::" + variants[0] : state;
+            lines.push("    }");
+            for (const source of sources) {
+                lines.push("    " + quote(nodeOf(source)) + " -> " + quote(first) +
+                    " [lhead=" + quote("cluster_" + state) + ", style=dashed, label=\"source\"];");
+            }
+        }
+    }
+
+    for (const [system, loc] of bevyrlyIndex.locs) {
+        for (const transition of parseTransitions(system, loc.getText())) {
+            const from = (bevyrlyIndex.registrations.get(system) ?? [])
+                .flatMap(reg => reg.conditions)
+                .map(condition => condition.match(new RegExp("^in_state\\(\\s*" + transition.state + "::(\\w+)\\s*\\)$"))?.[1])
+                .filter(variant => variant !== undefined);
+            for (const variant of from.length > 0 ? from : [undefined]) {
+                const source = variant ? transition.state + "::" + variant : transition.state + "::*";
+                lines.push("    " + quote(source) + " -> " + quote(transition.state + "::" + transition.to) +
+                    " [label=" + quote(system) + "];");
+            }
+        }
+    }
+
+    lines.push("}");
+    return lines.join("\n");
+}
+
+export function registerGraph(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableGraph = vscode.commands.registerCommand('bevyrly.exportGraph', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const folder = vscode.workspace.workspaceFolders?.[0]?.uri;
+        const target = await vscode.window.showSaveDialog({
+            defaultUri: folder ? vscode.Uri.joinPath(folder, "bevyrly.dot") : undefined,
+            filters: { "Graphviz": ["dot", "gv"] }
+        });
+
+        if (target) {
+            await vscode.workspace.fs.writeFile(target, new TextEncoder().encode(generateGraph(bevyrlyIndex)));
+            vscode.window.showInformationMessage("Exported bevyrly graph to " + target.path);
+        }
+    });
+
+    context.subscriptions.push(disposableGraph);
+
+    let disposableStates = vscode.commands.registerCommand('bevyrly.exportStateDiagram', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const folder = vscode.workspace.workspaceFolders?.[0]?.uri;
+        const target = await vscode.window.showSaveDialog({
+            defaultUri: folder ? vscode.Uri.joinPath(folder, "states.dot") : undefined,
+            filters: { "Graphviz": ["dot", "gv"] }
+        });
+
+        if (target) {
+            await vscode.workspace.fs.writeFile(target, new TextEncoder().encode(generateStateDiagram(bevyrlyIndex)));
+            vscode.window.showInformationMessage("Exported bevyrly state diagram to " + target.path);
+        }
+    });
+
+    context.subscriptions.push(disposableStates);
+
+    let disposablePlugins = vscode.commands.registerCommand('bevyrly.exportPluginDiagram', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const folder = vscode.workspace.workspaceFolders?.[0]?.uri;
+        const target = await vscode.window.showSaveDialog({
+            defaultUri: folder ? vscode.Uri.joinPath(folder, "plugins.dot") : undefined,
+            filters: { "Graphviz": ["dot", "gv"] }
+        });
+
+        if (target) {
+            await vscode.workspace.fs.writeFile(target, new TextEncoder().encode(generatePluginDiagram(bevyrlyIndex)));
+            vscode.window.showInformationMessage("Exported bevyrly plugin diagram to " + target.path);
+        }
+    });
+
+    context.subscriptions.push(disposablePlugins);
+}
@@ -0,0 +1,70 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { showBevyrlyResultsAsTextDocument } from './text_document';
+
+export interface QueryHistoryEntry {
+    query: string;
+    // Milliseconds since the epoch of the latest run.
+    time: number;
+    results: number;
+}
+
+// Queries run in notebooks and query documents, newest first, kept in the workspace state so they survive restarts.
+// Running a query again moves it to the top instead of adding a duplicate.
+export class QueryHistory {
+    private _state: vscode.Memento;
+
+    constructor(state: vscode.Memento) {
+        this._state = state;
+    }
+
+    entries(): QueryHistoryEntry[] {
+        return this._state.get<QueryHistoryEntry[]>('bevyrly.queryHistory') ?? [];
+    }
+
+    record(query: string, results: number) {
+        const size = vscode.workspace.getConfiguration('bevyrly').get<number>('queryHistorySize') ?? 100;
+        if (size <= 0 || query.trim().length == 0) {
+            return;
+        }
+
+        const entries = [{ query, time: Date.now(), results }, ...this.entries().filter(entry => entry.query != query)];
+        this._state.update('bevyrly.queryHistory', entries.slice(0, size));
+    }
+
+    clear() {
+        this._state.update('bevyrly.queryHistory', undefined);
+    }
+}
+
+let queryHistory: QueryHistory | undefined = undefined;
+
+export function recordQuery(query: string, results: number) {
+    queryHistory?.record(query, results);
+}
+
+export function queryHistoryEntries(): QueryHistoryEntry[] {
+    return queryHistory?.entries() ?? [];
+}
+
+export function registerQueryHistory(context: vscode.ExtensionContext, _bevyrlyIndex: BevyrlyIndex) {
+    queryHistory = new QueryHistory(context.workspaceState);
+
+    let disposableShow = vscode.commands.registerCommand('bevyrly.showQueryHistory', async () => {
+        const picked = await vscode.window.showQuickPick(queryHistoryEntries().map(entry => ({
+            label: entry.query,
+            description: entry.results + (entry.results == 1 ? " result" : " results"),
+            detail: new Date(entry.time).toLocaleString(),
+        })), { title: 'Query history', matchOnDescription: true, matchOnDetail: true });
+
+        if (picked) {
+            showBevyrlyResultsAsTextDocument(picked.label);
+        }
+    });
+    context.subscriptions.push(disposableShow);
+
+    let disposableClear = vscode.commands.registerCommand('bevyrly.clearQueryHistory', () => {
+        queryHistory?.clear();
+    });
+    context.subscriptions.push(disposableClear);
+}
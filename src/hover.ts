@@ -0,0 +1,53 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { accessLabels } from './report';
+
+export function systemSummary(bevyrlyIndex: BevyrlyIndex, system: string): vscode.MarkdownString {
+    let summary = new vscode.MarkdownString();
+    summary.appendMarkdown("**bevyrly** system `" + system + "`\n\n");
+
+    const registrations = bevyrlyIndex.registrations.get(system) ?? [];
+    if (registrations.length == 0) {
+        summary.appendMarkdown("- Not registered with `add_systems`\n");
+    }
+    for (const reg of registrations) {
+        summary.appendMarkdown("- Schedule `" + reg.schedule + "`" +
+            (reg.conditions.length > 0 ? ", run if " + reg.conditions.map(condition => "`" + condition + "`").join(", ") : "") + "\n");
+    }
+
+    const accesses = bevyrlyIndex.accessesOf(system);
+    for (const [storage, label] of accessLabels) {
+        const keys = accesses.get(storage);
+        if (keys) {
+            summary.appendMarkdown("- " + label + ": " + keys.map(key => "`" + key + "`").join(", ") + "\n");
+        }
+    }
+
+    return summary;
+}
+
+class SystemHoverProvider implements vscode.HoverProvider {
+    private _bevyrlyIndex: BevyrlyIndex;
+
+    constructor(bevyrlyIndex: BevyrlyIndex) {
+        this._bevyrlyIndex = bevyrlyIndex;
+    }
+
+    provideHover(document: vscode.TextDocument, position: vscode.Position, _token: vscode.CancellationToken): vscode.Hover | undefined {
+        const range = document.getWordRangeAtPosition(position, /[A-Za-z_]\w*/);
+        if (!range) {
+            return undefined;
+        }
+
+        const word = document.getText(range);
+        if (!this._bevyrlyIndex.systems.has(word) && !this._bevyrlyIndex.registrations.has(word)) {
+            return undefined;
+        }
+
+        return new vscode.Hover(systemSummary(this._bevyrlyIndex, word), range);
+    }
+}
+
+export function registerHover(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    context.subscriptions.push(vscode.languages.registerHoverProvider({ language: 'rust' }, new SystemHoverProvider(bevyrlyIndex)));
+}
@@ -0,0 +1,44 @@
+import * as vscode from 'vscode';
+import { createHash } from 'crypto';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { expandLinkFromName, expandModuleFromName } from './extension';
+
+// A hash of the system's crate, module and name. It does not change across reindexes or edits to other code, or to the
+// system's own parameters and body, so baselines and links keep pointing at it; moving or renaming it gives a new ID.
+export function systemId(bevyrlyIndex: BevyrlyIndex, system: string): string {
+    const [crate, module] = expandModuleFromName(bevyrlyIndex, system) ?? ["", ""];
+    return createHash("sha1").update(crate + "\n" + module + "\n" + system).digest("hex").slice(0, 16);
+}
+
+export function systemById(bevyrlyIndex: BevyrlyIndex, id: string): string | undefined {
+    return bevyrlyIndex.derived("systemIds", () => {
+        let systems: Map<string, string> = new Map();
+        for (const system of bevyrlyIndex.locs.keys()) {
+            systems.set(systemId(bevyrlyIndex, system), system);
+        }
+        return systems;
+    }).get(id);
+}
+
+export function systemLink(bevyrlyIndex: BevyrlyIndex, system: string): string {
+    return vscode.env.uriScheme + "://DEVLIKE.bevyrly/system?id=" + systemId(bevyrlyIndex, system);
+}
+
+export function registerSystemLinks(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableCopy = vscode.commands.registerCommand('bevyrly.copySystemLink', async (system?: string) => {
+        await whenIndexed(context, bevyrlyIndex);
+        if (!system) {
+            const picked = await vscode.window.showQuickPick(Array.from(bevyrlyIndex.locs.keys()).sort().map(name => ({
+                label: name,
+                detail: expandLinkFromName(bevyrlyIndex, name)?.[1]
+            })), { title: 'Copy a link to system' });
+            system = picked?.label;
+        }
+
+        if (system) {
+            await vscode.env.clipboard.writeText(systemLink(bevyrlyIndex, system));
+            vscode.window.showInformationMessage("Copied a link to " + system);
+        }
+    });
+    context.subscriptions.push(disposableCopy);
+}
@@ -1,6 +1,21 @@
 import * as vscode from 'vscode';
-import { FunctionParameterDeclaration, Identifier, Loc, Node, StatementNode, TupleLiteral, TypeCall, TypeReference, TypeTuple, rs } from "jinx-rust";
+import { FunctionParameterDeclaration, Identifier, Node, StatementNode, TupleLiteral, TypeCall, TypeReference, TypeTuple, rs } from "jinx-rust";
 import { Uri } from 'vscode';
+import { PluginProvision, SystemRegistration, conditionResources, parseProvisions, parseRegistrations, parseTypeDefinitions } from './registrations';
+import { parseStateSources, parseStates, stateGates, stateWriters } from './states';
+import { ecosystemAccesses, parseTypeAliases } from './ecosystem';
+import { parseDynamicAccesses } from './dynamic';
+import { ParameterAccess, calledFunctions, parameterAccesses } from './calls';
+import { parameterPositions } from './params';
+import { CrateFeatures, loadCrateFeatures } from './cfg';
+import { gizmoGroups } from './gizmos';
+import { BlameInfo } from './blame';
+import { IndexCache, contentHash, recordSystems, replaySystems } from './cache';
+import { BevyVersions, IgnoreRule, ProjectConfig, isGeneratedSource, isIgnored, loadProjectConfig, matchesAny, parseGitignore } from './config';
+import { defaultSymbolTable, derivedMeanings, parseQueryPart, parseQueryPartWith, queryParts } from './symbols';
+import { assetSpawns, assetTypeUses, assetUses } from './assets';
+import { systemCategories } from './categories';
+import { systemTags } from './tags';
 
 function intersect_safe<T>(a: T[], b: T[]): T[] {
     return Array.from(new Set(b.filter(Set.prototype.has.bind(new Set(a)))));
@@ -18,7 +33,7 @@ type Adders
     | "addWith"
     | "addWithout";
 
-type QueryStorage
+export type QueryStorage
     = "event_write"
     | "event_read"
     | "query"
@@ -29,6 +44,17 @@ type QueryStorage
     | "without"
     | "systems";
 
+// The part of a jinx-rust `Loc` bevyrly relies on, so that systems loaded from the index cache can stand in for parsed ones.
+export interface SystemSpan {
+    0: number;
+    1: number;
+    src: { filepath?: string, l(offset: number): number };
+    getText(): string;
+}
+
+// Parameter types whose own name is not an access; only their type arguments are recorded.
+const wrapperTypes: Set<string> = new Set(["With", "Without", "Res", "ResMut", "Option", "Query", "Local", "NonSendMut"]);
+
 export class BevyrlyIndex {
     any: Map<string, Set<string>>;
     direct: Map<string, Set<string>>;
@@ -41,8 +67,37 @@ export class BevyrlyIndex {
     with: Map<string, Set<string>>;
     without: Map<string, Set<string>>;
     systems: Map<string, Set<string>>;
-    locs: Map<string, Loc>;
+    locs: Map<string, SystemSpan>;
+    registrations: Map<string, SystemRegistration[]>;
+    docs: Map<string, string>;
+    states: Map<string, string[]>;
+    // Sub-states and computed states -> the `State::Variant` (or `State`) keys they derive from.
+    stateSources: Map<string, string[]>;
+    provisions: PluginProvision[];
+    types: Map<string, [string, number]>;
+    sources: Map<string, string>;
+    // Milliseconds spent indexing each source file in the latest run.
+    timings: Map<string, number>;
+    blame: Map<string, BlameInfo>;
+    owners: Map<string, Set<string>>;
+    // `working-tree` and `since:<rev>` -> the systems in files changed by that measure, as resolved by `resolveChanged`.
+    changed: Map<string, Set<string>>;
+    // System -> the helper functions it calls, directly or through other helpers, whose accesses it was given.
+    calls: Map<string, Set<string>>;
+    // Crate directory -> the features its Cargo.toml declares, or undefined without a readable one.
+    crateFeatures: Map<string, CrateFeatures | undefined>;
+    configs: Map<string, ProjectConfig>;
+    lastQuery: string;
     isInitialized: boolean;
+    // Set by the first run and never reset, unlike `isInitialized`, which a cancelled run clears; watchers reindex
+    // only once the analyzer has been started.
+    hasStarted: boolean;
+    indexing: { source: vscode.CancellationTokenSource, done: Promise<void> } | undefined;
+    private _derived: Map<string, unknown> = new Map();
+    private _results: Map<string, [string[], "short" | "long"]> = new Map();
+    private _previous: [Map<string, string>, Map<string, string>, string] | undefined = undefined;
+    private _onDidIndex = new vscode.EventEmitter<void>();
+    readonly onDidIndex = this._onDidIndex.event;
 
     constructor() {
         this.any = new Map();
@@ -57,7 +112,24 @@ export class BevyrlyIndex {
         this.without = new Map();
         this.systems = new Map();
         this.locs = new Map();
+        this.registrations = new Map();
+        this.docs = new Map();
+        this.states = new Map();
+        this.stateSources = new Map();
+        this.provisions = [];
+        this.types = new Map();
+        this.sources = new Map();
+        this.timings = new Map();
+        this.blame = new Map();
+        this.owners = new Map();
+        this.changed = new Map();
+        this.calls = new Map();
+        this.crateFeatures = new Map();
+        this.configs = new Map();
+        this.lastQuery = "";
         this.isInitialized = false;
+        this.hasStarted = false;
+        this.indexing = undefined;
     }
 
     public toString(): string {
@@ -142,6 +214,7 @@ export class BevyrlyIndex {
     removeSystem(system: string) {
         if (this.systems.has(system)) {
             this.locs.delete(system);
+            this.docs.delete(system);
             const dets = this.systems.get(system);
             if (dets) {
                 for (const det of dets) {
@@ -161,33 +234,351 @@ export class BevyrlyIndex {
         }
     }
 
+    addRegistration(registration: SystemRegistration) {
+        if (!this.registrations.has(registration.system)) {
+            this.registrations.set(registration.system, []);
+        }
+
+        this.registrations.get(registration.system)?.push(registration);
+    }
+
+    accessesOf(system: string): Map<QueryStorage, string[]> {
+        let accesses: Map<QueryStorage, string[]> = new Map();
+        const storages: QueryStorage[] = ["query", "mut_query", "res", "mut_res", "event_read", "event_write", "with", "without"];
+        for (const storage of storages) {
+            const keys = Array.from(this[storage].entries())
+                .filter(([_, systems]) => systems.has(system))
+                .map(([key, _]) => key);
+            if (keys.length > 0) {
+                accesses.set(storage, keys);
+            }
+        }
+
+        return accesses;
+    }
+
+    // Schedule -> registered systems, in the same shape as the access maps so `@Update` works as a query part.
+    schedules(): Map<string, Set<string>> {
+        let schedules: Map<string, Set<string>> = new Map();
+        for (const reg of Array.from(this.registrations.values()).flat()) {
+            if (!schedules.has(reg.schedule)) {
+                schedules.set(reg.schedule, new Set());
+            }
+            schedules.get(reg.schedule)?.add(reg.system);
+        }
+
+        return schedules;
+    }
+
+    // Resource -> systems registered with a run condition on it, so `if:Score` works as a query part.
+    conditionResources(): Map<string, Set<string>> {
+        let resources: Map<string, Set<string>> = new Map();
+        for (const reg of Array.from(this.registrations.values()).flat()) {
+            for (const resource of reg.conditions.flatMap(conditionResources)) {
+                if (!resources.has(resource)) {
+                    resources.set(resource, new Set());
+                }
+                resources.get(resource)?.add(reg.system);
+            }
+        }
+
+        return resources;
+    }
+
+    schedulesOf(system: string): string[] {
+        return Array.from(new Set((this.registrations.get(system) ?? []).map(reg => reg.schedule)));
+    }
+
+    // Immediate `.before`/`.after`/`.chain()` neighbours as [runs after, runs before], from both sides of each edge.
+    orderingOf(system: string): [string[], string[]] {
+        let after: Set<string> = new Set();
+        let before: Set<string> = new Set();
+        for (const reg of Array.from(this.registrations.values()).flat()) {
+            if (reg.system == system) {
+                reg.after.forEach(other => after.add(other));
+                reg.before.forEach(other => before.add(other));
+            } else {
+                if (reg.before.includes(system)) after.add(reg.system);
+                if (reg.after.includes(system)) before.add(reg.system);
+            }
+        }
+
+        // Generic systems chained with themselves (`foo::<A>, foo::<B>`) aren't ordered against anything new.
+        after.delete(system);
+        before.delete(system);
+        return [Array.from(after).sort(), Array.from(before).sort()];
+    }
+
+    // Whole-project analyses (ambiguities, lints) are computed at most once per indexing run and shared by every
+    // view, diagnostic and API call that asks for them. They are dropped whenever the index changes.
+    derived<T>(key: string, compute: () => T): T {
+        if (!this._derived.has(key)) {
+            this.trimToBudget();
+            this._derived.set(key, compute());
+        }
+
+        return this._derived.get(key) as T;
+    }
+
+    // Over `bevyrly.memoryBudget`, cached analyses and query results go first; they are recomputed when next asked for.
+    trimToBudget() {
+        const budget = memoryBudget();
+        if (budget > 0 && process.memoryUsage().heapUsed > budget * 1024 * 1024) {
+            this._derived.clear();
+            this._results.clear();
+        }
+    }
+
+    fireDidIndex() {
+        this._derived.clear();
+        this._onDidIndex.fire();
+    }
+
     clear() {
         for (const system of this.systems.keys()) {
             this.removeSystem(system);
         }
+        // Functions without accesses have locs but no entry in `systems`, so `removeSystem` never reaches them.
+        this.locs.clear();
+        this.docs.clear();
+        this.registrations.clear();
+        this.states.clear();
+        this.stateSources.clear();
+        this.provisions = [];
+        this.types.clear();
+        this.sources.clear();
+        this.timings.clear();
+        this._derived.clear();
+        this.configs.clear();
+        this.blame.clear();
+        this.owners.clear();
+        this.changed.clear();
+        this.calls.clear();
+        this.crateFeatures.clear();
+    }
+
+    queryPart(part: string): [Map<string, Set<string>>, string] {
+        const [meaning, _, ident] = parseQueryPart(part);
+        switch (meaning) {
+            case undefined: return [this.any, ident];
+            case "schedule": return [this.schedules(), ident];
+            case "owner": return [this.owners, ident];
+            case "asset": return [assetUses(this), ident];
+            case "asset_spawn": return [assetSpawns(this), ident];
+            case "asset_type": return [assetTypeUses(this), ident];
+            case "category": return [systemCategories(this), ident];
+            case "tag": return [systemTags(this), ident];
+            case "res_condition": return [this.conditionResources(), ident];
+            case "state": return [stateGates(this), ident];
+            case "next_state": return [stateWriters(this), ident];
+            case "changed": return [this.changed, ident];
+            case "param": case "first": case "last": return [parameterPositions(this, meaning), ident];
+            case "gizmo_group": return [gizmoGroups(this), ident];
+            default: return [this[meaning], ident];
+        }
     }
 
+    matchedAccesses(s: string, system: string): string[] {
+        let matched: string[] = [];
+        if (s.startsWith(":")) {
+            s = s.slice(1).trim();
+        }
+
+        for (const part of queryParts(s)) {
+            let [map, ident] = this.queryPart(part);
+            let prefix = parseQueryPart(part)[1];
+            for (const [key, systems] of map.entries()) {
+                if (key !== undefined && key.includes(ident) && systems.has(system)) {
+                    matched.push(prefix + key);
+                }
+            }
+        }
+
+        return Array.from(new Set(matched));
+    }
+
+    // Query results are cached by query text until a reindex changes a file defining a system that was in the result or
+    // now matches it. Schedule and owner parts depend on data outside the system's own file, so those always re-run.
     get(s: string): [string[], "short" | "long"] {
+        if (this._previous) {
+            return this.evaluate(s);
+        }
+
+        let cached = this._results.get(s);
+        if (!cached) {
+            this.trimToBudget();
+            cached = this.evaluate(s);
+            this._results.set(s, cached);
+        }
+
+        return [cached[0].slice(), cached[1]];
+    }
+
+    matches(s: string, system: string): boolean {
+        if (s.startsWith(":")) {
+            s = s.slice(1).trim();
+        }
+
+        return queryParts(s).every(part => {
+            const [map, ident] = this.queryPart(part);
+            return Array.from(map.entries()).some(([key, systems]) => key !== undefined && key.includes(ident) && systems.has(system));
+        });
+    }
+
+    // Drops the cached results `changed` systems could affect, or every one without `changed`.
+    invalidateResults(changed?: Set<string>) {
+        if (!changed) {
+            this._results.clear();
+            return;
+        }
+        for (const [query, [systems, _]] of this._results) {
+            const structural = queryParts(query.replace(/^:/, "")).some(part => {
+                const meaning = parseQueryPart(part)[0];
+                return meaning !== undefined && derivedMeanings.includes(meaning);
+            });
+            if (structural || systems.some(system => changed.has(system)) || Array.from(changed).some(system => this.matches(query, system))) {
+                this._results.delete(query);
+            }
+        }
+    }
+
+    // Remembers each file's text and where each system lives, so `endIndexing` can tell which systems a reindex touched.
+    // A cancelled run never reaches `endIndexing`, so the next run keeps comparing against the last complete index.
+    beginIndexing() {
+        if (this._previous) {
+            return;
+        }
+
+        let files: Map<string, string> = new Map();
+        for (const [system, loc] of this.locs) {
+            files.set(system, loc.src.filepath ?? "");
+        }
+        this._previous = [new Map(this.sources), files, this._wrapperKey()];
+    }
+
+    private _wrapperKey(): string {
+        return JSON.stringify(Array.from(this.configs.entries()).map(([folder, config]) => [folder, Array.from(config.wrappers), config.helperDepth]));
+    }
+
+    // `part` comes from bevyrly.toml or the built-in ecosystem parameters, so it's read with the built-in symbols.
+    private _addAccess(system: string, part: string) {
+        const [meaning, _, type] = parseQueryPartWith(defaultSymbolTable, part);
+        if (meaning === undefined) {
+            this.addDirect(system, type);
+        } else if (!derivedMeanings.includes(meaning)) {
+            this.addQueryStorage(system, type, meaning);
+        }
+    }
+
+    // Gives every system of `folder` taking a declared wrapper type the accesses `bevyrly.toml` lists for it, and those
+    // taking a known ecosystem parameter (egui contexts, spatial trees, ...) the accesses it is known to have.
+    applyWrappers(folder: string, config: ProjectConfig) {
+        const inFolder = (system: string) => (this.locs.get(system)?.src.filepath ?? "").startsWith(folder + "/");
+        for (const [wrapper, parts] of config.wrappers) {
+            for (const system of Array.from(this.direct.get(wrapper) ?? [])) {
+                if (!inFolder(system)) continue;
+                parts.forEach(part => this._addAccess(system, part));
+            }
+        }
+
+        let aliases: Map<string, string> = new Map();
+        for (const text of this.sources.values()) {
+            parseTypeAliases(text).forEach((type, alias) => aliases.set(alias, type));
+        }
+        for (const [system, loc] of Array.from(this.locs)) {
+            if (!inFolder(system)) continue;
+            ecosystemAccesses(loc.getText(), aliases, config.wrappers).forEach(part => this._addAccess(system, part));
+        }
+    }
+
+    // Gives the systems of `folder` the accesses of the helper functions they call, up to `depth` calls deep: a system
+    // calling `spawn_smoke(&mut commands, &image_assets)` uses what `spawn_smoke`'s parameters ask for and what its body
+    // reaches through the world, and so on for the helpers `spawn_smoke` calls. Registered systems aren't helpers, even
+    // when another system calls them directly.
+    applyHelperCalls(folder: string, depth: number) {
+        const inFolder = (system: string) => (this.locs.get(system)?.src.filepath ?? "").startsWith(folder + "/");
+        const apply = (system: string, accesses: ParameterAccess[]) => accesses.forEach(([storage, type]) =>
+            storage == "direct" ? this.addDirect(system, type) : this.addQueryStorage(system, type, storage));
+
+        let called: Map<string, string[]> = new Map();
+        const calledBy = (caller: string) => {
+            if (!called.has(caller)) {
+                called.set(caller, calledFunctions(this.locs.get(caller)?.getText() ?? "")
+                    .filter(name => name != caller && this.locs.has(name) && !this.registrations.has(name)));
+            }
+            return called.get(caller) ?? [];
+        };
+        let helpers: Map<string, ParameterAccess[]> = new Map();
+        const accessesOf = (helper: string) => {
+            if (!helpers.has(helper)) {
+                const text = this.locs.get(helper)?.getText() ?? "";
+                helpers.set(helper, [...parameterAccesses(text), ...parseDynamicAccesses(text).get(helper) ?? []]);
+            }
+            return helpers.get(helper) ?? [];
+        };
+
+        for (const system of Array.from(this.locs.keys())) {
+            if (!inFolder(system)) continue;
+            apply(system, parameterAccesses(this.locs.get(system)?.getText() ?? ""));
+
+            let reached: Set<string> = new Set();
+            let frontier = [system];
+            for (let level = 0; level < depth && frontier.length > 0; level++) {
+                frontier = frontier.flatMap(calledBy).filter(helper => helper != system && !reached.has(helper));
+                frontier.forEach(helper => reached.add(helper));
+            }
+            if (reached.size == 0) continue;
+
+            this.calls.set(system, reached);
+            reached.forEach(helper => apply(system, accessesOf(helper)));
+        }
+    }
+
+    endIndexing() {
+        if (!this._previous) {
+            return;
+        }
+
+        const [sources, files, wrappers] = this._previous;
+        this._previous = undefined;
+        // Declared wrapper types and the helper depth change accesses without changing any source.
+        if (wrappers != this._wrapperKey()) {
+            this._results.clear();
+        }
+        let changedFiles: Set<string> = new Set();
+        for (const filepath of new Set([...sources.keys(), ...this.sources.keys()])) {
+            if (sources.get(filepath) !== this.sources.get(filepath)) {
+                changedFiles.add(filepath);
+            }
+        }
+
+        let changed: Set<string> = new Set();
+        for (const [system, filepath] of files) {
+            if (changedFiles.has(filepath)) changed.add(system);
+        }
+        for (const [system, loc] of this.locs) {
+            if (changedFiles.has(loc.src.filepath ?? "") || files.get(system) != loc.src.filepath) changed.add(system);
+        }
+        // Callers take on their helpers' accesses, so they change with them.
+        for (const [system, helpers] of this.calls) {
+            if (Array.from(helpers).some(helper => changed.has(helper))) changed.add(system);
+        }
+
+        // Edits change what git reports, so `changed:` parts are asked again.
+        this.changed.clear();
+        this.invalidateResults(changed);
+        this.trimToBudget();
+    }
+
+    evaluate(s: string): [string[], "short" | "long"] {
         let all_systems = Array.from(this.systems.keys());
         let long_print = s.startsWith(":");
         if (long_print) {
             s = s.slice(1).trim();
         }
 
-        for (const part of s.split(" ")) {
-            let ident = part.slice(1);
-            let map: Map<string, Set<string>>;
-            switch (part.at(0)) {
-                case '&': map = this.query; break;
-                case '*': map = this.mut_query; break;
-                case '!': map = this.event_write; break;
-                case '?': map = this.event_read; break;
-                case '#': map = this.res; break;
-                case '$': map = this.mut_res; break;
-                case '+': map = this.with; break;
-                case '-': map = this.without; break;
-                default: map = this.any; ident = part; break;
-            }
+        for (const part of queryParts(s)) {
+            let [map, ident] = this.queryPart(part);
 
             let layer = Array.from(map.keys())
                 .filter(key => (key === undefined) ? false : key.includes(ident))
@@ -210,7 +601,7 @@ export class BevyrlyIndex {
 
         if (obj instanceof TypeCall) {
             let name = obj.typeCallee.name;
-            if (!new Set(["With", "Without", "Res", "ResMut", "Option", "Query", "Local", "NonSendMut"]).has(name)) {
+            if (!wrapperTypes.has(name)) {
                 this[item](system_name, name);
             }
             for (const sub of obj.typeArguments.values()) {
@@ -364,13 +755,55 @@ export class BevyrlyIndex {
 
 export let bevyrlyLog: string = "";
 
-async function recursiveReadDirectory(path: Uri): Promise<Uri[]> {
+export function parseDocComments(text: string): Map<string, string> {
+    let docs: Map<string, string> = new Map();
+    let pending: string[] = [];
+    for (const line of text.split(/\r?\n/)) {
+        const trimmed = line.trim();
+        if (trimmed.startsWith("///")) {
+            pending.push(trimmed.slice(3).trim());
+            continue;
+        } else if (trimmed.startsWith("#[")) {
+            continue;
+        }
+
+        const found = trimmed.match(/^(?:pub(?:\([^)]*\))?\s+)?(?:async\s+)?fn\s+(\w+)/);
+        if (found && pending.length > 0) {
+            docs.set(found[1], pending.join("\n"));
+        }
+        pending = [];
+    }
+
+    return docs;
+}
+
+async function readGitignore(directory: Uri): Promise<IgnoreRule[]> {
+    try {
+        return parseGitignore(new TextDecoder().decode(await vscode.workspace.fs.readFile(Uri.joinPath(directory, ".gitignore"))), directory.path);
+    } catch {
+        return [];
+    }
+}
+
+// With `ignores` given, `.gitignore` files met along the way add to them for their own subtree.
+async function recursiveReadDirectory(
+    path: Uri, skip: (uri: Uri, directory: boolean) => boolean = () => false, ignores?: IgnoreRule[]
+): Promise<Uri[]> {
     let result = [];
     let dir = await vscode.workspace.fs.readDirectory(path);
+    if (ignores && dir.some(([sub, type]) => sub == ".gitignore" && type == vscode.FileType.File)) {
+        ignores = [...ignores, ...await readGitignore(path)];
+    }
+
     for (let [sub, type] of dir) {
-        if (type == vscode.FileType.Directory) {
+        const directory = type == vscode.FileType.Directory;
+        if (skip(Uri.joinPath(path, sub), directory) || (ignores && isIgnored(ignores, Uri.joinPath(path, sub).path, directory))) {
+            continue;
+        }
+
+        if (directory) {
             bevyrlyLog += "Accessing dir " + sub + "<br>";
-            for (let add of await recursiveReadDirectory(Uri.joinPath(path, sub))) {
+            for (let add of await recursiveReadDirectory(Uri.joinPath(path, sub), skip, ignores)) {
                 result.push(add);
             }
         } else {
@@ -385,44 +818,260 @@ async function recursiveReadDirectory(path: Uri): Promise<Uri[]> {
     return result;
 }
 
-export async function startBevyrlyIndexing(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
-    const config = vscode.workspace.getConfiguration('bevyrly');
-    const src = config.get<string>('sourceFolder') ?? "src";
+function indexFunctions(bevyrlyIndex: BevyrlyIndex, text: string, filepath: string) {
+    let ast = rs.parseFile(text, { filepath }).program.ast;
+    for (const node of ast.values()) {
+        if (node.nodeType == 38) {
+            bevyrlyLog += "    Adding function " + node.id.name + ".<br />";
+            bevyrlyIndex.addFunctionNode(node);
+        } else if (node.nodeType == 54) {
+            for (const sub of node.body.values()) {
+                if (sub.nodeType == 38) {
+                    bevyrlyLog += "    Adding function " + sub.id.name + ".<br />";
+                    bevyrlyIndex.addFunctionNode(sub);
+                }
+            }
+        }
+    }
+}
+
+// Megabytes of extension host heap bevyrly tries to stay under, or 0 for no limit.
+export function memoryBudget(): number {
+    return vscode.workspace.getConfiguration('bevyrly').get<number>('memoryBudget') ?? 0;
+}
+
+// With a cache, unchanged files skip parsing: their systems are replayed from the records stored for the same content.
+// Under a memory budget, parsed files are replayed as well, so that no syntax tree outlives the indexing of its file.
+// `bevyVersion` is the Bevy version of the file's crate, which decides how its registrations are recognized.
+export function indexSourceFile(bevyrlyIndex: BevyrlyIndex, text: string, filepath: string, cache?: IndexCache, bevyVersion?: string) {
+    const start = performance.now();
+    bevyrlyIndex.sources.set(filepath, text);
+    if (cache || memoryBudget() > 0) {
+        const hash = contentHash(text);
+        let systems = cache?.lookup(filepath, hash);
+        if (!systems) {
+            let scratch = new BevyrlyIndex();
+            indexFunctions(scratch, text, filepath);
+            systems = recordSystems(scratch);
+            cache?.store(filepath, hash, systems);
+        }
+        replaySystems(bevyrlyIndex, systems, text, filepath);
+    } else {
+        indexFunctions(bevyrlyIndex, text, filepath);
+    }
+
+    for (const [system, accesses] of parseDynamicAccesses(text)) {
+        if (bevyrlyIndex.locs.get(system)?.src.filepath != filepath) continue;
+        for (const [storage, type] of accesses) {
+            bevyrlyIndex.addQueryStorage(system, type, storage);
+        }
+    }
+
+    for (const registration of parseRegistrations(text, filepath, bevyVersion)) {
+        bevyrlyIndex.addRegistration(registration);
+    }
+
+    bevyrlyIndex.provisions.push(...parseProvisions(text, filepath));
+
+    for (const [name, line] of parseTypeDefinitions(text)) {
+        bevyrlyIndex.types.set(name, [filepath, line]);
+    }
+
+    for (const [state, variants] of parseStates(text)) {
+        bevyrlyIndex.states.set(state, variants);
+    }
+
+    for (const [state, sources] of parseStateSources(text)) {
+        bevyrlyIndex.stateSources.set(state, sources);
+    }
 
+    for (const [system, doc] of parseDocComments(text)) {
+        if (bevyrlyIndex.systems.has(system)) {
+            bevyrlyIndex.docs.set(system, doc);
+        }
+    }
+    bevyrlyIndex.timings.set(filepath, performance.now() - start);
+}
+
+// Reads `files` with up to `concurrency` reads in flight, preferring the contents of open (possibly unsaved) editors.
+async function readSourceFiles(files: Uri[], concurrency: number): Promise<string[]> {
+    let texts: string[] = new Array(files.length);
+    let next = 0;
+    const worker = async () => {
+        while (next < files.length) {
+            const i = next++;
+            const open = vscode.workspace.textDocuments.find(doc => doc.uri.toString() == files[i].toString());
+            texts[i] = open ? open.getText() : new TextDecoder().decode(await vscode.workspace.fs.readFile(files[i]));
+        }
+    };
+
+    await Promise.all(Array.from({ length: Math.max(1, Math.min(concurrency, files.length)) }, worker));
+    return texts;
+}
+
+// Where the walk of a folder starts: `include` globs in bevyrly.toml widen it to the whole folder, otherwise it's the
+// source folder, which each root of a multi-root workspace can set on its own.
+function walkRoot(folder: vscode.WorkspaceFolder, config: ProjectConfig): Uri {
+    const src = vscode.workspace.getConfiguration('bevyrly', folder.uri).get<string>('sourceFolder') ?? "src";
+    return config.include.length > 0 ? folder.uri : Uri.joinPath(folder.uri, src);
+}
+
+// What the walk leaves out: paths matching `exclude` (from the settings and bevyrly.toml) either way, and files
+// matching no `include` glob when there are any.
+function skipsPath(folder: vscode.WorkspaceFolder, config: ProjectConfig): (uri: Uri, directory: boolean) => boolean {
+    const relative = (uri: Uri) => uri.path.slice(folder.uri.path.length + 1);
+    const exclude = [...vscode.workspace.getConfiguration('bevyrly', folder.uri).get<string[]>('exclude') ?? [], ...config.exclude];
+    return (uri, directory) =>
+        matchesAny(exclude, directory ? relative(uri) + "/" : relative(uri)) ||
+        matchesAny(exclude, relative(uri)) ||
+        (!directory && config.include.length > 0 && !matchesAny(config.include, relative(uri)));
+}
+
+// Whether a reindex would read `uri`, by the same root, globs and `.gitignore` files as the walk.
+export async function isIndexedSource(bevyrlyIndex: BevyrlyIndex, uri: Uri): Promise<boolean> {
+    const folder = vscode.workspace.getWorkspaceFolder(uri);
+    if (!folder || !uri.path.endsWith(".rs")) {
+        return false;
+    }
+
+    const config = bevyrlyIndex.configs.get(folder.uri.toString()) ?? await loadProjectConfig(folder.uri);
+    const root = walkRoot(folder, config);
+    if (!uri.path.startsWith(root.path + "/")) {
+        return false;
+    }
+
+    const skip = skipsPath(folder, config);
+    const gitignore = vscode.workspace.getConfiguration('bevyrly', folder.uri).get<boolean>('respectGitignore') ?? true;
+    let ignores: IgnoreRule[] = [];
+    const segments = uri.path.slice(folder.uri.path.length + 1).split("/");
+    let directory = folder.uri;
+    for (const [i, segment] of segments.entries()) {
+        if (gitignore) {
+            ignores.push(...await readGitignore(directory));
+        }
+        const child = Uri.joinPath(directory, segment);
+        const isDirectory = i < segments.length - 1;
+        if (child.path.startsWith(root.path + "/") && (skip(child, isDirectory) || isIgnored(ignores, child.path, isDirectory))) {
+            return false;
+        }
+        directory = child;
+    }
+    return true;
+}
+
+async function indexWorkspace(
+    context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex, token: vscode.CancellationToken,
+    progress: vscode.Progress<{ message?: string }>
+) {
+    bevyrlyIndex.beginIndexing();
     bevyrlyIndex.clear();
-    bevyrlyLog += "Clearing bevyrly...<br />";
+    // The log covers the latest run only, so repeated reindexing does not grow it without bound.
+    bevyrlyLog = "Clearing bevyrly...<br />";
+    const cache = await IndexCache.load(context);
     if (vscode.workspace.workspaceFolders) {
         // commands
         bevyrlyLog += "Found workspace...<br />";
         for (const folder of vscode.workspace.workspaceFolders) {
-            let path = Uri.joinPath(folder.uri, src);
+            const config = await loadProjectConfig(folder.uri);
+            bevyrlyIndex.configs.set(folder.uri.toString(), config);
+
+            const path = walkRoot(folder, config);
+            const relative = (uri: Uri) => uri.path.slice(folder.uri.path.length + 1);
             bevyrlyLog += "Reading dir " + path + "...<br />";
-            recursiveReadDirectory(path).then(async (r: Uri[]) => {
-                for (const file of r) {
-                    bevyrlyLog += "  Found file " + file + ".<br />";
-                    await vscode.workspace.openTextDocument(file).then((f: vscode.TextDocument) => {
-                        let ast = rs.parseFile(f.getText(), { filepath: file.toString() }).program.ast;
-                        for (const node of ast.values()) {
-                            if (node.nodeType == 38) {
-                                bevyrlyLog += "    Adding function " + JSON.stringify(node.toJSON()) + ".<br />";
-                                bevyrlyIndex.addFunctionNode(node);
-                            } else if (node.nodeType == 54) {
-                                for (const sub of node.body.values()) {
-                                    if (sub.nodeType == 38) {
-                                        bevyrlyLog += "    Adding function " + sub.toJSON() + ".<br />";
-                                        bevyrlyIndex.addFunctionNode(sub);
-                                    }
-                                }
-                            }
-                        }
-                    });
+            progress.report({ message: "reading " + folder.name });
+            const settings = vscode.workspace.getConfiguration('bevyrly', folder.uri);
+            // The .gitignore files of the folders above the walk's starting point apply too.
+            let ignores: IgnoreRule[] | undefined = undefined;
+            if (settings.get<boolean>('respectGitignore') ?? true) {
+                ignores = [];
+                for (let above = folder.uri; above.path != path.path && path.path.startsWith(above.path + "/");
+                    above = Uri.joinPath(above, path.path.slice(above.path.length + 1).split("/")[0])) {
+                    ignores.push(...await readGitignore(above));
+                }
+            }
+            const r = await recursiveReadDirectory(path, skipsPath(folder, config), ignores);
+            const skipGenerated = settings.get<boolean>('skipGeneratedFiles') ?? true;
+            const versions = new BevyVersions(config, async path => {
+                try {
+                    return new TextDecoder().decode(await vscode.workspace.fs.readFile(Uri.joinPath(folder.uri, path)));
+                } catch {
+                    return undefined;
                 }
-            }).then(_ => {
-                bevyrlyIndex.isInitialized = true;
-                console.log(bevyrlyIndex);
-                bevyrlyLog += "Bevyrly reinitialized.<br />";
-                bevyrlyLog += JSON.stringify(bevyrlyIndex);
             });
+            // Reading is I/O bound and runs concurrently; parsing then happens in file order so the index is deterministic.
+            const concurrency = vscode.workspace.getConfiguration('bevyrly').get<number>('indexingConcurrency') ?? 8;
+            const texts = await readSourceFiles(r, concurrency);
+            for (const [i, file] of r.entries()) {
+                // Yield now and then, so that commands and queries stay responsive and a newer run can take over.
+                if (i % 25 == 0) {
+                    progress.report({ message: folder.name + " " + i + "/" + r.length });
+                    await new Promise(resolve => setImmediate(resolve));
+                }
+                if (token.isCancellationRequested) {
+                    bevyrlyIndex.isInitialized = false;
+                    bevyrlyLog += "Bevyrly indexing cancelled.<br />";
+                    return;
+                }
+
+                if (skipGenerated && isGeneratedSource(texts[i])) {
+                    bevyrlyLog += "  Skipping generated file " + file + ".<br />";
+                    continue;
+                }
+
+                const bevyVersion = await versions.of(relative(file));
+                bevyrlyLog += "  Found file " + file + (bevyVersion ? " (Bevy " + bevyVersion + ")" : "") + ".<br />";
+                indexSourceFile(bevyrlyIndex, texts[i], file.toString(), cache, bevyVersion);
+                await loadCrateFeatures(bevyrlyIndex, file.toString());
+            }
+            bevyrlyIndex.applyWrappers(folder.uri.toString(), config);
+            bevyrlyIndex.applyHelperCalls(folder.uri.toString(), config.helperDepth);
+        }
+
+        // Listeners run once, against the index of every folder.
+        bevyrlyIndex.isInitialized = true;
+        bevyrlyIndex.fireDidIndex();
+        bevyrlyLog += "Bevyrly reinitialized: " + bevyrlyIndex.locs.size + " functions in " + bevyrlyIndex.sources.size + " files.<br />";
+    }
+
+    bevyrlyIndex.endIndexing();
+    await cache?.save();
+}
+
+// Reindexes in the background with progress in the status bar. A run already in progress is cancelled and awaited
+// first, so only one run writes to the index at a time; the returned promise settles when this run ends.
+export async function startBevyrlyIndexing(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    const previous = bevyrlyIndex.indexing;
+    previous?.source.cancel();
+    bevyrlyIndex.hasStarted = true;
+
+    const source = new vscode.CancellationTokenSource();
+    const done = (async () => {
+        await previous?.done;
+        if (source.token.isCancellationRequested) {
+            return;
+        }
+
+        await vscode.window.withProgress({ location: vscode.ProgressLocation.Window, title: "bevyrly" }, progress =>
+            indexWorkspace(context, bevyrlyIndex, source.token, progress));
+    })();
+
+    bevyrlyIndex.indexing = { source, done };
+    try {
+        await done;
+    } finally {
+        if (bevyrlyIndex.indexing?.source === source) {
+            bevyrlyIndex.indexing = undefined;
         }
+        source.dispose();
+    }
+}
+
+// Waits for the run in progress, or starts one if the index was never built; used before reading the index.
+export async function whenIndexed(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    if (bevyrlyIndex.indexing) {
+        await bevyrlyIndex.indexing.done;
+    }
+    if (!bevyrlyIndex.isInitialized) {
+        await startBevyrlyIndexing(context, bevyrlyIndex);
     }
 }
@@ -0,0 +1,86 @@
+import { BevyrlyIndex } from '.';
+import { parseSpawns } from './archetypes';
+import { blankComments, matchingClose } from './registrations';
+
+const cameras = new Set(["Camera", "Camera2d", "Camera3d", "Camera2dBundle", "Camera3dBundle"]);
+const renderables = new Set([
+    "Sprite", "SpriteBundle", "SpriteSheetBundle", "Text2d", "Text2dBundle", "Mesh2d", "Mesh3d", "Mesh2dHandle",
+    "PbrBundle", "MaterialMeshBundle", "MaterialMesh2dBundle", "ColorMesh2dBundle", "SceneRoot", "SceneBundle",
+]);
+
+export interface RenderLayerSite {
+    system: string;
+    camera: boolean;
+    // The layers the spawned entity is on; without a `RenderLayers` it's on layer 0.
+    layers: number[];
+    // `RenderLayers::all()`, on every layer.
+    all: boolean;
+    filepath: string;
+    line: number;
+    // The site whose `.with_children(..)` spawned this one, when that's rendered too.
+    parent?: RenderLayerSite;
+}
+
+// `const UI_LAYER: usize = 1;` declarations, so that `RenderLayers::layer(UI_LAYER)` resolves.
+function layerConstants(bevyrlyIndex: BevyrlyIndex): Map<string, number> {
+    let constants: Map<string, number> = new Map();
+    for (const text of bevyrlyIndex.sources.values()) {
+        for (const found of blankComments(text).matchAll(/\bconst\s+(\w+)\s*:\s*(?:usize|u8|u32|Layer)\s*=\s*(\d+)\s*;/g)) {
+            constants.set(found[1], Number(found[2]));
+        }
+    }
+    return constants;
+}
+
+// The layers named by the `RenderLayers` in a spawn's text: `layer(n)`, `from_layers(&[..])` and chained `.with(n)`.
+// Undefined when a layer isn't a literal or a known constant.
+function layersOf(text: string, constants: Map<string, number>): number[] | "all" | undefined {
+    const start = text.search(/\bRenderLayers\s*::/);
+    if (start < 0) return [0];
+    const expr = text.slice(start).match(/^RenderLayers\s*::\s*(\w+)\s*\(([^)]*)\)((?:\s*\.\s*(?:with|without)\s*\([^)]*\))*)/);
+    if (!expr) return undefined;
+    if (expr[1] == "all") return "all";
+
+    const resolve = (value: string) => /^\d+$/.test(value) ? Number(value) : constants.get(value.split("::").pop() ?? "");
+    let layers: Set<number | undefined> = new Set(expr[1] == "none" ? [] : expr[2].replace(/[&\[\]]/g, "").split(",")
+        .map(value => value.trim()).filter(value => value.length > 0).map(resolve));
+    for (const call of expr[3].matchAll(/\.\s*(with|without)\s*\(\s*([\w:]+)\s*\)/g)) {
+        const layer = resolve(call[2]);
+        call[1] == "with" ? layers.add(layer) : layers.delete(layer);
+    }
+    return layers.has(undefined) ? undefined : Array.from(layers) as number[];
+}
+
+// Every camera and rendered entity (sprites, 2d text, meshes, scenes) spawned by a system, with its render layers.
+// Spawns whose layers bevyrly can't work out are left out.
+export function renderLayerSites(bevyrlyIndex: BevyrlyIndex): RenderLayerSite[] {
+    return bevyrlyIndex.derived("render-layers", () => {
+        const constants = layerConstants(bevyrlyIndex);
+        let sites: RenderLayerSite[] = [];
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            const text = blankComments(loc.getText());
+            let spawned: [number, number, RenderLayerSite][] = [];
+            for (const site of parseSpawns(text)) {
+                const camera = site.components.some(component => cameras.has(component));
+                if (!camera && !site.components.some(component => renderables.has(component))) continue;
+
+                const layers = layersOf(text.slice(site.offset, site.end), constants);
+                if (layers === undefined) continue;
+                const children = text.slice(site.end).match(/^\s*\.\s*with_children\s*\(/);
+                const close = children ? matchingClose(text, site.end + children[0].length - 1) : -1;
+                const current: RenderLayerSite = {
+                    system,
+                    camera,
+                    layers: layers == "all" ? [] : layers,
+                    all: layers == "all",
+                    filepath: loc.src.filepath ?? "",
+                    line: loc.src.l(loc[0] + site.offset),
+                    parent: spawned.filter(([start, end, _]) => start < site.offset && site.offset < end).pop()?.[2],
+                };
+                spawned.push([site.end, close, current]);
+                sites.push(current);
+            }
+        }
+        return sites;
+    });
+}
@@ -0,0 +1,150 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { blankComments, observerLines, parseDerives } from './registrations';
+import { expandLinkFromName, revealSystem } from './extension';
+import { symbolOf } from './symbols';
+
+// Generic types like `ToggleUI<HpBar>` are indexed with their arguments, so they're matched by prefix as well.
+export function systemsUsing(map: Map<string, Set<string>>, name: string): string[] {
+    let systems: Set<string> = new Set();
+    for (const [key, users] of map) {
+        if (key == name || key.startsWith(name + "<")) {
+            users.forEach(system => systems.add(system));
+        }
+    }
+
+    return Array.from(systems).sort();
+}
+
+function plural(count: number, word: string): string {
+    return count + " " + word + (count == 1 ? "" : "s");
+}
+
+class DeriveLensProvider implements vscode.CodeLensProvider {
+    private _bevyrlyIndex: BevyrlyIndex;
+    private _onDidChangeCodeLenses = new vscode.EventEmitter<void>();
+    readonly onDidChangeCodeLenses = this._onDidChangeCodeLenses.event;
+
+    constructor(bevyrlyIndex: BevyrlyIndex) {
+        this._bevyrlyIndex = bevyrlyIndex;
+        bevyrlyIndex.onDidIndex(() => this._onDidChangeCodeLenses.fire());
+    }
+
+    provideCodeLenses(document: vscode.TextDocument, _token: vscode.CancellationToken): vscode.CodeLens[] {
+        let lenses: vscode.CodeLens[] = [];
+        for (const type of parseDerives(document.getText())) {
+            const position = document.positionAt(type.offset);
+            const range = new vscode.Range(position, position);
+
+            if (type.derives.includes("Event")) {
+                const writers = systemsUsing(this._bevyrlyIndex.event_write, type.name);
+                const readers = systemsUsing(this._bevyrlyIndex.event_read, type.name);
+                lenses.push(new vscode.CodeLens(range, {
+                    title: plural(writers.length, "writer") + " · " + plural(readers.length, "reader"),
+                    command: 'bevyrly.listSystems',
+                    arguments: [type.name, [...writers.map(system => [system, "writer"]), ...readers.map(system => [system, "reader"])]]
+                }));
+            }
+
+            if (type.derives.includes("Component")) {
+                const queried = new Set([this._bevyrlyIndex.query, this._bevyrlyIndex.mut_query, this._bevyrlyIndex.with, this._bevyrlyIndex.without]
+                    .flatMap(map => systemsUsing(map, type.name)));
+                const mutated = systemsUsing(this._bevyrlyIndex.mut_query, type.name);
+                lenses.push(new vscode.CodeLens(range, {
+                    title: "queried in " + plural(queried.size, "system"),
+                    command: 'bevyrly.runQuery',
+                    arguments: [type.name]
+                }));
+                lenses.push(new vscode.CodeLens(range, {
+                    title: "mutated in " + mutated.length,
+                    command: 'bevyrly.runQuery',
+                    arguments: [symbolOf("mut_query") + type.name]
+                }));
+            }
+
+            if (type.derives.includes("Resource")) {
+                const read = systemsUsing(this._bevyrlyIndex.res, type.name);
+                const mutated = systemsUsing(this._bevyrlyIndex.mut_res, type.name);
+                lenses.push(new vscode.CodeLens(range, {
+                    title: "read in " + plural(read.length, "system"),
+                    command: 'bevyrly.runQuery',
+                    arguments: [symbolOf("res") + type.name]
+                }));
+                lenses.push(new vscode.CodeLens(range, {
+                    title: "mutated in " + mutated.length,
+                    command: 'bevyrly.runQuery',
+                    arguments: [symbolOf("mut_res") + type.name]
+                }));
+            }
+        }
+
+        return lenses;
+    }
+}
+
+// Every `add_systems` (including pipes and generic instantiations) and `add_observer` site registering `system`.
+export function registrationSites(bevyrlyIndex: BevyrlyIndex, system: string): vscode.Location[] {
+    let sites = (bevyrlyIndex.registrations.get(system) ?? [])
+        .map(reg => new vscode.Location(vscode.Uri.parse(reg.filepath), new vscode.Position(reg.line, 0)));
+    for (const [filepath, text] of bevyrlyIndex.sources) {
+        for (const line of observerLines(text, system)) {
+            sites.push(new vscode.Location(vscode.Uri.parse(filepath), new vscode.Position(line, 0)));
+        }
+    }
+
+    return sites;
+}
+
+class RegistrationLensProvider implements vscode.CodeLensProvider {
+    private _bevyrlyIndex: BevyrlyIndex;
+    private _onDidChangeCodeLenses = new vscode.EventEmitter<void>();
+    readonly onDidChangeCodeLenses = this._onDidChangeCodeLenses.event;
+
+    constructor(bevyrlyIndex: BevyrlyIndex) {
+        this._bevyrlyIndex = bevyrlyIndex;
+        bevyrlyIndex.onDidIndex(() => this._onDidChangeCodeLenses.fire());
+    }
+
+    provideCodeLenses(document: vscode.TextDocument, _token: vscode.CancellationToken): vscode.CodeLens[] {
+        let lenses: vscode.CodeLens[] = [];
+        const text = blankComments(document.getText());
+        const pattern = /\bfn\s+(\w+)/g;
+        let found;
+        while ((found = pattern.exec(text)) !== null) {
+            const system = found[1];
+            if (!this._bevyrlyIndex.systems.has(system)) continue;
+
+            const position = document.positionAt(found.index);
+            const sites = registrationSites(this._bevyrlyIndex, system);
+            lenses.push(new vscode.CodeLens(new vscode.Range(position, position), sites.length == 0 ? {
+                title: "not registered",
+                command: ''
+            } : {
+                title: "registered in " + plural(sites.length, "place"),
+                command: 'editor.action.showReferences',
+                arguments: [document.uri, position, sites]
+            }));
+        }
+
+        return lenses;
+    }
+}
+
+export function registerCodeLenses(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    context.subscriptions.push(vscode.languages.registerCodeLensProvider({ language: 'rust' }, new DeriveLensProvider(bevyrlyIndex)));
+    context.subscriptions.push(vscode.languages.registerCodeLensProvider({ language: 'rust' }, new RegistrationLensProvider(bevyrlyIndex)));
+
+    let disposableList = vscode.commands.registerCommand('bevyrly.listSystems', async (title: string, systems: [string, string][]) => {
+        const picked = await vscode.window.showQuickPick(systems.map(([system, role]) => ({
+            label: system,
+            description: role,
+            detail: expandLinkFromName(bevyrlyIndex, system)?.[1]
+        })), { title });
+
+        if (picked) {
+            await revealSystem(bevyrlyIndex, picked.label);
+        }
+    });
+
+    context.subscriptions.push(disposableList);
+}
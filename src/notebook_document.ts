@@ -1,9 +1,19 @@
 import * as vscode from 'vscode';
 import { BevyrlyIndex, bevyrlyLog, startBevyrlyIndexing } from '.';
-import { expandLinkFromName, expandSystemFromName } from './extension';
+import { escapeHtml, expandLinkFromName, expandSystemFromName, provenanceOf } from './extension';
+import { definitionLink } from './definitions';
+import { blameLabel } from './blame';
+import { querySyntax } from './symbols';
+import { recordQuery } from './history';
+import { noteSessionQuery, scopedQuery, sessionNamed } from './sessions';
+import { resolveChanged } from './changed';
+import { explainQuery, explanationLines, splitExplain } from './explain';
+import { featureScopedGet } from './cfg';
+import { crateLabel, groupByModule, moduleLabel, orderingSummary, pageSummary, resultColumns, resultHtmlTable, resultLayout, resultPage, resultRow, splitPage } from './results';
 
 interface BevyrlyNotebook {
     cells: BevyrlyNotebookCell[];
+    session?: string;
 }
 
 interface BevyrlyNotebookCell {
@@ -11,6 +21,11 @@ interface BevyrlyNotebookCell {
     kind: "markup" | "code";
 }
 
+// Escapes `text` and renders its `backticked` parts as code.
+function codeHtml(text: string): string {
+    return escapeHtml(text).replace(/`([^`]*)`/g, "<code>$1</code>");
+}
+
 class BevyrlyController implements vscode.Disposable {
     readonly controllerId = 'bevyrly-controller-id';
     readonly notebookType = 'bevyrly-notebook';
@@ -55,6 +70,9 @@ class BevyrlyController implements vscode.Disposable {
         execution.executionOrder = ++this._executionOrder;
         execution.start(Date.now());
 
+        // Cells run while indexing wait for it, rather than answering from a half-built index.
+        await this._bevyrlyIndex.indexing?.done;
+
         let query = cell.document.getText();
 
         if (query == "~") {
@@ -70,27 +88,9 @@ class BevyrlyController implements vscode.Disposable {
                 "<b>Bevy</b> is <b>rly</b> useful, but requires some hygiene! Pronounced as /ˈbɛvə(ɹ)li/, derives from Old English, combining <i>befer</i> (\"beaver\") and <i>leah</i> (\"clearing\").<br />",
                 "Bevyrly is a tool for quickly looking for Bevy systems by querying its arguments.<br />",
                 "Bevyrly can be used as a notebook, enabling you to save useful queries and even document the many systems you use.",
-                "<h2>Search control</h2><ul>",
-                "<li><code>&Transform</code>: find all systems that include <code>Query&lt;&Transform&gt;</code> within it</li>",
-                "<li><code>*Transform</code>: find all systems that include <code>Query&lt;&mut Transform&gt;</code> within it</li>",
-                "<li><code>#Config</code>: find all systems that include <code>Res&lt;Config&gt;</code> within it</li>",
-                "<li><code>$Config</code>: find all systems that include <code>ResMut&lt;Config&gt;</code> or <code>NonSendMut&lt;Config&gt;</code> within it</li>",
-                "<li><code>&lt;ShipFireEvent</code>: find all systems that include <code>EventReader&lt;ShipFireEvent&gt;</code> within it</li>",
-                "<li><code>&gt;ShipFireEvent</code>: find all systems that include <code>EventWriter&lt;ShipFireEvent&gt;</code> within it</li>",
-                "<li><code>+Tag</code>: find all systems that include <code>With&lt;Tag&gt;</code> within it</li>",
-                "<li><code>-Tag</code>: find all systems that include <code>Without&lt;Tag&gt;</code> within it</li>",
-                "<li><code>JustText</code>: will match any of the above (might yield a <b>lot</b> of content)</li>",
-                "</ul>",
-                "<h2>Output control</h2><ul>",
-                "<li><code>?</code>: prints this documentation</li>",
-                "<li><code>my prompt goes here</code>: find and print locations of all systems that mention 'my', 'prompt', 'goes', and 'here'</li>",
-                "<li><code>:my prompt goes here</code>: find and print declaration for all systems that mention 'my', 'prompt', 'goes', and 'here'</li>",
-                "</ul>",
-                "<h2>Examples</h2><ul>",
-                "<li><code>:&Transform &gt;ShipFireEvent +Player</code>: prints full function declarations for any system that queries the <code>Transform</code> component immutably, accesses the <code>EventWriter&lt;ShipFireEvent&gt;</code>, and has a <code>With&lt;Player&gt;</code>.</li>",
-                "<li><code>+Player -Player</code>: prints linkable locations to all the systems that require <code>With&lt;Player&gt;</code> and <code>Without&lt;Player&gt;</code> (possibly in different arguments)</li>",
-                "<li><code>Foo Bar</code>: prints locations of all the systems that have the strings <code>Foo</code> and <code>Bar</code> <i>anywhere</i> in their arguments (including resources, components, etc.)</li>",
-                "</ul>"
+                ...querySyntax().map(section => "<h2>" + escapeHtml(section.title) + "</h2><ul>" +
+                    section.rows.map(([syntax, description]) => "<li>" + codeHtml(syntax) + ": " + codeHtml(description) + "</li>").join("") +
+                    "</ul>"),
             ].join("<br>");
 
             let result = new vscode.NotebookCellOutput([vscode.NotebookCellOutputItem.text(output, "text/html")]);
@@ -101,7 +101,58 @@ class BevyrlyController implements vscode.Disposable {
 
         let result = [];
 
-        let [response, long] = this._bevyrlyIndex.get(query);
+        let pageNumber: number;
+        [query, pageNumber] = splitPage(query);
+        let explain: boolean, explained: string | undefined;
+        [query, explain, explained] = splitExplain(query);
+        // A notebook runs in the session it was bound to by `bevyrly: Switch Query Session`, or the active one.
+        const session = sessionNamed(cell.notebook.metadata?.session);
+        this._bevyrlyIndex.lastQuery = query;
+        noteSessionQuery(session.name, query);
+        const scoped = scopedQuery(session, query);
+        await resolveChanged(this._bevyrlyIndex, scoped);
+        // Feature flags stay in the query, so history and reruns keep them; everything below works on the query without.
+        let [found, long, unflagged] = featureScopedGet(this._bevyrlyIndex, scoped);
+        recordQuery(query, found.length);
+        const page = resultPage(resultLayout() == "list" ? found : [...found].sort(), pageNumber);
+        let response = page.systems;
+        const summary = pageSummary(page, query);
+        if (summary) {
+            result.push(new vscode.NotebookCellOutput([
+                vscode.NotebookCellOutputItem.text("<i>" + codeHtml(summary) + "</i>", 'text/html'),
+            ]));
+        }
+        if (explain) {
+            result.push(new vscode.NotebookCellOutput([
+                vscode.NotebookCellOutputItem.text("<pre>" + escapeHtml(explanationLines(explainQuery(this._bevyrlyIndex, unflagged, explained), explained).join("\n")) + "</pre>", 'text/html'),
+            ]));
+        }
+        if (session.scope.trim().length > 0 || session.pinned.length > 0) {
+            const link = (system: string) => {
+                const href = definitionLink(this._bevyrlyIndex, system);
+                return href ? "<a href='" + escapeHtml(href) + "'>" + escapeHtml(system) + "</a>" : escapeHtml(system);
+            };
+            result.push(new vscode.NotebookCellOutput([
+                vscode.NotebookCellOutputItem.text("<i style='color: #888888'>Session <b>" + escapeHtml(session.name) + "</b>" +
+                    (session.scope.trim().length > 0 ? ", scoped to <code>" + escapeHtml(session.scope.trim()) + "</code>" : "") +
+                    (session.pinned.length > 0 ? "; pinned: " + session.pinned.map(link).join(", ") : "") + "</i>", 'text/html'),
+            ]));
+        }
+
+        if (resultLayout() == "grouped") {
+            result.push(new vscode.NotebookCellOutput([
+                vscode.NotebookCellOutputItem.text(this._groupedOutput(response, long), 'text/html'),
+            ]));
+            response = [];
+        } else if (resultLayout() == "table") {
+            const rows = response.map(system => resultRow(this._bevyrlyIndex, unflagged, system));
+            const table = resultHtmlTable(rows, resultColumns(), name => definitionLink(this._bevyrlyIndex, name));
+            result.push(new vscode.NotebookCellOutput([
+                vscode.NotebookCellOutputItem.text(table, 'text/html'),
+            ]));
+            response = [];
+        }
+
         for (const item of response) {
             let expandedLink = expandLinkFromName(this._bevyrlyIndex, item);
             if (expandedLink) {
@@ -109,12 +160,16 @@ class BevyrlyController implements vscode.Disposable {
                 const start = loc.src.l(loc[0]) + 1;
                 const end = loc.src.l(loc[1]) + 1;
                 const path = vscode.Uri.parse(loc.src.filepath?.replace("file:///", "") ?? "").path.split('/src/').pop();
+                const ordering = orderingSummary(this._bevyrlyIndex, item);
+                const blame = blameLabel(this._bevyrlyIndex, item);
+                const orderingHtml = (ordering.length > 0 ? " <i style='color: #888888'>(" + escapeHtml(ordering) + ")</i>" : "") +
+                    (blame.length > 0 ? " <span style='color: #888888'>— " + escapeHtml(blame) + "</span>" : "");
 
                 if (long == "long") {
                     result.push(new vscode.NotebookCellOutput([
                         vscode.NotebookCellOutputItem.text("═══════════╣  <a style='color: #cccccc; text-decoration: none;' href='" +
-                            (loc.src.filepath ?? "") + ":" + start + "'>Go to: <b>" + path + "</b>, lines <b>" +
-                            start + "-" + end + "</b></a>  ╠═══════════", 'text/html'),
+                            (loc.src.filepath ?? "") + ":" + start + "'>Go to: <b>" + escapeHtml(provenanceOf(this._bevyrlyIndex, item)) + ": " + path + "</b>, lines <b>" +
+                            start + "-" + end + "</b></a>" + orderingHtml + "  ╠═══════════", 'text/html'),
                     ]));
 
                     let text = expandSystemFromName(this._bevyrlyIndex, item, false);
@@ -126,8 +181,8 @@ class BevyrlyController implements vscode.Disposable {
                 } else {
                     result.push(new vscode.NotebookCellOutput([
                         vscode.NotebookCellOutputItem.text("<a style='color: #cccccc; text-decoration: none;' href='" +
-                            (loc.src.filepath ?? "") + ":" + start + "'><b>[" + path + "] " + item + ":<b>" +
-                            start + "</b></a>", 'text/html'),
+                            (loc.src.filepath ?? "") + ":" + start + "'><b>[" + escapeHtml(provenanceOf(this._bevyrlyIndex, item)) + ": " + path + "] " + item + ":<b>" +
+                            start + "</b></a>" + orderingHtml, 'text/html'),
                     ]));
                 }
             }
@@ -136,6 +191,35 @@ class BevyrlyController implements vscode.Disposable {
         execution.replaceOutput(result, cell);
         execution.end(true, Date.now());
     }
+
+    private _groupedOutput(response: string[], long: "short" | "long"): string {
+        let html: string[] = [];
+        for (const [crate, modules] of groupByModule(this._bevyrlyIndex, response)) {
+            const count = Array.from(modules.values()).reduce((sum, systems) => sum + systems.length, 0);
+            html.push("<details open><summary><b>" + escapeHtml(crateLabel(crate)) + "</b> (" + count + ")</summary>");
+            for (const [module, systems] of modules) {
+                html.push("<details open style='margin-left: 1em'><summary>" + escapeHtml(moduleLabel(module)) +
+                    " (" + systems.length + ")</summary><div style='margin-left: 1em'>");
+                for (const item of systems) {
+                    const [loc, path] = expandLinkFromName(this._bevyrlyIndex, item) ?? [undefined, ""];
+                    if (!loc) continue;
+
+                    html.push("<a style='color: #cccccc; text-decoration: none;' href='" + (loc.src.filepath ?? "") + ":" +
+                        path.split(":").pop() + "'><b>" + escapeHtml(item) + "</b> " + escapeHtml(path) + "</a>");
+                    const ordering = orderingSummary(this._bevyrlyIndex, item);
+                    html.push((ordering.length > 0 ? " <i style='color: #888888'>(" + escapeHtml(ordering) + ")</i>" : "") + "<br>");
+                    if (long == "long") {
+                        let text = expandSystemFromName(this._bevyrlyIndex, item, false);
+                        html.push("<pre>" + escapeHtml(text.slice(0, text.indexOf("{")) + "{ /* ... */ }") + "</pre>");
+                    }
+                }
+                html.push("</div></details>");
+            }
+            html.push("</details>");
+        }
+
+        return html.join("\n");
+    }
 }
 
 class BevyrlyNotebookSerializer implements vscode.NotebookSerializer {
@@ -145,8 +229,11 @@ class BevyrlyNotebookSerializer implements vscode.NotebookSerializer {
         var contents = new TextDecoder().decode(content);
 
         let raw: BevyrlyNotebookCell[];
+        let session: string | undefined = undefined;
         try {
-            raw = (<BevyrlyNotebook>JSON.parse(contents)).cells;
+            const notebook = <BevyrlyNotebook>JSON.parse(contents);
+            raw = notebook.cells;
+            session = notebook.session;
         } catch {
             raw = [];
         }
@@ -160,7 +247,9 @@ class BevyrlyNotebookSerializer implements vscode.NotebookSerializer {
                 )
         );
 
-        return new vscode.NotebookData(cells);
+        let data = new vscode.NotebookData(cells);
+        data.metadata = { session };
+        return data;
     }
 
     async serializeNotebook(
@@ -178,7 +267,7 @@ class BevyrlyNotebookSerializer implements vscode.NotebookSerializer {
             }
         }
 
-        return new TextEncoder().encode(JSON.stringify({ cells: contents }));
+        return new TextEncoder().encode(JSON.stringify({ cells: contents, session: data.metadata?.session }));
     }
 }
 
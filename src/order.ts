@@ -0,0 +1,202 @@
+import { BevyrlyIndex } from '.';
+import { Cfg, cfgEnvironment, cfgGates, evalCfg } from './cfg';
+import { SystemRegistration, blankComments, matchingClose, splitMethodChain, splitTopLevel } from './registrations';
+import { framePhases, phaseOf } from './timeline';
+
+// `configure_sets(Schedule, ..)` edges between system sets: [schedule, set, "before" | "in_set", other, offset of the
+// call].
+type SetEdge = [string, string, "before" | "in_set", string, number];
+
+function setName(expr: string): string {
+    return expr.replace(/\s+/g, "");
+}
+
+// Set orderings and nesting from `configure_sets`, e.g. `(Input, Physics).chain()`, `Physics.after(Input)` and
+// `Inner.in_set(Outer)`.
+export function parseSetConfigs(source: string): SetEdge[] {
+    const text = blankComments(source);
+    let edges: SetEdge[] = [];
+    for (const found of text.matchAll(/\.\s*configure_sets?\s*\(/g)) {
+        const open = (found.index ?? 0) + found[0].length - 1;
+        const close = matchingClose(text, open);
+        if (close < 0) continue;
+        const args = splitTopLevel(text.slice(open + 1, close));
+        if (args.length < 2) continue;
+
+        const schedule = setName(args[0][0]);
+        const at = found.index ?? 0;
+        const collect = (expr: string): string[] => {
+            const [head, methods] = splitMethodChain(expr.trim());
+            const sets = head.startsWith("(") ? [] as string[] : [setName(head)];
+            if (head.startsWith("(")) {
+                let previous: string[] = [];
+                for (const [sub] of splitTopLevel(head.slice(1, -1))) {
+                    const current = collect(sub);
+                    if (methods.some(method => method.name == "chain")) {
+                        previous.forEach(prev => current.forEach(next => edges.push([schedule, prev, "before", next, at])));
+                    }
+                    previous = current;
+                    sets.push(...current);
+                }
+            }
+            for (const method of methods) {
+                const other = setName(method.args);
+                if (method.name == "before") sets.forEach(set => edges.push([schedule, set, "before", other, at]));
+                if (method.name == "after") sets.forEach(set => edges.push([schedule, other, "before", set, at]));
+                if (method.name == "in_set") sets.forEach(set => edges.push([schedule, set, "in_set", other, at]));
+            }
+            return sets;
+        };
+        args.slice(1).forEach(([expr]) => collect(expr));
+    }
+    return edges;
+}
+
+export interface ScheduleOrder {
+    schedule: string;
+    // A plausible execution order: every `.before`/`.after`/`.chain()` and set ordering respected, ties broken by
+    // where systems are registered.
+    order: string[];
+    // System -> every system it's guaranteed to run before, directly or transitively.
+    before: Map<string, Set<string>>;
+    // Systems caught in an ordering cycle, which Bevy refuses to build; they're appended in registration order.
+    cycle: string[];
+}
+
+// Names `target` can refer to a registration by: the system itself, or a set it's in. A bare target like `Input` may
+// name `GameSet::Input` after a `use GameSet::*`, but a path like `GameSet::Input` only ever names that set.
+function refersTo(target: string, reg: SystemRegistration, nesting: Map<string, string[]>): boolean {
+    if (target == reg.system) return true;
+    let sets = reg.sets.map(setName);
+    for (let i = 0; i < sets.length; i++) {
+        sets.push(...(nesting.get(sets[i]) ?? []).filter(outer => !sets.includes(outer)));
+    }
+    return sets.some(set => set == target || (!target.includes("::") && set.split("::").pop() == target));
+}
+
+function simulate(schedule: string, registrations: SystemRegistration[], setEdges: SetEdge[]): ScheduleOrder {
+    let nesting: Map<string, string[]> = new Map();
+    for (const [_, set, kind, other] of setEdges) {
+        if (kind == "in_set") nesting.set(set, [...nesting.get(set) ?? [], other]);
+    }
+    const systems = Array.from(new Set(registrations.map(reg => reg.system)));
+    const members = (target: string) => Array.from(new Set(registrations.filter(reg => refersTo(target, reg, nesting)).map(reg => reg.system)));
+
+    let edges: Map<string, Set<string>> = new Map(systems.map(system => [system, new Set()]));
+    const addEdges = (from: string[], to: string[]) => from.forEach(a => to.forEach(b => a != b && edges.get(a)?.add(b)));
+    for (const reg of registrations) {
+        reg.before.forEach(next => addEdges([reg.system], members(next)));
+        reg.after.forEach(prev => addEdges(members(prev), [reg.system]));
+    }
+    for (const [_, set, kind, other] of setEdges) {
+        if (kind == "before") addEdges(members(set), members(other));
+    }
+
+    // Kahn's algorithm, always taking the earliest registered of the systems that are ready.
+    let incoming: Map<string, number> = new Map(systems.map(system => [system, 0]));
+    edges.forEach(next => next.forEach(system => incoming.set(system, (incoming.get(system) ?? 0) + 1)));
+    let order: string[] = [];
+    let ready = systems.filter(system => incoming.get(system) == 0);
+    while (ready.length > 0) {
+        ready.sort((a, b) => systems.indexOf(a) - systems.indexOf(b));
+        const system = ready.shift() as string;
+        order.push(system);
+        for (const next of edges.get(system) ?? []) {
+            incoming.set(next, (incoming.get(next) ?? 0) - 1);
+            if (incoming.get(next) == 0) ready.push(next);
+        }
+    }
+    const cycle = systems.filter(system => !order.includes(system));
+
+    let before: Map<string, Set<string>> = new Map();
+    for (const start of systems) {
+        let seen: Set<string> = new Set();
+        let stack = Array.from(edges.get(start) ?? []);
+        while (stack.length > 0) {
+            const next = stack.pop() as string;
+            if (seen.has(next)) continue;
+            seen.add(next);
+            stack.push(...(edges.get(next) ?? []));
+        }
+        before.set(start, seen);
+    }
+
+    return { schedule, order: [...order, ...cycle], before, cycle };
+}
+
+// The simulated order of every schedule systems are registered in, as the default build (default features, debug
+// assertions on) has it: registrations and `configure_sets` calls cfg'd out of that build are left out.
+export function scheduleOrders(bevyrlyIndex: BevyrlyIndex): Map<string, ScheduleOrder> {
+    return bevyrlyIndex.derived("schedule-orders", () => {
+        const gates = cfgGates(bevyrlyIndex);
+        const defaultBuild = { features: [], noDefaultFeatures: false, release: false };
+        const enabled = (filepath: string, cfgs: Cfg[]) =>
+            cfgs.every(cfg => evalCfg(cfg, cfgEnvironment(bevyrlyIndex, filepath, defaultBuild)) !== false);
+        const setEdges = Array.from(bevyrlyIndex.sources.entries())
+            .flatMap(([filepath, text]) => parseSetConfigs(text).filter(edge => enabled(filepath, gates.at(filepath, edge[4]))));
+        let schedules: Map<string, SystemRegistration[]> = new Map();
+        for (const reg of Array.from(bevyrlyIndex.registrations.values()).flat()) {
+            if (!enabled(reg.filepath, gates.registrations.get(reg) ?? [])) continue;
+            schedules.set(reg.schedule, [...schedules.get(reg.schedule) ?? [], reg]);
+        }
+        for (const registrations of schedules.values()) {
+            registrations.sort((a, b) => a.filepath.localeCompare(b.filepath) || a.line - b.line);
+        }
+
+        return new Map(Array.from(schedules.entries()).sort(([a], [b]) => a.localeCompare(b))
+            .map(([schedule, registrations]) => [schedule, simulate(schedule, registrations, setEdges.filter(edge => edge[0] == schedule))]));
+    });
+}
+
+export interface RunsBefore {
+    // Whether `first` runs before `second` every time both run in a frame, never does, or may either way.
+    verdict: "always" | "never" | "ambiguous";
+    reason: string;
+}
+
+// Whether `first` runs before `second`: by their frame phases when their schedules differ, by the ordering edges
+// between them when they share one. With several registrations each, every pair has to agree for a definite answer.
+export function runsBefore(bevyrlyIndex: BevyrlyIndex, first: string, second: string): RunsBefore {
+    const firstSchedules = Array.from(new Set((bevyrlyIndex.registrations.get(first) ?? []).map(reg => reg.schedule)));
+    const secondSchedules = Array.from(new Set((bevyrlyIndex.registrations.get(second) ?? []).map(reg => reg.schedule)));
+    if (firstSchedules.length == 0 || secondSchedules.length == 0) {
+        return { verdict: "ambiguous", reason: (firstSchedules.length == 0 ? first : second) + " isn't registered in any schedule" };
+    }
+
+    const orders = scheduleOrders(bevyrlyIndex);
+    let answers: RunsBefore[] = [];
+    for (const a of firstSchedules) {
+        for (const b of secondSchedules) {
+            if (a == b) {
+                const order = orders.get(a);
+                if (order?.before.get(first)?.has(second)) {
+                    answers.push({ verdict: "always", reason: first + " is ordered before " + second + " in " + a });
+                } else if (order?.before.get(second)?.has(first)) {
+                    answers.push({ verdict: "never", reason: first + " is ordered after " + second + " in " + a });
+                } else {
+                    answers.push({ verdict: "ambiguous", reason: "nothing orders " + first + " and " + second + " in " + a });
+                }
+                continue;
+            }
+
+            const [phaseA, phaseB] = [phaseOf(a), phaseOf(b)];
+            if (phaseA === undefined || phaseB === undefined) {
+                answers.push({ verdict: "ambiguous", reason: "where " + (phaseA === undefined ? a : b) + " runs in the frame isn't known" });
+            } else if (phaseA == phaseB) {
+                answers.push({ verdict: "ambiguous", reason: a + " and " + b + " both run in " + phaseA + ", in an order decided at run time" });
+            } else {
+                const earlier = framePhases.indexOf(phaseA) < framePhases.indexOf(phaseB);
+                answers.push({ verdict: earlier ? "always" : "never", reason: a + " runs " + (earlier ? "before " : "after ") + b + " in the frame" });
+            }
+        }
+    }
+
+    const verdicts = new Set(answers.map(answer => answer.verdict));
+    if (verdicts.size == 1 && !verdicts.has("ambiguous")) {
+        return { verdict: answers[0].verdict, reason: answers.map(answer => answer.reason).join("; ") };
+    }
+    return {
+        verdict: "ambiguous",
+        reason: (verdicts.size > 1 ? "their registrations disagree: " : "") + answers.map(answer => answer.reason).join("; "),
+    };
+}
@@ -0,0 +1,34 @@
+import { BevyrlyIndex } from '.';
+import { systemParameters } from './complexity';
+
+function addSystem(map: Map<string, Set<string>>, key: string, system: string) {
+    if (!map.has(key)) {
+        map.set(key, new Set());
+    }
+    map.get(key)?.add(system);
+}
+
+// A parameter type as `first:`/`last:` parts see it: no whitespace or lifetimes, so `EventWriter<'w, Hit>` is
+// `EventWriter<Hit>`.
+function normalizedType(type: string): string {
+    return type.replace(/'\w+\s*,?\s*/g, "").replace(/<\s*>/g, "").replace(/\s+/g, "");
+}
+
+// Where each system's parameters sit: "param" maps names, "first" and "last" the types at either end. These are what
+// `param:`, `first:` and `last:` query parts match against, for checking signature conventions.
+export function parameterPositions(bevyrlyIndex: BevyrlyIndex, position: "param" | "first" | "last"): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("params:" + position, () => {
+        let map: Map<string, Set<string>> = new Map();
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            const params = systemParameters(loc.getText()).filter(([name, _]) => name != "self" && !/^&\s*(?:mut\s+)?self$/.test(name));
+            if (params.length == 0) continue;
+
+            if (position == "param") {
+                params.forEach(([name, _]) => addSystem(map, name, system));
+            } else {
+                addSystem(map, normalizedType(params[position == "first" ? 0 : params.length - 1][1]), system);
+            }
+        }
+        return map;
+    });
+}
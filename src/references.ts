@@ -0,0 +1,124 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { componentsOf } from './archetypes';
+import { definitionOf } from './definitions';
+import { expandLinkFromName } from './extension';
+import { systemsUsing } from './lenses';
+import { PluginProvision, blankComments, matchingClose } from './registrations';
+import { accessLabels } from './report';
+
+export interface EcsReference {
+    category: string;
+    label: string;
+    location: vscode.Location;
+}
+
+const provisionCalls: Record<PluginProvision["kind"], string> = {
+    event: "add_event",
+    resource: "init_resource",
+    gizmo_group: "init_gizmo_group",
+};
+
+// `.spawn(..)` and `.insert(..)` calls inside the given system that add a component named `name`, as lines.
+function insertionLines(bevyrlyIndex: BevyrlyIndex, system: string, name: string): number[] {
+    const loc = bevyrlyIndex.locs.get(system);
+    if (!loc) return [];
+
+    const text = blankComments(loc.getText());
+    let lines: Set<number> = new Set();
+    const pattern = /\.\s*(?:spawn(?:_bundle)?|insert)\s*\(/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        const open = found.index + found[0].length - 1;
+        const close = matchingClose(text, open);
+        if (close < 0) continue;
+
+        if (componentsOf(text.slice(open + 1, close)).includes(name)) {
+            lines.add(loc.src.l(loc[0] + found.index));
+        }
+    }
+
+    return Array.from(lines);
+}
+
+export function ecsReferences(bevyrlyIndex: BevyrlyIndex, name: string): EcsReference[] {
+    let references: EcsReference[] = [];
+    const definition = definitionOf(bevyrlyIndex, name);
+    if (definition) {
+        references.push({ category: "Definition", label: name, location: definition });
+    }
+
+    for (const provision of bevyrlyIndex.provisions.filter(provision => provision.name == name || provision.name.startsWith(name + "<"))) {
+        references.push({
+            category: "Registration",
+            label: provisionCalls[provision.kind] + "::<" + provision.name + ">" +
+                (provision.plugin ? " in " + provision.plugin : ""),
+            location: new vscode.Location(vscode.Uri.parse(provision.filepath), new vscode.Position(provision.line, 0)),
+        });
+    }
+
+    for (const [storage, label] of accessLabels) {
+        for (const system of systemsUsing(bevyrlyIndex[storage], name)) {
+            const location = definitionOf(bevyrlyIndex, system);
+            if (location) {
+                references.push({ category: label, label: system, location });
+            }
+        }
+    }
+
+    for (const system of Array.from(bevyrlyIndex.locs.keys()).sort()) {
+        const uri = vscode.Uri.parse(bevyrlyIndex.locs.get(system)?.src.filepath ?? "");
+        for (const line of insertionLines(bevyrlyIndex, system, name)) {
+            references.push({ category: "Spawned or inserted", label: system, location: new vscode.Location(uri, new vscode.Position(line, 0)) });
+        }
+    }
+
+    return references;
+}
+
+export function registerReferences(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableReferences = vscode.commands.registerCommand('bevyrly.ecsReferences', async (name?: string) => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const editor = vscode.window.activeTextEditor;
+        if (!name && editor) {
+            const range = editor.document.getWordRangeAtPosition(editor.selection.active, /[A-Za-z_]\w*/);
+            name = range ? editor.document.getText(range) : undefined;
+        }
+
+        if (!name) {
+            return;
+        }
+
+        const references = ecsReferences(bevyrlyIndex, name);
+        let items: (vscode.QuickPickItem & { location?: vscode.Location })[] = [];
+        let category = "";
+        for (const reference of references) {
+            if (reference.category != category) {
+                category = reference.category;
+                items.push({ label: category, kind: vscode.QuickPickItemKind.Separator });
+            }
+
+            const path = reference.location.uri.path.split('/src/').pop();
+            items.push({
+                label: reference.label,
+                description: path + ":" + (reference.location.range.start.line + 1),
+                detail: bevyrlyIndex.systems.has(reference.label) ? expandLinkFromName(bevyrlyIndex, reference.label)?.[1] : undefined,
+                location: reference.location
+            });
+        }
+
+        if (references.length == 0) {
+            vscode.window.showInformationMessage("bevyrly found no ECS references to " + name);
+            return;
+        }
+
+        const picked = await vscode.window.showQuickPick(items, { title: 'ECS references to ' + name, matchOnDescription: true });
+        if (picked?.location) {
+            const doc = await vscode.workspace.openTextDocument(picked.location.uri);
+            await vscode.window.showTextDocument(doc, { selection: picked.location.range, preview: false });
+        }
+    });
+
+    context.subscriptions.push(disposableReferences);
+}
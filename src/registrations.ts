@@ -0,0 +1,388 @@
+import { bevyVersionBefore } from './config';
+
+export interface SystemRegistration {
+    system: string;
+    schedule: string;
+    conditions: string[];
+    sets: string[];
+    before: string[];
+    after: string[];
+    plugin: string;
+    filepath: string;
+    line: number;
+}
+
+// Replaces comments with whitespace so that offsets (and thus line numbers) stay intact. String, raw string and char
+// literals are matched too, and kept as they are, so `"http://.."` or `'/'` don't start a comment.
+export function blankComments(text: string): string {
+    return text.replace(/\/\/[^\n]*|\/\*[\s\S]*?\*\/|(?<!\w)[bc]?r(#*)"[\s\S]*?"\1|"(?:[^"\\]|\\[\s\S])*"|'(?:[^'\\\n]|\\[^\n][^'\n]*)'/g,
+        m => m.startsWith("//") ? " ".repeat(m.length) : m.startsWith("/*") ? m.replace(/[^\n]/g, " ") : m);
+}
+
+// The newline offsets of the last text `lineAt` was asked about, as callers ask about many offsets of one file in a row.
+let newlines: [string, number[]] | undefined;
+
+export function lineAt(text: string, offset: number): number {
+    if (newlines?.[0] !== text) {
+        newlines = [text, Array.from(text.matchAll(/\n/g), found => found.index ?? 0)];
+    }
+
+    // The number of newlines before `offset`.
+    const breaks = newlines[1];
+    let [low, high] = [0, breaks.length];
+    while (low < high) {
+        const middle = (low + high) >> 1;
+        if (breaks[middle] < offset) low = middle + 1;
+        else high = middle;
+    }
+    return low;
+}
+
+export function matchingClose(text: string, open: number): number {
+    let depth = 0;
+    for (let i = open; i < text.length; i++) {
+        const c = text[i];
+        if (c == '"') {
+            i++;
+            while (i < text.length && text[i] != '"') {
+                if (text[i] == "\\") i++;
+                i++;
+            }
+        } else if (c == "(" || c == "[" || c == "{") {
+            depth++;
+        } else if (c == ")" || c == "]" || c == "}") {
+            depth--;
+            if (depth == 0) return i;
+        }
+    }
+    return -1;
+}
+
+function isGenericOpen(text: string, i: number): boolean {
+    return text[i] == "<" && i > 0 && /[\w:]/.test(text[i - 1]);
+}
+
+// Splits on separators that are not nested inside brackets or generic arguments.
+// Returns each part along with its offset inside `text`.
+export function splitTopLevel(text: string, separator: string = ","): [string, number][] {
+    let parts: [string, number][] = [];
+    let depth = 0;
+    let angles = 0;
+    let start = 0;
+    for (let i = 0; i < text.length; i++) {
+        const c = text[i];
+        if (c == "(" || c == "[" || c == "{") depth++;
+        else if (c == ")" || c == "]" || c == "}") depth--;
+        else if (isGenericOpen(text, i)) angles++;
+        else if (c == ">" && angles > 0 && text[i - 1] != "-" && text[i - 1] != "=") angles--;
+        else if (c == separator && depth == 0 && angles == 0) {
+            parts.push([text.slice(start, i), start]);
+            start = i + 1;
+        }
+    }
+    parts.push([text.slice(start), start]);
+    return parts.filter(([part, _]) => part.trim().length > 0);
+}
+
+export interface MethodCall {
+    name: string;
+    args: string;
+}
+
+// Splits `head.method(args).other()` into the head expression and its trailing method calls.
+export function splitMethodChain(expr: string): [string, MethodCall[]] {
+    let head: string;
+    let rest: string;
+    if (expr.startsWith("(")) {
+        const close = matchingClose(expr, 0);
+        if (close < 0) return [expr, []];
+        head = expr.slice(0, close + 1);
+        rest = expr.slice(close + 1);
+    } else {
+        let depth = 0;
+        let angles = 0;
+        let dot = expr.length;
+        for (let i = 0; i < expr.length; i++) {
+            const c = expr[i];
+            if (c == "(" || c == "[" || c == "{") depth++;
+            else if (c == ")" || c == "]" || c == "}") depth--;
+            else if (isGenericOpen(expr, i)) angles++;
+            else if (c == ">" && angles > 0) angles--;
+            else if (c == "." && depth == 0 && angles == 0) { dot = i; break; }
+        }
+        head = expr.slice(0, dot);
+        rest = expr.slice(dot);
+    }
+
+    let methods: MethodCall[] = [];
+    while (true) {
+        const found = rest.match(/^\s*\.\s*(\w+)\s*(::\s*<)?/);
+        if (!found) break;
+        let i = found[0].length;
+        if (found[2]) {
+            let angles = 1;
+            while (i < rest.length && angles > 0) {
+                if (rest[i] == "<") angles++;
+                else if (rest[i] == ">") angles--;
+                i++;
+            }
+        }
+        while (i < rest.length && /\s/.test(rest[i])) i++;
+        if (rest[i] != "(") break;
+        const close = matchingClose(rest, i);
+        if (close < 0) break;
+        methods.push({ name: found[1], args: rest.slice(i + 1, close).trim().replace(/\s+/g, " ") });
+        rest = rest.slice(close + 1);
+    }
+
+    return [head.trim(), methods];
+}
+
+// `show_ui_elements::<HpBar>` and `crate::ui::spawn_ui` both resolve to the bare function name.
+export function systemNameOf(path: string): string | undefined {
+    const name = path.replace(/::\s*<[\s\S]*>$/, "").split("::").pop()?.trim();
+    if (name && /^[A-Za-z_]\w*$/.test(name)) {
+        return name;
+    }
+
+    return undefined;
+}
+
+// `.add_observer(on_hit)` and `.observe(on_hit)` calls naming `system`, as lines.
+export function observerLines(source: string, system: string): number[] {
+    const text = blankComments(source);
+    const pattern = new RegExp("\\.\\s*(?:add_observer|observe)\\s*\\(\\s*(?:[\\w:]*::)?" + system + "\\b", "g");
+    let lines: number[] = [];
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        lines.push(lineAt(text, found.index));
+    }
+
+    return lines;
+}
+
+export interface DerivedType {
+    name: string;
+    derives: string[];
+    offset: number;
+}
+
+// Finds structs and enums along with the traits they derive; `offset` points at the type name.
+export function parseDerives(source: string): DerivedType[] {
+    const text = blankComments(source);
+    let types: DerivedType[] = [];
+    const pattern = /#\[derive\(([^)]*)\)\]\s*(?:#\[[^\]]*\]\s*)*(?:pub(?:\([^)]*\))?\s+)?(?:struct|enum)\s+(\w+)/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        types.push({
+            name: found[2],
+            derives: found[1].split(",").map(derive => derive.trim().split("::").pop() ?? "").filter(derive => derive.length > 0),
+            offset: found.index + found[0].length - found[2].length,
+        });
+    }
+
+    return types;
+}
+
+// Every `struct`, `enum` and `type` declaration as [name, line], for navigating to the types matched by queries.
+export function parseTypeDefinitions(source: string): [string, number][] {
+    const text = blankComments(source);
+    let types: [string, number][] = [];
+    const pattern = /\b(?:struct|enum|type)\s+([A-Z]\w*)/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        types.push([found[1], lineAt(text, found.index)]);
+    }
+
+    return types;
+}
+
+// Finds the `impl Plugin for X { ... }` blocks in a file, as [name, start, end] offsets.
+export function pluginBlocks(text: string): [string, number, number][] {
+    let blocks: [string, number, number][] = [];
+    const pattern = /impl\s+(?:[\w:]*::)?Plugin\s+for\s+(\w+)[^{]*\{/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        const open = found.index + found[0].length - 1;
+        blocks.push([found[1], open, matchingClose(text, open)]);
+    }
+
+    return blocks;
+}
+
+export interface PluginProvision {
+    plugin: string;
+    kind: "event" | "resource" | "gizmo_group";
+    name: string;
+    filepath: string;
+    line: number;
+}
+
+// Events, resources and gizmo config groups added to the app by `add_event::<E>()`, `init_resource::<R>()`,
+// `insert_resource(R { .. })`, `init_gizmo_group::<G>()` and `insert_gizmo_config(G, ..)`, attributed to the enclosing
+// `impl Plugin` (or "" outside of one).
+export function parseProvisions(source: string, filepath: string): PluginProvision[] {
+    const text = blankComments(source);
+    const plugins = pluginBlocks(text);
+    const pluginAt = (offset: number) => plugins.find(([_, start, end]) => start < offset && offset < end)?.[0] ?? "";
+
+    let result: PluginProvision[] = [];
+    const turbofish = /\.\s*(add_event|init_resource|init_gizmo_group)\s*::\s*<(.+?)>\s*\(\s*\)/g;
+    let found;
+    while ((found = turbofish.exec(text)) !== null) {
+        result.push({
+            plugin: pluginAt(found.index),
+            kind: found[1] == "add_event" ? "event" : found[1] == "init_resource" ? "resource" : "gizmo_group",
+            name: found[2].replace(/\s+/g, ""),
+            filepath,
+            line: lineAt(text, found.index),
+        });
+    }
+
+    const insert = /\.\s*insert_resource\s*\(\s*([\w:]+)/g;
+    while ((found = insert.exec(text)) !== null) {
+        const name = found[1].split("::").filter(segment => /^[A-Z]/.test(segment)).shift();
+        if (name) {
+            result.push({ plugin: pluginAt(found.index), kind: "resource", name, filepath, line: lineAt(text, found.index) });
+        }
+    }
+
+    // `insert_gizmo_group` before Bevy 0.14.
+    const gizmos = /\.\s*insert_gizmo_(?:config|group)\s*\(\s*(?:[\w:]*::)?([A-Z]\w*)/g;
+    while ((found = gizmos.exec(text)) !== null) {
+        result.push({ plugin: pluginAt(found.index), kind: "gizmo_group", name: found[1], filepath, line: lineAt(text, found.index) });
+    }
+
+    return result;
+}
+
+// The resources a run condition reads: `resource_exists::<T>`, `resource_changed::<T>` and the other `resource_*`
+// conditions name theirs in a turbofish, `resource_equals(GameMode::Versus)` by the value it compares with.
+export function conditionResources(condition: string): string[] {
+    let resources: string[] = [];
+    for (const found of condition.matchAll(/\bresource_\w+\s*::\s*<\s*(?:[\w:]*::)?(\w+)/g)) {
+        resources.push(found[1]);
+    }
+    for (const found of condition.matchAll(/\bresource_(?:exists_and_)?equals\s*\(\s*([\w:]+)/g)) {
+        const name = found[1].split("::").find(segment => /^[A-Z]/.test(segment));
+        if (name) resources.push(name);
+    }
+    return Array.from(new Set(resources));
+}
+
+// Returns the registrations emitted for `expr`, so that enclosing tuples can order them for `.chain()`.
+function collectSystems(
+    text: string, expr: string, offset: number, schedule: string, conditions: string[], sets: string[],
+    plugin: string, filepath: string, out: SystemRegistration[]
+): SystemRegistration[] {
+    const trimmed = expr.trimStart();
+    offset += expr.length - trimmed.length;
+
+    const [head, methods] = splitMethodChain(trimmed.trimEnd());
+    conditions = conditions.slice();
+    sets = sets.slice();
+    for (const method of methods) {
+        if (method.name == "run_if" || method.name == "distributive_run_if") {
+            conditions.push(method.args);
+        } else if (method.name == "in_set") {
+            sets.push(method.args);
+        }
+    }
+
+    let emitted: SystemRegistration[] = [];
+    if (head.startsWith("(")) {
+        let previous: SystemRegistration[] = [];
+        const chained = methods.some(method => method.name == "chain");
+        for (const [sub, at] of splitTopLevel(head.slice(1, -1))) {
+            const current = collectSystems(text, sub, offset + 1 + at, schedule, conditions, sets, plugin, filepath, out);
+            if (chained) {
+                for (const reg of previous) reg.before.push(...current.map(next => next.system));
+                for (const reg of current) reg.after.push(...previous.map(prev => prev.system));
+            }
+            previous = current;
+            emitted.push(...current);
+        }
+    } else {
+        const system = systemNameOf(head);
+        if (system) {
+            const registration: SystemRegistration = {
+                system, schedule, conditions, sets, before: [], after: [], plugin, filepath, line: lineAt(text, offset)
+            };
+            out.push(registration);
+            emitted.push(registration);
+
+            // `a.pipe(b)` registers `b` too, running right after `a` as part of the same system.
+            let previous = registration;
+            for (const method of methods.filter(method => method.name == "pipe")) {
+                const piped = systemNameOf(method.args);
+                if (!piped) continue;
+
+                const next: SystemRegistration = {
+                    system: piped, schedule, conditions, sets, before: [], after: [previous.system], plugin, filepath, line: lineAt(text, offset)
+                };
+                previous.before.push(piped);
+                out.push(next);
+                previous = next;
+            }
+        }
+    }
+
+    for (const method of methods) {
+        if (method.name == "before" || method.name == "after") {
+            // Systems by their bare name, sets (`GameSet::Input`) by their whole path.
+            const name = systemNameOf(method.args);
+            const target = name && !/^[A-Z]/.test(name) ? name : method.args.replace(/\s+/g, "");
+            for (const reg of emitted) (method.name == "before" ? reg.before : reg.after).push(target);
+        }
+    }
+
+    return emitted;
+}
+
+// Before Bevy 0.11, systems went to `Update` unless `.in_schedule(S)`, `.in_base_set(CoreSet::S)` or `.on_startup()`
+// said otherwise, and `.in_set(OnUpdate(State::A))` ran them only in that state.
+function legacySchedule(expr: string, method: string): [string, string[]] {
+    let schedule = method == "add_startup_system" || method == "add_startup_systems" ? "Startup" : "Update";
+    let conditions: string[] = [];
+    for (const call of splitMethodChain(expr.trim())[1]) {
+        if (call.name == "in_schedule") {
+            schedule = call.args.replace(/\s+/g, "");
+        } else if (call.name == "in_base_set") {
+            schedule = call.args.replace(/\s+/g, "").replace(/^(?:\w+::)*CoreSet::/, "");
+        } else if (call.name == "on_startup") {
+            schedule = "Startup";
+        } else if (call.name == "in_set" && /^OnUpdate\s*\(/.test(call.args)) {
+            conditions.push("in_state(" + call.args.replace(/^OnUpdate\s*\(/, ""));
+        }
+    }
+    return [schedule, conditions];
+}
+
+// `bevyVersion` picks the recognition rules: `add_systems(Schedule, ..)` from 0.11 on, and `add_system(..)`,
+// `add_startup_system(..)` and the scheduleless `add_systems(..)` before it. Without a version, both are recognized.
+export function parseRegistrations(source: string, filepath: string, bevyVersion?: string): SystemRegistration[] {
+    const text = blankComments(source);
+    let result: SystemRegistration[] = [];
+    const plugins = pluginBlocks(text);
+    const legacy = bevyVersion === undefined || bevyVersionBefore(bevyVersion, 0, 11);
+    const modern = !bevyVersionBefore(bevyVersion, 0, 11);
+    const pattern = /\.\s*(add_systems|add_system|add_startup_systems|add_startup_system)\s*\(/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        const open = found.index + found[0].length - 1;
+        const close = matchingClose(text, open);
+        if (close < 0) continue;
+
+        const args = splitTopLevel(text.slice(open + 1, close));
+        const plugin = plugins.find(([_, start, end]) => start < open && open < end)?.[0] ?? "";
+        if (args.length >= 2 && found[1] == "add_systems" && modern) {
+            const schedule = args[0][0].replace(/\s+/g, "");
+            collectSystems(text, args[1][0], open + 1 + args[1][1], schedule, [], [], plugin, filepath, result);
+        } else if (args.length == 1 && legacy) {
+            const [schedule, conditions] = legacySchedule(args[0][0], found[1]);
+            collectSystems(text, args[0][0], open + 1 + args[0][1], schedule, conditions, [], plugin, filepath, result);
+        }
+    }
+
+    return result;
+}
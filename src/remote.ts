@@ -0,0 +1,132 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { expandModuleFromPath } from './extension';
+
+let requestId = 0;
+
+// Bevy Remote Protocol is JSON-RPC 2.0 over HTTP, served by `RemotePlugin` + `RemoteHttpPlugin`.
+export async function brpRequest(url: string, method: string, params: any): Promise<any> {
+    const response = await fetch(url, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ jsonrpc: "2.0", id: ++requestId, method, params }),
+    });
+    const body: any = await response.json();
+    if (body.error) {
+        throw new Error(body.error.message);
+    }
+
+    return body.result;
+}
+
+// Full type path of a workspace type as the reflection registry names it, e.g. `my_game::combat::Health`.
+export function typePath(bevyrlyIndex: BevyrlyIndex, name: string): string | undefined {
+    const definition = bevyrlyIndex.types.get(name);
+    if (!definition) {
+        return undefined;
+    }
+
+    const [crate, module] = expandModuleFromPath(bevyrlyIndex, definition[0]);
+    return [crate.replace(/-/g, "_"), module, name].filter(segment => segment.length > 0).join("::");
+}
+
+// Components a system's queries require, as type paths. Systems touching types bevyrly can't resolve (generics,
+// Bevy's own components) are left out rather than reported with a misleading count.
+function requiredComponents(bevyrlyIndex: BevyrlyIndex, system: string): string[] | undefined {
+    const accesses = bevyrlyIndex.accessesOf(system);
+    const names = [...(accesses.get("query") ?? []), ...(accesses.get("mut_query") ?? []), ...(accesses.get("with") ?? [])];
+    const paths = names.map(name => typePath(bevyrlyIndex, name));
+    if (names.length == 0 || paths.some(path => path === undefined)) {
+        return undefined;
+    }
+
+    return Array.from(new Set(paths as string[])).sort();
+}
+
+// Live entity counts per system, with one `bevy/query` per distinct component set.
+export async function entityCounts(bevyrlyIndex: BevyrlyIndex, url: string): Promise<Map<string, number>> {
+    let byComponents: Map<string, number> = new Map();
+    let counts: Map<string, number> = new Map();
+    for (const system of bevyrlyIndex.systems.keys()) {
+        const components = requiredComponents(bevyrlyIndex, system);
+        if (!components) continue;
+
+        const key = components.join(",");
+        if (!byComponents.has(key)) {
+            const entities = await brpRequest(url, "bevy/query", { data: { components: [] }, filter: { with: components } });
+            byComponents.set(key, Array.isArray(entities) ? entities.length : 0);
+        }
+        counts.set(system, byComponents.get(key) ?? 0);
+    }
+
+    return counts;
+}
+
+export function registerRemote(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    const decorationType = vscode.window.createTextEditorDecorationType({
+        after: { margin: '0 0 0 2em', color: new vscode.ThemeColor('editorCodeLens.foreground') }
+    });
+    context.subscriptions.push(decorationType);
+
+    let counts: Map<string, number> = new Map();
+    let timer: NodeJS.Timeout | undefined = undefined;
+    // Bumped by every disconnect, so a poll still awaiting its answer doesn't revive a connection that was dropped.
+    let connection = 0;
+
+    const decorate = (editor: vscode.TextEditor | undefined) => {
+        if (!editor || editor.document.languageId != "rust") {
+            return;
+        }
+
+        let decorations: vscode.DecorationOptions[] = [];
+        for (const [system, count] of counts) {
+            const loc = bevyrlyIndex.locs.get(system);
+            if (loc && loc.src.filepath == editor.document.uri.toString()) {
+                const line = editor.document.lineAt(loc.src.l(loc[0]));
+                decorations.push({
+                    range: new vscode.Range(line.range.end, line.range.end),
+                    renderOptions: { after: { contentText: "● " + count + (count == 1 ? " entity" : " entities") + " live" } }
+                });
+            }
+        }
+        editor.setDecorations(decorationType, decorations);
+    };
+
+    const disconnect = () => {
+        connection++;
+        if (timer) {
+            clearTimeout(timer);
+            timer = undefined;
+        }
+        counts = new Map();
+        vscode.window.visibleTextEditors.forEach(decorate);
+    };
+
+    let disposableConnect = vscode.commands.registerCommand('bevyrly.connectRemote', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const url = vscode.workspace.getConfiguration('bevyrly').get<string>('remoteUrl') ?? "http://127.0.0.1:15702";
+        // Each poll schedules the next once it's answered, so slow answers never overlap, and a failed one stops polling.
+        const poll = async (current: number) => {
+            try {
+                const next = await entityCounts(bevyrlyIndex, url);
+                if (current != connection) return;
+                counts = next;
+                vscode.window.visibleTextEditors.forEach(decorate);
+                timer = setTimeout(() => poll(current), 2000);
+            } catch (error: any) {
+                if (current != connection) return;
+                disconnect();
+                vscode.window.showErrorMessage("bevyrly lost the connection to " + url + ": " + error.message);
+            }
+        };
+
+        disconnect();
+        await poll(connection);
+    });
+
+    let disposableDisconnect = vscode.commands.registerCommand('bevyrly.disconnectRemote', disconnect);
+
+    context.subscriptions.push(disposableConnect, disposableDisconnect, { dispose: disconnect });
+    context.subscriptions.push(vscode.window.onDidChangeActiveTextEditor(decorate));
+}
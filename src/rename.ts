@@ -0,0 +1,96 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+
+// Renames whole identifiers inside query parts, keeping prefixes and generic arguments: with `Health` -> `Hp`,
+// `*Health` and `Bar<Health>` change but `HealthBar` doesn't.
+export function renameInQuery(query: string, from: string, to: string): string {
+    const escaped = from.replace(/[.*+?^${}()|[\]\\]/g, "\\$&");
+    return query.replace(new RegExp("(^|[^\\w])" + escaped + "(?!\\w)", "g"), (_, prefix: string) => prefix + to);
+}
+
+interface SavedNotebook {
+    cells: { source: string[], kind: "markup" | "code" }[];
+}
+
+// Saved `.bevyrly` notebooks: open ones are edited in place and re-run, closed ones are rewritten on disk.
+async function renameInNotebooks(from: string, to: string): Promise<number> {
+    let changed = 0;
+    let open: Set<string> = new Set();
+    let edit = new vscode.WorkspaceEdit();
+    let rerun: vscode.NotebookDocument[] = [];
+    for (const notebook of vscode.workspace.notebookDocuments.filter(notebook => notebook.notebookType == 'bevyrly-notebook')) {
+        open.add(notebook.uri.toString());
+        let touched = false;
+        for (const cell of notebook.getCells().filter(cell => cell.kind == vscode.NotebookCellKind.Code)) {
+            const text = cell.document.getText();
+            const renamed = renameInQuery(text, from, to);
+            if (renamed != text) {
+                edit.replace(cell.document.uri, new vscode.Range(cell.document.positionAt(0), cell.document.positionAt(text.length)), renamed);
+                changed++;
+                touched = true;
+            }
+        }
+        if (touched) {
+            rerun.push(notebook);
+        }
+    }
+    await vscode.workspace.applyEdit(edit);
+
+    for (const notebook of rerun) {
+        await vscode.window.showNotebookDocument(notebook);
+        await vscode.commands.executeCommand('notebook.execute');
+    }
+
+    for (const file of await vscode.workspace.findFiles('**/*.bevyrly')) {
+        if (open.has(file.toString())) continue;
+
+        const contents = new TextDecoder().decode(await vscode.workspace.fs.readFile(file));
+        let notebook: SavedNotebook;
+        try {
+            notebook = JSON.parse(contents);
+        } catch {
+            continue;
+        }
+
+        let touched = false;
+        for (const cell of notebook.cells.filter(cell => cell.kind == "code")) {
+            const renamed = cell.source.map(line => renameInQuery(line, from, to));
+            if (renamed.join("\n") != cell.source.join("\n")) {
+                cell.source = renamed;
+                changed++;
+                touched = true;
+            }
+        }
+        if (touched) {
+            await vscode.workspace.fs.writeFile(file, new TextEncoder().encode(JSON.stringify(notebook)));
+        }
+    }
+
+    return changed;
+}
+
+export function registerRename(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableRename = vscode.commands.registerCommand('bevyrly.renameInQueries', async () => {
+        const editor = vscode.window.activeTextEditor;
+        const range = editor?.document.getWordRangeAtPosition(editor.selection.active, /[A-Za-z_]\w*/);
+        const from = await vscode.window.showInputBox({
+            value: range ? editor?.document.getText(range) : '',
+            prompt: 'Type renamed in the code'
+        });
+
+        if (!from) {
+            return;
+        }
+
+        const to = await vscode.window.showInputBox({ value: from, prompt: 'New name of ' + from });
+        if (!to || to == from) {
+            return;
+        }
+
+        bevyrlyIndex.lastQuery = renameInQuery(bevyrlyIndex.lastQuery, from, to);
+        const changed = await renameInNotebooks(from, to);
+        vscode.window.showInformationMessage("bevyrly updated " + changed + " saved queries from " + from + " to " + to);
+    });
+
+    context.subscriptions.push(disposableRename);
+}
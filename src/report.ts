@@ -0,0 +1,192 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, QueryStorage, whenIndexed } from '.';
+import { expandLinkFromName, expandModuleFromName } from './extension';
+import { exportSystems } from './export';
+import { derivedMeanings, querySymbolTable } from './symbols';
+
+export const accessLabels: [QueryStorage, string][] = [
+    ["query", "Reads components"],
+    ["mut_query", "Writes components"],
+    ["with", "With"],
+    ["without", "Without"],
+    ["res", "Reads resources"],
+    ["mut_res", "Writes resources"],
+    ["event_read", "Reads events"],
+    ["event_write", "Writes events"],
+];
+
+function code(items: string[]): string {
+    return items.map(item => "`" + item + "`").join(", ");
+}
+
+export function systemsByCrate(bevyrlyIndex: BevyrlyIndex): Map<string, string[]> {
+    let crates: Map<string, string[]> = new Map();
+    for (const system of Array.from(bevyrlyIndex.systems.keys()).sort()) {
+        const [crate, _] = expandModuleFromName(bevyrlyIndex, system) ?? ["", ""];
+        if (!crates.has(crate)) {
+            crates.set(crate, []);
+        }
+
+        crates.get(crate)?.push(system);
+    }
+
+    return crates;
+}
+
+function crossReference(bevyrlyIndex: BevyrlyIndex, systems: string[], readers: Map<string, Set<string>>, writers: Map<string, Set<string>>): string[] {
+    let lines: string[] = [];
+    const keys = new Set([...readers.keys(), ...writers.keys()]);
+    for (const key of Array.from(keys).sort()) {
+        const read = Array.from(readers.get(key) ?? []).filter(system => systems.includes(system)).sort();
+        const written = Array.from(writers.get(key) ?? []).filter(system => systems.includes(system)).sort();
+        if (read.length + written.length == 0) continue;
+
+        lines.push("### `" + key + "`", "");
+        lines.push("- Writers: " + (written.length > 0 ? code(written) : "_none_"));
+        lines.push("- Readers: " + (read.length > 0 ? code(read) : "_none_"), "");
+    }
+
+    return lines;
+}
+
+export function generateCrateReport(bevyrlyIndex: BevyrlyIndex, crate: string, systems: string[]): string {
+    let lines: string[] = ["# " + (crate || "Systems") + " systems catalog", ""];
+
+    lines.push("## Systems", "");
+    for (const system of systems) {
+        const [_, module] = expandModuleFromName(bevyrlyIndex, system) ?? ["", ""];
+        lines.push("### `" + (module ? module + "::" : "") + system + "`", "");
+
+        const link = expandLinkFromName(bevyrlyIndex, system);
+        if (link) {
+            lines.push("_" + link[1] + "_", "");
+        }
+
+        const doc = bevyrlyIndex.docs.get(system);
+        if (doc) {
+            lines.push(doc, "");
+        }
+
+        for (const registration of bevyrlyIndex.registrations.get(system) ?? []) {
+            lines.push("- Schedule: `" + registration.schedule + "`" +
+                (registration.conditions.length > 0 ? ", run if " + code(registration.conditions) : ""));
+        }
+
+        const accesses = bevyrlyIndex.accessesOf(system);
+        for (const [storage, label] of accessLabels) {
+            const keys = accesses.get(storage);
+            if (keys) {
+                lines.push("- " + label + ": " + code(keys.sort()));
+            }
+        }
+        lines.push("");
+    }
+
+    lines.push("## Events", "");
+    lines.push(...crossReference(bevyrlyIndex, systems, bevyrlyIndex.event_read, bevyrlyIndex.event_write));
+
+    lines.push("## Resources", "");
+    lines.push(...crossReference(bevyrlyIndex, systems, bevyrlyIndex.res, bevyrlyIndex.mut_res));
+
+    return lines.join("\n");
+}
+
+const siteScript = `
+const escape = text => text.replace(/[&<>"]/g, c => ({ "&": "&amp;", "<": "&lt;", ">": "&gt;", '"': "&quot;" })[c]);
+
+function matches(system, part) {
+    const prefix = symbols.find(([symbol, _]) => part.startsWith(symbol));
+    if (prefix) {
+        return (system.accesses[prefix[1]] || []).some(key => key.includes(part.slice(prefix[0].length)));
+    }
+    return system.name.includes(part) || Object.values(system.accesses).some(keys => keys.some(key => key.includes(part)));
+}
+
+function render() {
+    const parts = document.getElementById("search").value.split(" ").filter(part => part.length > 0);
+    const found = systems.filter(system => parts.every(part => matches(system, part)));
+    document.getElementById("count").textContent = found.length + " of " + systems.length + " systems";
+    document.getElementById("systems").innerHTML = found.map(system =>
+        "<details><summary><code>" + escape((system.module ? system.module + "::" : "") + system.name) + "</code> <small>" +
+        escape(system.crate + " / " + system.file + ":" + system.line) + "</small></summary>" +
+        (system.doc ? "<p>" + escape(system.doc) + "</p>" : "") + "<ul>" +
+        system.registrations.map(reg => "<li>Schedule: <code>" + escape(reg.schedule) + "</code>" +
+            (reg.conditions.length > 0 ? ", run if <code>" + escape(reg.conditions.join(", ")) + "</code>" : "") + "</li>").join("") +
+        labels.filter(([storage, _]) => system.accesses[storage]).map(([storage, label]) =>
+            "<li>" + label + ": <code>" + escape(system.accesses[storage].join(", ")) + "</code></li>").join("") +
+        "</ul></details>").join("");
+}
+
+document.getElementById("search").addEventListener("input", render);
+render();
+`;
+
+export function generateSite(bevyrlyIndex: BevyrlyIndex): string {
+    const data = JSON.stringify(exportSystems(bevyrlyIndex)).replace(/</g, "\\u003c");
+    const labels = JSON.stringify(accessLabels);
+    // The site only has accesses to search, so schedule, owner and asset symbols stay plain text there.
+    const symbols = JSON.stringify(querySymbolTable().filter(([_, meaning]) => !derivedMeanings.includes(meaning)))
+        .replace(/</g, "\\u003c");
+
+    return [
+        "<!DOCTYPE html>",
+        "<html><head><meta charset='utf-8'><title>Bevyrly ECS documentation</title>",
+        "<style>body { font-family: sans-serif; max-width: 960px; margin: auto; } #search { width: 100%; font-size: 1.2em; } " +
+        "details { border-bottom: 1px solid #ddd; padding: 4px; } small { color: #888; }</style>",
+        "</head><body>",
+        "<h1>Bevyrly ECS documentation</h1>",
+        "<input id='search' placeholder='&amp;Transform !ShipFireEvent +Player' autofocus>",
+        "<p id='count'></p>",
+        "<div id='systems'></div>",
+        "<script>const systems = " + data + ";\nconst labels = " + labels + ";\nconst symbols = " + symbols + ";" + siteScript + "</script>",
+        "</body></html>",
+    ].join("\n");
+}
+
+export function registerReport(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableReport = vscode.commands.registerCommand('bevyrly.generateReport', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const folders = await vscode.window.showOpenDialog({
+            canSelectFiles: false,
+            canSelectFolders: true,
+            canSelectMany: false,
+            openLabel: "Generate Report Here"
+        });
+
+        if (!folders || folders.length == 0) {
+            return;
+        }
+
+        for (const [crate, systems] of systemsByCrate(bevyrlyIndex)) {
+            const target = vscode.Uri.joinPath(folders[0], (crate || "systems") + ".md");
+            const contents = generateCrateReport(bevyrlyIndex, crate, systems);
+            await vscode.workspace.fs.writeFile(target, new TextEncoder().encode(contents));
+        }
+
+        vscode.window.showInformationMessage("Generated bevyrly report in " + folders[0].path);
+    });
+
+    context.subscriptions.push(disposableReport);
+
+    let disposableSite = vscode.commands.registerCommand('bevyrly.generateSite', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const folders = await vscode.window.showOpenDialog({
+            canSelectFiles: false,
+            canSelectFolders: true,
+            canSelectMany: false,
+            openLabel: "Generate Site Here"
+        });
+
+        if (!folders || folders.length == 0) {
+            return;
+        }
+
+        const target = vscode.Uri.joinPath(folders[0], "index.html");
+        await vscode.workspace.fs.writeFile(target, new TextEncoder().encode(generateSite(bevyrlyIndex)));
+        vscode.window.showInformationMessage("Generated bevyrly site at " + target.path);
+    });
+
+    context.subscriptions.push(disposableSite);
+}
@@ -0,0 +1,305 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, SystemSpan } from '.';
+import { escapeHtml, expandLinkFromName, expandModuleFromName, provenanceOf } from './extension';
+import { systemParameters } from './complexity';
+import { blameLabel } from './blame';
+import { blankComments, matchingClose } from './registrations';
+import { QueryMeaning, parseQueryPart } from './symbols';
+import { systemId } from './ids';
+import { tagsOf } from './tags';
+import { featureScopedGet } from './cfg';
+
+export type ResultLayout = "list" | "grouped" | "table";
+
+export type ResultColumn = "name" | "crate" | "file" | "schedule" | "params" | "matched" | "ordering" | "owner" | "tags";
+
+export const resultColumnLabels: [ResultColumn, string][] = [
+    ["name", "System"],
+    ["crate", "Crate"],
+    ["file", "File"],
+    ["schedule", "Schedule"],
+    ["params", "#Params"],
+    ["matched", "Matched"],
+    ["ordering", "Ordering"],
+    ["owner", "Last change"],
+    ["tags", "Tags"],
+];
+
+export function resultLayout(): ResultLayout {
+    return vscode.workspace.getConfiguration('bevyrly').get<ResultLayout>('resultLayout') ?? "list";
+}
+
+export function resultColumns(): ResultColumn[] {
+    const columns = vscode.workspace.getConfiguration('bevyrly').get<ResultColumn[]>('resultColumns') ?? [];
+    const known = resultColumnLabels.map(([column, _]) => column);
+    const selected = columns.filter(column => known.includes(column));
+    return selected.length > 0 ? selected : known;
+}
+
+// 0-based, like `vscode.Range`, so records can be turned into ranges (and serialized) as they are.
+export interface SourceRange {
+    start: { line: number, character: number };
+    end: { line: number, character: number };
+}
+
+export interface MatchedToken {
+    // The query part that matched, as typed in the active symbols (e.g. `*Transform`).
+    text: string;
+    meaning: QueryMeaning | undefined;
+    type: string;
+    range?: SourceRange;
+}
+
+// Everything known about one system matching a query. Tables, exports and the extension API all render from this.
+export interface SystemMatch {
+    // See `systemId`.
+    id: string;
+    system: string;
+    crate: string;
+    module: string;
+    provenance: string;
+    file: string;
+    location: string;
+    line: number;
+    range?: SourceRange;
+    signature?: SourceRange;
+    params: number;
+    tokens: MatchedToken[];
+    matched: string[];
+    schedules: string[];
+    conditions: string[];
+    ordering: { after: string[], before: string[] };
+    owner: string;
+    tags: string[];
+}
+
+// Lines come from the file's own line table (`src.l`), built once per file, so a page of results doesn't rescan files
+// from their start for every range.
+function rangeOf(src: SystemSpan["src"], text: string, start: number, end: number): SourceRange {
+    const position = (offset: number) =>
+        ({ line: src.l(offset), character: offset - (offset > 0 ? text.lastIndexOf("\n", offset - 1) + 1 : 0) });
+    return { start: position(start), end: position(end) };
+}
+
+// From the start of the system to just before its body, where clauses included.
+function signatureEnd(text: string, start: number, end: number): number {
+    const code = blankComments(text.slice(start, end));
+    const open = code.indexOf("(");
+    if (open < 0) return end;
+    const body = code.indexOf("{", matchingClose(code, open));
+    return start + (body < 0 ? code.length : code.slice(0, body).trimEnd().length);
+}
+
+export function systemMatch(bevyrlyIndex: BevyrlyIndex, query: string, system: string): SystemMatch {
+    const loc = bevyrlyIndex.locs.get(system);
+    const filepath = loc?.src.filepath ?? "";
+    const text = bevyrlyIndex.sources.get(filepath);
+    const [crate, module] = expandModuleFromName(bevyrlyIndex, system) ?? ["", ""];
+    const location = expandLinkFromName(bevyrlyIndex, system)?.[1] ?? "";
+    const registrations = bevyrlyIndex.registrations.get(system) ?? [];
+    const [after, before] = bevyrlyIndex.orderingOf(system);
+
+    const sigEnd = loc && text !== undefined ? signatureEnd(text, loc[0], loc[1]) : undefined;
+    const matched = bevyrlyIndex.matchedAccesses(query, system);
+    const tokens = matched.map((token): MatchedToken => {
+        const [meaning, _, type] = parseQueryPart(token);
+        let range: SourceRange | undefined = undefined;
+        if (loc && text !== undefined && sigEnd !== undefined) {
+            const escaped = type.replace(/[.*+?^${}()|[\]\\]/g, "\\$&");
+            const found = new RegExp("\\b" + escaped + "(?![\\w])").exec(blankComments(text.slice(loc[0], sigEnd)));
+            if (found) range = rangeOf(loc.src, text, loc[0] + found.index, loc[0] + found.index + type.length);
+        }
+        return { text: token, meaning, type, range };
+    });
+
+    return {
+        id: systemId(bevyrlyIndex, system),
+        system,
+        crate,
+        module,
+        provenance: provenanceOf(bevyrlyIndex, system),
+        file: filepath,
+        location,
+        line: loc ? loc.src.l(loc[0]) + 1 : 0,
+        range: loc && text !== undefined ? rangeOf(loc.src, text, loc[0], loc[1]) : undefined,
+        signature: loc && text !== undefined && sigEnd !== undefined ? rangeOf(loc.src, text, loc[0], sigEnd) : undefined,
+        params: systemParameters(loc?.getText() ?? "").length,
+        tokens,
+        matched,
+        schedules: bevyrlyIndex.schedulesOf(system),
+        conditions: Array.from(new Set(registrations.flatMap(reg => reg.conditions))),
+        ordering: { after, before },
+        owner: blameLabel(bevyrlyIndex, system),
+        tags: tagsOf(bevyrlyIndex, system),
+    };
+}
+
+// `--features` and the other build flags in `query` leave out systems cfg'd out of that build.
+export function queryMatches(bevyrlyIndex: BevyrlyIndex, query: string): SystemMatch[] {
+    const [found, _, parts] = featureScopedGet(bevyrlyIndex, query);
+    return found.slice().sort().map(system => systemMatch(bevyrlyIndex, parts, system));
+}
+
+export interface ResultPage {
+    total: number;
+    offset: number;
+    // 1-based, out of `pages`.
+    page: number;
+    pages: number;
+    systems: string[];
+}
+
+// Results per page in notebooks and query documents; 0 shows them all.
+export function pageSize(): number {
+    return vscode.workspace.getConfiguration('bevyrly').get<number>('pageSize') ?? 200;
+}
+
+// Takes a `page:N` part (1-based) out of a query, so it only decides which slice of the results is shown.
+export function splitPage(query: string): [string, number] {
+    let page = 1;
+    const rest = query.split(" ").filter(part => {
+        const found = part.match(/^page:(\d+)$/);
+        if (found) page = Math.max(1, Number(found[1]));
+        return !found;
+    });
+    return [rest.join(" "), page];
+}
+
+// Pages past the end show the last one.
+export function resultPage(systems: string[], page: number, size: number = pageSize()): ResultPage {
+    if (size <= 0) {
+        return { total: systems.length, offset: 0, page: 1, pages: 1, systems };
+    }
+    const pages = Math.max(1, Math.ceil(systems.length / size));
+    page = Math.min(Math.max(1, page), pages);
+    const offset = (page - 1) * size;
+    return { total: systems.length, offset, page, pages, systems: systems.slice(offset, offset + size) };
+}
+
+// "Showing 201–400 of 3456 systems; run `&Transform page:3` for more", or undefined when everything fits on one page.
+export function pageSummary(page: ResultPage, query: string): string | undefined {
+    if (page.pages <= 1) {
+        return undefined;
+    }
+    const next = page.page < page.pages ? "; run `" + (query + " page:" + (page.page + 1)).trim() + "` for more" : "";
+    return "Showing " + (page.offset + 1) + "–" + (page.offset + page.systems.length) + " of " + page.total + " systems" + next;
+}
+
+export function resultRow(bevyrlyIndex: BevyrlyIndex, query: string, system: string): Record<ResultColumn, string> {
+    return resultRowOf(systemMatch(bevyrlyIndex, query, system));
+}
+
+export function resultRowOf(match: SystemMatch): Record<ResultColumn, string> {
+    return {
+        name: match.system,
+        crate: match.provenance,
+        file: match.location,
+        schedule: match.schedules.join(", "),
+        params: String(match.params),
+        matched: match.matched.join(" "),
+        ordering: orderingLabel(match.ordering.after, match.ordering.before),
+        owner: match.owner,
+        tags: match.tags.join(", "),
+    };
+}
+
+// "after a, b; before c", or an empty string for systems without explicit ordering.
+export function orderingSummary(bevyrlyIndex: BevyrlyIndex, system: string): string {
+    const [after, before] = bevyrlyIndex.orderingOf(system);
+    return orderingLabel(after, before);
+}
+
+function orderingLabel(after: string[], before: string[]): string {
+    let parts: string[] = [];
+    if (after.length > 0) parts.push("after " + after.join(", "));
+    if (before.length > 0) parts.push("before " + before.join(", "));
+    return parts.join("; ");
+}
+
+// Aligned plain-text table, for the virtual query documents.
+export function resultTextTable(rows: Record<ResultColumn, string>[], columns: ResultColumn[]): string {
+    const headers = columns.map(column => resultColumnLabels.find(([other, _]) => other == column)?.[1] ?? column);
+    const widths = columns.map((column, i) => Math.max(headers[i].length, ...rows.map(row => row[column].length)));
+    const line = (cells: string[]) => "// " + cells.map((cell, i) => cell.padEnd(widths[i])).join(" | ").trimEnd();
+
+    return [
+        line(headers),
+        line(widths.map(width => "-".repeat(width))),
+        ...rows.map(row => line(columns.map(column => row[column]))),
+    ].join("\n") + "\n";
+}
+
+// Notebook outputs share one webview, so the sorting script only touches the table it was rendered with.
+export function resultHtmlTable(rows: Record<ResultColumn, string>[], columns: ResultColumn[], linkOf: (name: string) => string | undefined): string {
+    const id = "bevyrly-results-" + Math.random().toString(36).slice(2);
+    const headers = columns.map(column => "<th style='cursor: pointer'>" +
+        escapeHtml(resultColumnLabels.find(([other, _]) => other == column)?.[1] ?? column) + "</th>");
+    const link = (name: string) => {
+        const href = linkOf(name);
+        return href ? "<a href='" + escapeHtml(href) + "'>" + escapeHtml(name) + "</a>" : escapeHtml(name);
+    };
+    const body = rows.map(row => "<tr>" + columns.map(column => {
+        let value = escapeHtml(row[column]);
+        if (column == "name") {
+            value = link(row.name);
+        } else if (column == "matched") {
+            value = row.matched.split(" ").map(link).join(" ");
+        }
+        return "<td data-value='" + escapeHtml(row[column]) + "'>" + value + "</td>";
+    }).join("") + "</tr>");
+
+    return "<table id='" + id + "' style='border-collapse: collapse'><thead><tr>" + headers.join("") + "</tr></thead><tbody>" +
+        body.join("") + "</tbody></table><script>(() => {\n" +
+        "const table = document.getElementById('" + id + "');\n" +
+        "table.querySelectorAll('th').forEach((header, column) => header.addEventListener('click', () => {\n" +
+        "    const body = table.tBodies[0];\n" +
+        "    const rows = Array.from(body.rows);\n" +
+        "    const descending = header.dataset.order == 'asc';\n" +
+        "    rows.sort((a, b) => {\n" +
+        "        const x = a.cells[column].dataset.value, y = b.cells[column].dataset.value;\n" +
+        "        const order = (x == '' || isNaN(x) || isNaN(y)) ? x.localeCompare(y) : x - y;\n" +
+        "        return descending ? -order : order;\n" +
+        "    });\n" +
+        "    table.querySelectorAll('th').forEach(other => delete other.dataset.order);\n" +
+        "    header.dataset.order = descending ? 'desc' : 'asc';\n" +
+        "    rows.forEach(row => body.appendChild(row));\n" +
+        "}));\n" +
+        "})();</script>";
+}
+
+// crate → module → systems, each level sorted by name.
+export function groupByModule(bevyrlyIndex: BevyrlyIndex, systems: string[]): Map<string, Map<string, string[]>> {
+    let groups: Map<string, Map<string, string[]>> = new Map();
+    for (const system of [...systems].sort()) {
+        const [crate, module] = expandModuleFromName(bevyrlyIndex, system) ?? ["", ""];
+        if (!groups.has(crate)) {
+            groups.set(crate, new Map());
+        }
+
+        let modules = groups.get(crate) as Map<string, string[]>;
+        if (!modules.has(module)) {
+            modules.set(module, []);
+        }
+        modules.get(module)?.push(system);
+    }
+
+    let sorted: Map<string, Map<string, string[]>> = new Map();
+    for (const crate of Array.from(groups.keys()).sort()) {
+        const modules = groups.get(crate) as Map<string, string[]>;
+        let sortedModules: Map<string, string[]> = new Map();
+        for (const module of Array.from(modules.keys()).sort()) {
+            sortedModules.set(module, modules.get(module) ?? []);
+        }
+        sorted.set(crate, sortedModules);
+    }
+
+    return sorted;
+}
+
+export function crateLabel(crate: string): string {
+    return crate.length > 0 ? crate : "(crate)";
+}
+
+export function moduleLabel(module: string): string {
+    return module.length > 0 ? module : "(root)";
+}
@@ -0,0 +1,219 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { expandLinkFromName, revealSystem } from './extension';
+import { resolveChanged } from './changed';
+import { featureScopedGet } from './cfg';
+
+// A named line of inquiry. Its scope is ANDed onto every query run in it (e.g. `@Update` for an "update loop" audit),
+// and its pinned systems stay listed above the results of each query, so switching to an ad-hoc session and back
+// loses nothing.
+export interface QuerySession {
+    name: string;
+    scope: string;
+    pinned: string[];
+    lastQuery: string;
+}
+
+export const defaultSessionName = "default";
+
+export class SessionStore {
+    private _state: vscode.Memento;
+    private _onDidChange = new vscode.EventEmitter<void>();
+    readonly onDidChange = this._onDidChange.event;
+
+    constructor(state: vscode.Memento) {
+        this._state = state;
+    }
+
+    all(): QuerySession[] {
+        const sessions = this._state.get<QuerySession[]>('bevyrly.sessions') ?? [];
+        return sessions.some(session => session.name == defaultSessionName)
+            ? sessions
+            : [{ name: defaultSessionName, scope: "", pinned: [], lastQuery: "" }, ...sessions];
+    }
+
+    get(name: string | undefined): QuerySession {
+        const sessions = this.all();
+        return sessions.find(session => session.name == (name ?? this.activeName())) ?? sessions[0];
+    }
+
+    activeName(): string {
+        return this._state.get<string>('bevyrly.activeSession') ?? defaultSessionName;
+    }
+
+    async update(session: QuerySession) {
+        const others = this.all().filter(other => other.name != session.name);
+        await this._state.update('bevyrly.sessions', [...others, session].sort((a, b) => a.name.localeCompare(b.name)));
+        this._onDidChange.fire();
+    }
+
+    async remove(name: string) {
+        await this._state.update('bevyrly.sessions', this.all().filter(session => session.name != name));
+        if (this.activeName() == name) {
+            await this._state.update('bevyrly.activeSession', undefined);
+        }
+        this._onDidChange.fire();
+    }
+
+    async activate(name: string) {
+        await this._state.update('bevyrly.activeSession', name);
+        this._onDidChange.fire();
+    }
+}
+
+let sessionStore: SessionStore | undefined = undefined;
+
+export function sessions(): QuerySession[] {
+    return sessionStore?.all() ?? [];
+}
+
+export function sessionNamed(name?: string): QuerySession {
+    return sessionStore?.get(name) ?? { name: defaultSessionName, scope: "", pinned: [], lastQuery: "" };
+}
+
+export function activeSessionName(): string {
+    return sessionStore?.activeName() ?? defaultSessionName;
+}
+
+// The query as it is evaluated in `session`: its scope parts go after a leading `:` and before the typed ones.
+export function scopedQuery(session: QuerySession, query: string): string {
+    if (session.scope.trim().length == 0) {
+        return query;
+    }
+    const long = query.startsWith(":");
+    return (long ? ":" : "") + (session.scope.trim() + " " + (long ? query.slice(1) : query).trim()).trim();
+}
+
+// Remembers the last query of `name`, which later prompts in that session start from.
+export function noteSessionQuery(name: string, query: string) {
+    const session = sessionNamed(name);
+    if (sessionStore && session.lastQuery != query) {
+        sessionStore.update({ ...session, lastQuery: query });
+    }
+}
+
+async function pickSession(title: string, allowNew: boolean): Promise<string | undefined> {
+    const newItem = "$(add) New session...";
+    const items = sessions().map(session => ({
+        label: session.name,
+        description: (session.name == activeSessionName() ? "active · " : "") + (session.scope || "no scope") +
+            (session.pinned.length > 0 ? " · " + session.pinned.length + " pinned" : ""),
+    }));
+    const picked = await vscode.window.showQuickPick(allowNew ? [...items, { label: newItem, description: "" }] : items, { title });
+    if (!picked || picked.label != newItem) {
+        return picked?.label;
+    }
+
+    const name = await vscode.window.showInputBox({ prompt: 'Session name', placeHolder: 'event audit' });
+    if (!name || name.trim().length == 0) {
+        return undefined;
+    }
+    const scope = await vscode.window.showInputBox({ prompt: 'Scope, ANDed onto every query in the session (optional)', placeHolder: '@Update' });
+    await sessionStore?.update({ name: name.trim(), scope: scope ?? "", pinned: [], lastQuery: "" });
+    return name.trim();
+}
+
+export function registerSessions(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    sessionStore = new SessionStore(context.workspaceState);
+    const store = sessionStore;
+
+    const status = vscode.window.createStatusBarItem(vscode.StatusBarAlignment.Left);
+    status.command = 'bevyrly.switchSession';
+    const refresh = () => {
+        const session = store.get(undefined);
+        status.text = "$(search) bevyrly: " + session.name;
+        status.tooltip = "Query session" + (session.scope ? ", scoped to " + session.scope : "") + ". Click to switch";
+        if (session.name == defaultSessionName && sessions().length == 1) {
+            status.hide();
+        } else {
+            status.show();
+        }
+    };
+    refresh();
+    context.subscriptions.push(status, store.onDidChange(refresh));
+
+    // Each session keeps its own last query; switching swaps it in and out of the shared prompt default. A focused
+    // bevyrly notebook is bound to the session, and keeps running its cells in it after switching elsewhere.
+    let disposableSwitch = vscode.commands.registerCommand('bevyrly.switchSession', async () => {
+        const name = await pickSession('Switch query session', true);
+        if (!name) {
+            return;
+        }
+        await store.update({ ...store.get(undefined), lastQuery: bevyrlyIndex.lastQuery });
+        await store.activate(name);
+        bevyrlyIndex.lastQuery = store.get(name).lastQuery;
+
+        const notebook = vscode.window.activeNotebookEditor?.notebook;
+        if (notebook?.notebookType == 'bevyrly-notebook') {
+            let edit = new vscode.WorkspaceEdit();
+            edit.set(notebook.uri, [vscode.NotebookEdit.updateNotebookMetadata({ ...notebook.metadata, session: name })]);
+            await vscode.workspace.applyEdit(edit);
+        }
+    });
+    context.subscriptions.push(disposableSwitch);
+
+    let disposableScope = vscode.commands.registerCommand('bevyrly.setSessionScope', async () => {
+        const session = store.get(undefined);
+        const scope = await vscode.window.showInputBox({ value: session.scope, prompt: 'Scope of session "' + session.name + '"', placeHolder: '@Update' });
+        if (scope !== undefined) {
+            await store.update({ ...session, scope });
+        }
+    });
+    context.subscriptions.push(disposableScope);
+
+    let disposableDelete = vscode.commands.registerCommand('bevyrly.deleteSession', async () => {
+        const name = await pickSession('Delete query session', false);
+        if (name) {
+            await store.remove(name);
+        }
+    });
+    context.subscriptions.push(disposableDelete);
+
+    let disposablePin = vscode.commands.registerCommand('bevyrly.pinSystem', async (system?: string) => {
+        await whenIndexed(context, bevyrlyIndex);
+        const session = store.get(undefined);
+        if (!system) {
+            if (bevyrlyIndex.lastQuery) {
+                await resolveChanged(bevyrlyIndex, scopedQuery(session, bevyrlyIndex.lastQuery));
+            }
+            const candidates = bevyrlyIndex.lastQuery ? featureScopedGet(bevyrlyIndex, scopedQuery(session, bevyrlyIndex.lastQuery))[0] : Array.from(bevyrlyIndex.locs.keys());
+            const picked = await vscode.window.showQuickPick(candidates.filter(name => !session.pinned.includes(name)).sort().map(name => ({
+                label: name,
+                detail: expandLinkFromName(bevyrlyIndex, name)?.[1]
+            })), { title: 'Pin a system to session "' + session.name + '"' });
+            system = picked?.label;
+        }
+        if (system && !session.pinned.includes(system)) {
+            await store.update({ ...session, pinned: [...session.pinned, system] });
+        }
+    });
+    context.subscriptions.push(disposablePin);
+
+    let disposablePinned = vscode.commands.registerCommand('bevyrly.showPinnedSystems', async () => {
+        const session = store.get(undefined);
+        const unpin = { iconPath: new vscode.ThemeIcon('pinned'), tooltip: 'Unpin' };
+        const quickPick = vscode.window.createQuickPick();
+        quickPick.title = 'Pinned in session "' + session.name + '"';
+        const fill = () => quickPick.items = store.get(session.name).pinned.map(name => ({
+            label: name,
+            detail: expandLinkFromName(bevyrlyIndex, name)?.[1],
+            buttons: [unpin],
+        }));
+        fill();
+        quickPick.onDidTriggerItemButton(async event => {
+            const current = store.get(session.name);
+            await store.update({ ...current, pinned: current.pinned.filter(name => name != event.item.label) });
+            fill();
+        });
+        quickPick.onDidAccept(async () => {
+            const picked = quickPick.selectedItems[0];
+            quickPick.hide();
+            if (picked) {
+                await revealSystem(bevyrlyIndex, picked.label);
+            }
+        });
+        quickPick.onDidHide(() => quickPick.dispose());
+        quickPick.show();
+    });
+    context.subscriptions.push(disposablePinned);
+}
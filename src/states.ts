@@ -0,0 +1,148 @@
+import { BevyrlyIndex } from '.';
+import { SystemRegistration, blankComments, matchingClose } from './registrations';
+
+export interface StateTransition {
+    system: string;
+    state: string;
+    to: string;
+}
+
+// Finds `#[derive(States)]` and `#[derive(SubStates)]` enums and returns their variants, in declaration order. Types with
+// an `impl ComputedStates` in the same file are states too, with no variants when they are structs.
+export function parseStates(source: string): Map<string, string[]> {
+    const text = blankComments(source);
+    let states: Map<string, string[]> = new Map();
+    for (const found of text.matchAll(/impl\s+(?:[\w:]*::)?ComputedStates\s+for\s+(\w+)/g)) {
+        states.set(found[1], []);
+    }
+    const pattern = /#\[derive\(([^)]*)\)\]\s*(?:#\[[^\]]*\]\s*)*(?:pub(?:\([^)]*\))?\s+)?enum\s+(\w+)[^{]*\{/g;
+    let found;
+    while ((found = pattern.exec(text)) !== null) {
+        if (!found[1].split(",").some(derive => derive.trim() == "States" || derive.trim() == "SubStates") && !states.has(found[2])) continue;
+
+        const open = found.index + found[0].length - 1;
+        const body = text.slice(open + 1, matchingClose(text, open));
+        const variants = body
+            .replace(/#\[[^\]]*\]/g, "")
+            .split(",")
+            .map(variant => variant.trim().match(/^\w+/)?.[0])
+            .filter((variant): variant is string => variant !== undefined);
+        states.set(found[2], variants);
+    }
+
+    return states;
+}
+
+// Finds `next.set(S::Variant)` calls where `next` is a `ResMut<NextState<S>>` parameter of the system.
+export function parseTransitions(system: string, source: string): StateTransition[] {
+    const text = blankComments(source);
+    let transitions: StateTransition[] = [];
+    const params = /(\w+)\s*:\s*ResMut\s*<\s*NextState\s*<\s*(\w+)\s*>\s*>/g;
+    let param;
+    while ((param = params.exec(text)) !== null) {
+        const calls = new RegExp("\\b" + param[1] + "\\s*\\.\\s*set\\s*\\(\\s*(?:\\w+::)*" + param[2] + "::(\\w+)", "g");
+        let call;
+        while ((call = calls.exec(text)) !== null) {
+            transitions.push({ system, state: param[2], to: call[1] });
+        }
+    }
+
+    return transitions;
+}
+
+// What sub-states and computed states derive from, as `State::Variant` (or just `State`) keys: the `#[source(..)]`
+// attribute of a `#[derive(SubStates)]` enum, or the `SourceStates` of an `impl ComputedStates for X`.
+export function parseStateSources(source: string): Map<string, string[]> {
+    const text = blankComments(source);
+    let sources: Map<string, string[]> = new Map();
+    const keyOf = (path: string) => path.replace(/\{[\s\S]*\}|\([\s\S]*\)/g, "").trim().split("::").slice(-2).join("::");
+
+    const sub = /#\[derive\(([^)]*)\)\]\s*((?:#\[[^\]]*\]\s*)*)(?:pub(?:\([^)]*\))?\s+)?enum\s+(\w+)/g;
+    let found;
+    while ((found = sub.exec(text)) !== null) {
+        if (!found[1].split(",").some(derive => derive.trim() == "SubStates")) continue;
+        const attribute = found[2].match(/#\[\s*source\s*\(\s*\w+\s*=\s*([^\]]*)\)\s*\]/)?.[1] ?? "";
+        sources.set(found[3], attribute.split("|").map(keyOf).filter(key => key.length > 0));
+    }
+
+    const computed = /impl\s+(?:[\w:]*::)?ComputedStates\s+for\s+(\w+)[^{]*\{/g;
+    while ((found = computed.exec(text)) !== null) {
+        const open = found.index + found[0].length - 1;
+        const types = text.slice(open + 1, matchingClose(text, open)).match(/type\s+SourceStates\s*=\s*([^;]+);/)?.[1] ?? "";
+        sources.set(found[1], Array.from(types.matchAll(/\b([A-Z]\w*)\b/g), type => type[1]).filter(type => type != "Option"));
+    }
+
+    return sources;
+}
+
+// The states a registration is gated on, as `State::Variant` (or `State`) keys: `in_state(..)` conditions and the
+// `OnEnter`/`OnExit`/`OnTransition` schedules. Gating on a sub-state or computed state also gates on what it derives
+// from, so `InGame` systems include those running only while `Paused::Yes`.
+export function statesOf(bevyrlyIndex: BevyrlyIndex, reg: SystemRegistration): string[] {
+    let gates: string[] = [];
+    for (const condition of reg.conditions) {
+        for (const found of condition.matchAll(/\bin_state\s*\(\s*([\w:]+)/g)) {
+            gates.push(found[1].split("::").slice(-2).join("::"));
+        }
+    }
+    if (/^(?:OnEnter|OnExit|OnTransition)\b/.test(reg.schedule)) {
+        for (const found of reg.schedule.matchAll(/\b([A-Z]\w*::\w+)/g)) {
+            gates.push(found[1]);
+        }
+    }
+
+    let expanded: Set<string> = new Set();
+    while (gates.length > 0) {
+        const gate = gates.pop() as string;
+        if (expanded.has(gate)) continue;
+        expanded.add(gate);
+        gates.push(...bevyrlyIndex.stateSources.get(gate.split("::")[0]) ?? []);
+    }
+    return Array.from(expanded);
+}
+
+// `State::Variant` (and `State`) -> the systems gated on it, which `state:` query parts match against.
+export function stateGates(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("state-gates", () => {
+        let gates: Map<string, Set<string>> = new Map();
+        for (const reg of Array.from(bevyrlyIndex.registrations.values()).flat()) {
+            for (const gate of statesOf(bevyrlyIndex, reg)) {
+                if (!gates.has(gate)) {
+                    gates.set(gate, new Set());
+                }
+                gates.get(gate)?.add(reg.system);
+            }
+        }
+        return gates;
+    });
+}
+
+// State type (and `State::Variant` set) -> the systems able to change it: those taking `ResMut<NextState<S>>`, and
+// those calling `commands.set_state(S::Variant)`. What `next:` query parts match against, for tracking down unexpected
+// transitions.
+export function stateWriters(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("state-writers", () => {
+        let writers: Map<string, Set<string>> = new Map();
+        const add = (key: string, system: string) => {
+            if (!writers.has(key)) {
+                writers.set(key, new Set());
+            }
+            writers.get(key)?.add(system);
+        };
+
+        for (const [system, loc] of bevyrlyIndex.locs) {
+            const text = blankComments(loc.getText());
+            for (const found of text.matchAll(/\bResMut\s*<\s*(?:'\w+\s*,\s*)?(?:[\w:]*::)?NextState\s*<\s*(?:[\w:]*::)?(\w+)\s*>\s*>/g)) {
+                add(found[1], system);
+            }
+            for (const transition of parseTransitions(system, text)) {
+                add(transition.state + "::" + transition.to, system);
+            }
+            for (const found of text.matchAll(/\.\s*set_state\s*\(\s*(?:\w+::)*(\w+)::(\w+)/g)) {
+                add(found[1], system);
+                add(found[1] + "::" + found[2], system);
+            }
+        }
+        return writers;
+    });
+}
@@ -0,0 +1,117 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { expandModuleFromPath } from './extension';
+import { findOrphanSystems } from './diagnostics';
+import { blankComments, parseDerives, pluginBlocks } from './registrations';
+
+export interface CrateStats {
+    crate: string;
+    systems: number;
+    components: number;
+    resources: number;
+    events: number;
+    schedules: number;
+    plugins: number;
+    orphans: number;
+    files: number;
+    lines: number;
+    bytes: number;
+    indexMs: number;
+}
+
+function crateOf(bevyrlyIndex: BevyrlyIndex, filepath: string): string {
+    return expandModuleFromPath(bevyrlyIndex, filepath)[0];
+}
+
+// What the index holds per crate, sorted by crate name. Types count by their derive, schedules by the distinct
+// schedules the crate's `add_systems` calls name.
+export function indexStats(bevyrlyIndex: BevyrlyIndex): CrateStats[] {
+    let stats: Map<string, CrateStats> = new Map();
+    let schedules: Map<string, Set<string>> = new Map();
+    const of = (filepath: string): CrateStats => {
+        const crate = crateOf(bevyrlyIndex, filepath);
+        if (!stats.has(crate)) {
+            stats.set(crate, {
+                crate, systems: 0, components: 0, resources: 0, events: 0, schedules: 0, plugins: 0, orphans: 0,
+                files: 0, lines: 0, bytes: 0, indexMs: 0
+            });
+            schedules.set(crate, new Set());
+        }
+        return stats.get(crate) as CrateStats;
+    };
+
+    for (const [filepath, text] of bevyrlyIndex.sources) {
+        let crate = of(filepath);
+        crate.files++;
+        crate.lines += text.split("\n").length;
+        crate.bytes += new TextEncoder().encode(text).length;
+        crate.indexMs += bevyrlyIndex.timings.get(filepath) ?? 0;
+        crate.plugins += pluginBlocks(blankComments(text)).length;
+        for (const type of parseDerives(text)) {
+            if (type.derives.includes("Component")) crate.components++;
+            if (type.derives.includes("Resource")) crate.resources++;
+            if (type.derives.includes("Event")) crate.events++;
+        }
+    }
+    for (const loc of bevyrlyIndex.locs.values()) {
+        of(loc.src.filepath ?? "").systems++;
+    }
+    for (const registrations of bevyrlyIndex.registrations.values()) {
+        for (const registration of registrations) {
+            of(registration.filepath);
+            schedules.get(crateOf(bevyrlyIndex, registration.filepath))?.add(registration.schedule);
+        }
+    }
+    for (const finding of findOrphanSystems(bevyrlyIndex)) {
+        of(finding.filepath).orphans++;
+    }
+    for (const [crate, names] of schedules) {
+        (stats.get(crate) as CrateStats).schedules = names.size;
+    }
+
+    return Array.from(stats.values()).sort((a, b) => a.crate.localeCompare(b.crate));
+}
+
+const statsColumns: [keyof CrateStats, string][] = [
+    ["systems", "Systems"],
+    ["components", "Components"],
+    ["resources", "Resources"],
+    ["events", "Events"],
+    ["schedules", "Schedules"],
+    ["plugins", "Plugins"],
+    ["orphans", "Orphan systems"],
+    ["files", "Files"],
+    ["lines", "Lines"],
+    ["bytes", "Bytes"],
+    ["indexMs", "Index ms"],
+];
+
+// A markdown table with a total row, meant to be pasted into issues as is.
+export function statsReport(stats: CrateStats[]): string {
+    const cell = (key: keyof CrateStats, value: number) => key == "indexMs" ? value.toFixed(0) : String(value);
+    let total = new Map(statsColumns.map(([key, _]) => [key, 0]));
+    let report = "# Bevyrly index statistics\n\n";
+    report += "| Crate | " + statsColumns.map(([_, label]) => label).join(" | ") + " |\n";
+    report += "|---" + "|---:".repeat(statsColumns.length) + "|\n";
+    for (const crate of stats) {
+        report += "| " + (crate.crate || "(crate)") + " | " + statsColumns.map(([key, _]) => {
+            total.set(key, (total.get(key) ?? 0) + (crate[key] as number));
+            return cell(key, crate[key] as number);
+        }).join(" | ") + " |\n";
+    }
+    // Crates share schedules, so a sum of them would mean nothing.
+    report +="| **Total** | " + statsColumns.map(([key, _]) => key == "schedules" ? "" : "**" + cell(key, total.get(key) ?? 0) + "**").join(" | ") + " |\n";
+
+    return report;
+}
+
+export function registerStats(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableStats = vscode.commands.registerCommand('bevyrly.showStats', async () => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        const doc = await vscode.workspace.openTextDocument({ content: statsReport(indexStats(bevyrlyIndex)), language: 'markdown' });
+        await vscode.window.showTextDocument(doc, { preview: false });
+    });
+
+    context.subscriptions.push(disposableStats);
+}
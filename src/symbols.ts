@@ -0,0 +1,247 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+
+export type QueryMeaning
+    = "query"
+    | "mut_query"
+    | "with"
+    | "without"
+    | "res"
+    | "mut_res"
+    | "event_read"
+    | "event_write"
+    | "schedule"
+    | "owner"
+    | "asset"
+    | "asset_spawn"
+    | "asset_type"
+    | "category"
+    | "tag"
+    | "res_condition"
+    | "state"
+    | "changed"
+    | "param"
+    | "first"
+    | "last"
+    | "gizmo_group"
+    | "next_state";
+
+// What each kind of query part finds, with `T` standing for the name typed after the symbol.
+export const queryMeanings: [QueryMeaning, string][] = [
+    ["query", "systems that include `Query<&T>`"],
+    ["mut_query", "systems that include `Query<&mut T>`"],
+    ["res", "systems that include `Res<T>`"],
+    ["mut_res", "systems that include `ResMut<T>` or `NonSendMut<T>`"],
+    ["event_read", "systems that include `EventReader<T>`"],
+    ["event_write", "systems that include `EventWriter<T>`"],
+    ["with", "systems that include `With<T>`"],
+    ["without", "systems that include `Without<T>`"],
+    ["schedule", "systems registered in a schedule whose name contains `T`"],
+    ["owner", "systems last changed by an author whose name contains `T` (needs `bevyrly.blame`)"],
+    ["asset", "systems that read a field of an `AssetCollection` resource, named `Collection.field`, that contains `T`"],
+    ["asset_spawn", "systems that spawn entities with a handle from an `AssetCollection` field that contains `T`"],
+    ["asset_type", "systems that load asset type `T` with the asset server (by turbofish, `Handle<T>` binding or file extension) or take `Assets<T>` or `Handle<T>`"],
+    ["category", "systems touching a cross-cutting concern: `visibility`, `render_layers`, or `camera`"],
+    ["res_condition", "systems with a run condition on resource `T`: `resource_exists::<T>`, `resource_changed::<T>`, `resource_equals(..)`, ..."],
+    ["state", "systems gated on state `T` (e.g. `AppState::InGame`) by `in_state` or `OnEnter`/`OnExit`, sub-states and computed states included"],
+    ["next_state", "systems that can change state `T`: `ResMut<NextState<T>>` parameters and `commands.set_state(..)`, with `T::Variant` for where `.set(..)` sends it"],
+    ["tag", "systems tagged with a tag containing `T`, by `#[bevyrly(tag = \"T\")]` or a `[[tags]]` rule in bevyrly.toml"],
+    ["param", "systems with a parameter whose name contains `T`, e.g. `param:commands`"],
+    ["first", "systems whose first parameter's type contains `T`, e.g. `first:Commands`"],
+    ["last", "systems whose last parameter's type contains `T`, e.g. `last:EventWriter`"],
+    ["gizmo_group", "systems drawing with `Gizmos<T>` (plain `Gizmos` is `DefaultGizmoConfigGroup`) or changing its config with `config_mut::<T>()`"],
+    ["changed", "systems in files git reports as changed: `working-tree` for uncommitted and untracked ones, `since <rev>` for everything differing from `<rev>`"],
+];
+
+// Meanings answered by analyses rather than the system's own parameters: its registrations (schedules, run
+// conditions and states, and the states it can change), blame and git status, the asset collections it reads from, its categories, its tags,
+// where its parameters sit and the gizmo groups it draws with.
+// They can't be declared for wrapper types, and only the editor (not the generated site) can search them.
+export const derivedMeanings: QueryMeaning[] = ["schedule", "owner", "asset", "asset_spawn", "asset_type", "category", "tag", "res_condition", "state", "changed", "param", "first", "last", "gizmo_group", "next_state"];
+
+const defaultSymbols: { [symbol: string]: QueryMeaning } = {
+    "&": "query",
+    "*": "mut_query",
+    "#": "res",
+    "$": "mut_res",
+    "?": "event_read",
+    "<": "event_read",
+    "!": "event_write",
+    ">": "event_write",
+    "+": "with",
+    "-": "without",
+    "@": "schedule",
+    "owner:": "owner",
+    "asset:": "asset",
+    "spawns:": "asset_spawn",
+    "asset_type:": "asset_type",
+    "category:": "category",
+    "tag:": "tag",
+    "if:": "res_condition",
+    "state:": "state",
+    "next:": "next_state",
+    "changed:": "changed",
+    "param:": "param",
+    "first:": "first",
+    "last:": "last",
+    "gizmos:": "gizmo_group",
+};
+
+let symbolTable: [string, QueryMeaning][] | undefined = undefined;
+
+// The built-in symbols overlaid with `bevyrly.querySymbols`, longest first so that `owner:` wins over `o`. Mapping a
+// symbol to "none" turns it off, which frees `<` and `>` to be swapped or replaced by e.g. `r:` and `w:`. Built once
+// and again whenever the setting changes, since every query part is parsed against it.
+export function querySymbolTable(): [string, QueryMeaning][] {
+    if (!symbolTable) {
+        symbolTable = buildSymbolTable(vscode.workspace.getConfiguration('bevyrly').get<{ [symbol: string]: string }>('querySymbols') ?? {});
+    }
+    return symbolTable;
+}
+
+function buildSymbolTable(configured: { [symbol: string]: string }): [string, QueryMeaning][] {
+    let table: Map<string, QueryMeaning> = new Map(Object.entries(defaultSymbols));
+    for (const [symbol, meaning] of Object.entries(configured)) {
+        if (meaning == "none") {
+            table.delete(symbol);
+        } else if (queryMeanings.some(([known, _]) => known == meaning)) {
+            table.set(symbol, meaning as QueryMeaning);
+        }
+    }
+
+    return Array.from(table.entries()).sort(([a, _a], [b, _b]) => b.length - a.length);
+}
+
+// The built-in symbols alone. bevyrly.toml and the built-in ecosystem parameters are written in these, so that a
+// shared config means the same to everyone whatever their `bevyrly.querySymbols`.
+export const defaultSymbolTable: [string, QueryMeaning][] = buildSymbolTable({});
+
+// Splits a query part into what it asks for, the symbol that says so, and the name; plain text has no meaning.
+export function parseQueryPart(part: string): [QueryMeaning | undefined, string, string] {
+    return parseQueryPartWith(querySymbolTable(), part);
+}
+
+export function parseQueryPartWith(table: [string, QueryMeaning][], part: string): [QueryMeaning | undefined, string, string] {
+    for (const [symbol, meaning] of table) {
+        if (symbol.length > 0 && part.startsWith(symbol)) {
+            return [meaning, symbol, part.slice(symbol.length)];
+        }
+    }
+
+    return [undefined, "", part];
+}
+
+// Splits a query on spaces into its parts, keeping `changed:since <rev>` together as `changed:since:<rev>`.
+export function queryParts(query: string): string[] {
+    let parts: string[] = [];
+    for (const part of query.split(" ")) {
+        const previous = parts[parts.length - 1];
+        if (previous !== undefined && part.length > 0 && /:since$/.test(previous) && parseQueryPart(previous)[0] == "changed") {
+            parts[parts.length - 1] = previous + ":" + part;
+        } else {
+            parts.push(part);
+        }
+    }
+    return parts;
+}
+
+// The symbol bevyrly uses when it writes a query part itself: the shortest one mapped to `meaning`.
+export function symbolOf(meaning: QueryMeaning): string {
+    const symbols = querySymbolTable().filter(([_, other]) => other == meaning).map(([symbol, _]) => symbol);
+    return symbols.sort((a, b) => a.length - b.length)[0] ?? "";
+}
+
+// The active mapping as [symbols, description] rows, in the order of `queryMeanings`.
+export function querySymbolHelp(): [string[], string][] {
+    const table = querySymbolTable();
+    return queryMeanings
+        .map(([meaning, description]): [string[], string] => [
+            table.filter(([_, other]) => other == meaning).map(([symbol, _]) => symbol).sort(),
+            description,
+        ])
+        .filter(([symbols, _]) => symbols.length > 0);
+}
+
+export interface SyntaxSection {
+    title: string;
+    // [what to type, what it does], with `code` in backticks.
+    rows: [string, string][];
+}
+
+// The query language as the parser currently reads it, symbol mapping included. Every cheat sheet is generated from
+// this, so none of them can drift from the implementation.
+export function querySyntax(): SyntaxSection[] {
+    const code = (text: string) => "`" + text + "`";
+    const search: [string, string][] = querySymbolHelp().map(([symbols, description]) =>
+        [symbols.map(symbol => code(symbol + "T")).join(", "), "find all " + description]);
+    search.push([code("JustText"), "will match any of the above (might yield a lot of content)"]);
+
+    // Examples using a meaning that has been mapped away are left out.
+    const example = (parts: [QueryMeaning | undefined, string][]): string | undefined =>
+        parts.some(([meaning, _]) => meaning && symbolOf(meaning).length == 0)
+            ? undefined
+            : parts.map(([meaning, name]) => (meaning ? symbolOf(meaning) : "") + name).join(" ");
+    const examples: [string | undefined, string][] = [
+        [example([["query", "Transform"], ["event_write", "ShipFireEvent"], ["with", "Player"]]),
+            "systems that query `Transform` immutably, write `ShipFireEvent`, and have a `With<Player>`"],
+        [example([["with", "Player"], ["without", "Player"]]),
+            "systems that require `With<Player>` and `Without<Player>` (possibly in different arguments)"],
+        [example([["mut_res", "Score"], ["schedule", "Update"]]), "systems in `Update` schedules that mutate the `Score` resource"],
+        [example([[undefined, "Foo"], [undefined, "Bar"]]),
+            "systems that have the strings `Foo` and `Bar` anywhere in their arguments (including resources, components, etc.)"],
+    ];
+
+    return [
+        { title: "Search control", rows: search },
+        {
+            title: "Output control", rows: [
+                [code("?"), "prints this documentation (in notebooks)"],
+                [code("~"), "prints the indexing log (in notebooks)"],
+                [code("my prompt goes here"), "find and print locations of all systems that mention 'my', 'prompt', 'goes', and 'here'"],
+                [code(":my prompt goes here"), "find and print the declarations of those systems instead"],
+                [code("my prompt page:2"), "shows the second page of results when there are more than `bevyrly.pageSize`"],
+            ]
+        },
+        {
+            title: "Flags", rows: [
+                [code("*Health <DamageEvent --explain"), "shows, per system matching any part, which parts matched and why the others didn't"],
+                [code("*Health --explain apply_damage"), "explains just `apply_damage`"],
+                [code("&Transform --features editor,debug"), "only systems a build with those features compiles in, counting what each feature enables"],
+                [code("--no-default-features"), "leaves the crate's `default` features off"],
+                [code("--release"), "builds without `debug_assertions`"],
+            ]
+        },
+        {
+            title: "Examples",
+            rows: examples.filter((row): row is [string, string] => row[0] !== undefined).map(([query, description]) => [code(query), description]),
+        },
+    ];
+}
+
+export function querySyntaxMarkdown(): string {
+    let lines = ["# Bevyrly query syntax", ""];
+    for (const section of querySyntax()) {
+        lines.push("## " + section.title, "");
+        lines.push(...section.rows.map(([syntax, description]) => "- " + syntax + ": " + description));
+        lines.push("");
+    }
+    return lines.join("\n");
+}
+
+export function registerSyntaxHelp(context: vscode.ExtensionContext) {
+    let disposableHelp = vscode.commands.registerCommand('bevyrly.showSyntaxHelp', async () => {
+        const doc = await vscode.workspace.openTextDocument({ content: querySyntaxMarkdown(), language: 'markdown' });
+        await vscode.commands.executeCommand('markdown.showPreview', doc.uri);
+    });
+    context.subscriptions.push(disposableHelp);
+}
+
+export function registerQuerySymbols(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    context.subscriptions.push(vscode.workspace.onDidChangeConfiguration(event => {
+        if (event.affectsConfiguration('bevyrly.querySymbols')) {
+            symbolTable = undefined;
+            // Cached results were parsed under the old mapping.
+            bevyrlyIndex.invalidateResults();
+        }
+    }));
+}
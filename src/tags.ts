@@ -0,0 +1,75 @@
+import { BevyrlyIndex } from '.';
+import { globToRegExp, matchesAny } from './config';
+import { blankComments } from './registrations';
+
+// `#[bevyrly(tag = "combat")]` (repeatable, or `tags = ["combat", "ui"]`) above a function tags it. The attribute
+// needs a no-op `bevyrly` attribute macro, or `#[cfg_attr(any(), bevyrly(tag = "combat"))]` to stay invisible to rustc.
+export function parseTagAttributes(text: string): Map<string, string[]> {
+    let tags: Map<string, string[]> = new Map();
+    let pending: string[] = [];
+    for (const line of blankComments(text).split(/\r?\n/)) {
+        const trimmed = line.trim();
+        if (trimmed.startsWith("#[")) {
+            const attribute = trimmed.match(/\bbevyrly\s*\(([^)]*)\)/)?.[1] ?? "";
+            for (const found of attribute.matchAll(/\btags?\s*=\s*(?:"([^"]+)"|\[([^\]]*)\])/g)) {
+                pending.push(...(found[1] !== undefined ? [found[1]] : Array.from(found[2].matchAll(/"([^"]+)"/g), tag => tag[1])));
+            }
+            continue;
+        } else if (trimmed.length == 0 && pending.length > 0) {
+            continue;
+        }
+
+        const found = trimmed.match(/^(?:pub(?:\([^)]*\))?\s+)?(?:async\s+)?fn\s+(\w+)/);
+        if (found && pending.length > 0) {
+            tags.set(found[1], pending);
+        }
+        pending = [];
+    }
+
+    return tags;
+}
+
+// Tag → tagged systems, from attributes in the sources and `[[tags]]` rules in each folder's bevyrly.toml. What `tag:`
+// query parts match against.
+export function systemTags(bevyrlyIndex: BevyrlyIndex): Map<string, Set<string>> {
+    return bevyrlyIndex.derived("tags", () => {
+        let tags: Map<string, Set<string>> = new Map();
+        const tag = (name: string, system: string) => {
+            if (!tags.has(name)) {
+                tags.set(name, new Set());
+            }
+            tags.get(name)?.add(system);
+        };
+
+        for (const text of bevyrlyIndex.sources.values()) {
+            for (const [system, names] of parseTagAttributes(text)) {
+                if (bevyrlyIndex.systems.has(system)) {
+                    names.forEach(name => tag(name, system));
+                }
+            }
+        }
+
+        for (const [folder, config] of bevyrlyIndex.configs) {
+            for (const rule of config.tags) {
+                const names = rule.systems.map(glob => globToRegExp(glob));
+                for (const [system, loc] of bevyrlyIndex.locs) {
+                    const filepath = loc.src.filepath ?? "";
+                    if (!filepath.startsWith(folder + "/")) continue;
+                    const relative = decodeURIComponent(filepath.slice(folder.length + 1));
+                    if (matchesAny(rule.paths, relative) || names.some(name => name.test(system))) {
+                        tag(rule.tag, system);
+                    }
+                }
+            }
+        }
+
+        return tags;
+    });
+}
+
+export function tagsOf(bevyrlyIndex: BevyrlyIndex, system: string): string[] {
+    return Array.from(systemTags(bevyrlyIndex).entries())
+        .filter(([_, systems]) => systems.has(system))
+        .map(([tag, _]) => tag)
+        .sort();
+}
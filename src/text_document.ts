@@ -1,23 +1,79 @@
 import * as vscode from 'vscode';
-import { BevyrlyIndex } from '.';
-import { expandSystemFromName } from './extension';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { promptQuery } from './completion';
+import { expandLinkFromName, expandSystemFromName, revealSystem } from './extension';
+import { systemById } from './ids';
+import { recordQuery } from './history';
+import { activeSessionName, noteSessionQuery, scopedQuery, sessionNamed } from './sessions';
+import { resolveChanged } from './changed';
+import { explainQuery, explanationLines, splitExplain } from './explain';
+import { featureScopedGet } from './cfg';
+import { crateLabel, groupByModule, moduleLabel, pageSummary, resultColumns, resultLayout, resultPage, resultRow, resultTextTable, splitPage } from './results';
 
-async function showBevyrlyResultsAsTextDocument(result: string) {
-    let doc = await vscode.workspace.openTextDocument(vscode.Uri.parse(encodeURI(result)).with({ scheme: 'bevyrly' }));
+// The document belongs to `session` (the active one by default) for as long as it is open.
+export async function showBevyrlyResultsAsTextDocument(result: string, session: string = activeSessionName()) {
+    let doc = await vscode.workspace.openTextDocument(vscode.Uri.parse(encodeURIComponent(result))
+        .with({ scheme: 'bevyrly', query: "session=" + encodeURIComponent(session) }));
     vscode.languages.setTextDocumentLanguage(doc, "rust");
     await vscode.window.showTextDocument(doc, { preview: false });
 }
 
+export function queryLink(query: string): string {
+    return vscode.env.uriScheme + "://DEVLIKE.bevyrly/query?q=" + encodeURIComponent(query);
+}
+
 let virtualTextProvider: vscode.TextDocumentContentProvider | null = null;
 
 function createVirtualTextProvider(bevyrlyIndex: BevyrlyIndex): vscode.TextDocumentContentProvider {
     return new (class implements vscode.TextDocumentContentProvider {
         onDidChange?: vscode.Event<vscode.Uri> | undefined;
 
-        provideTextDocumentContent(uri: vscode.Uri, token: vscode.CancellationToken): vscode.ProviderResult<string> {
-            let search = uri.path.slice(1);
-            let content = "";
-            for (const system of bevyrlyIndex.get(search)[0]) {
+        async provideTextDocumentContent(uri: vscode.Uri, token: vscode.CancellationToken): Promise<string> {
+            let [search, pageNumber] = splitPage(uri.path.slice(1));
+            let explain: boolean, explained: string | undefined;
+            [search, explain, explained] = splitExplain(search);
+            const session = sessionNamed(new URLSearchParams(uri.query).get("session") ?? undefined);
+            bevyrlyIndex.lastQuery = search;
+            noteSessionQuery(session.name, search);
+            const scoped = scopedQuery(session, search);
+            await resolveChanged(bevyrlyIndex, scoped);
+            // Tables and groups are sorted anyway; sorting before paging keeps every page a contiguous slice of them.
+            const [found, _, unflagged] = featureScopedGet(bevyrlyIndex, scoped);
+            recordQuery(search, found.length);
+            const page = resultPage(resultLayout() == "list" ? found : [...found].sort(), pageNumber);
+            const systems = page.systems;
+            const summary = pageSummary(page, search);
+            let content = summary ? "// " + summary + "\n" : "";
+            if (session.scope.trim().length > 0) {
+                content = "// Session " + session.name + ", scoped to " + session.scope.trim() + "\n" + content;
+            }
+            if (explain) {
+                content += explanationLines(explainQuery(bevyrlyIndex, unflagged, explained), explained).map(line => "// " + line + "\n").join("");
+            }
+            if (session.pinned.length > 0) {
+                content += "// Pinned: " + session.pinned.map(system => system + " (" + (expandLinkFromName(bevyrlyIndex, system)?.[1] ?? "gone") + ")").join(", ") + "\n";
+            }
+            if (resultLayout() == "table") {
+                const rows = systems.map(system => resultRow(bevyrlyIndex, unflagged, system));
+                return content + resultTextTable(rows, resultColumns());
+            } else if (resultLayout() == "grouped") {
+                // `#region` markers make each crate and module foldable in the editor.
+                for (const [crate, modules] of groupByModule(bevyrlyIndex, systems)) {
+                    const count = Array.from(modules.values()).reduce((sum, systems) => sum + systems.length, 0);
+                    content += "// #region " + crateLabel(crate) + " (" + count + ")\n";
+                    for (const [module, systems] of modules) {
+                        content += "// #region " + moduleLabel(module) + " (" + systems.length + ")\n";
+                        for (const system of systems) {
+                            content += expandSystemFromName(bevyrlyIndex, system) + "\n";
+                        }
+                        content += "// #endregion\n";
+                    }
+                    content += "// #endregion\n";
+                }
+                return content;
+            }
+
+            for (const system of systems) {
                 content += expandSystemFromName(bevyrlyIndex, system);
             }
 
@@ -33,14 +89,46 @@ export function registerTextDocument(context: vscode.ExtensionContext, bevyrlyIn
     }
 
     let disposableNewQuery = vscode.commands.registerCommand('bevyrly.newQuery', async () => {
-        const result = await vscode.window.showInputBox({
-            value: '',
-            placeHolder: '&Transform *Vel E'
-        });
+        const result = await promptQuery(bevyrlyIndex, '', '&Transform *Vel E');
 
         if (result) {
             showBevyrlyResultsAsTextDocument(result);
         }
     });
     context.subscriptions.push(disposableNewQuery);
+
+    let disposableCopyLink = vscode.commands.registerCommand('bevyrly.copyQueryLink', async () => {
+        const query = await promptQuery(bevyrlyIndex, bevyrlyIndex.lastQuery, '&Transform *Vel E');
+        if (query) {
+            await vscode.env.clipboard.writeText(queryLink(query));
+            vscode.window.showInformationMessage("Copied a link that runs " + query);
+        }
+    });
+    context.subscriptions.push(disposableCopyLink);
+
+    // Opens `vscode://DEVLIKE.bevyrly/query?q=...` links as query documents, and reveals the system of
+    // `vscode://DEVLIKE.bevyrly/system?id=...` links.
+    context.subscriptions.push(vscode.window.registerUriHandler({
+        async handleUri(uri: vscode.Uri) {
+            const params = new URLSearchParams(uri.query);
+            const query = params.get("q");
+            const id = params.get("id");
+            if (uri.path == "/query" && query) {
+                showBevyrlyResultsAsTextDocument(query);
+            } else if (uri.path == "/system" && id) {
+                await whenIndexed(context, bevyrlyIndex);
+                const system = systemById(bevyrlyIndex, id);
+                if (system) {
+                    await revealSystem(bevyrlyIndex, system);
+                } else {
+                    vscode.window.showWarningMessage("bevyrly found no system with ID " + id);
+                }
+            }
+        }
+    }));
+
+    let disposableRunQuery = vscode.commands.registerCommand('bevyrly.runQuery', async (query: string) => {
+        showBevyrlyResultsAsTextDocument(query);
+    });
+    context.subscriptions.push(disposableRunQuery);
 }
\ No newline at end of file
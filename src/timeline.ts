@@ -0,0 +1,131 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, whenIndexed } from '.';
+import { escapeHtml } from './extension';
+import { RunsBefore, runsBefore, scheduleOrders } from './order';
+
+// Bevy's main schedule order within a frame; the fixed timestep schedules all run inside `RunFixedMainLoop`.
+export const framePhases = [
+    "First", "PreUpdate", "StateTransition", "FixedFirst", "FixedPreUpdate", "FixedUpdate", "FixedPostUpdate",
+    "FixedLast", "Update", "SpawnScene", "PostUpdate", "Last",
+];
+
+export function phaseOf(schedule: string): string | undefined {
+    if (/^(OnEnter|OnExit|OnTransition)\b/.test(schedule)) {
+        return "StateTransition";
+    }
+
+    return framePhases.includes(schedule) ? schedule : undefined;
+}
+
+export interface TimelineEntry {
+    system: string;
+    phase: string;
+    schedule: string;
+    // Where the system runs in the simulated order of its schedule, from 0; past the end when it's cfg'd out of it.
+    position: number;
+    role: "writer" | "reader";
+    // Set for readers that run before every writer of the event in the frame, explaining why.
+    early?: string;
+}
+
+export function eventTimeline(bevyrlyIndex: BevyrlyIndex, event: string): TimelineEntry[] {
+    let entries: TimelineEntry[] = [];
+    const orders = scheduleOrders(bevyrlyIndex);
+    for (const [role, storage] of [["writer", bevyrlyIndex.event_write], ["reader", bevyrlyIndex.event_read]] as const) {
+        for (const system of storage.get(event) ?? []) {
+            for (const reg of bevyrlyIndex.registrations.get(system) ?? []) {
+                const phase = phaseOf(reg.schedule);
+                const order = orders.get(reg.schedule)?.order ?? [];
+                if (phase !== undefined) {
+                    const position = order.includes(system) ? order.indexOf(system) : order.length;
+                    entries.push({ system, phase, schedule: reg.schedule, position, role });
+                }
+            }
+        }
+    }
+
+    const writers = entries.filter(entry => entry.role == "writer");
+    if (writers.length == 0) {
+        return entries;
+    }
+
+    const first = Math.min(...writers.map(entry => framePhases.indexOf(entry.phase)));
+    for (const entry of entries.filter(entry => entry.role == "reader")) {
+        const phase = framePhases.indexOf(entry.phase);
+        if (phase < first) {
+            entry.early = "runs in " + entry.phase + ", before the first writer in " + framePhases[first];
+        } else if (phase == first) {
+            // Sets and `configure_sets` count too, as in the simulated order.
+            const local = writers.filter(writer => writer.phase == entry.phase);
+            if (local.every(writer => writer.schedule == entry.schedule && orders.get(entry.schedule)?.before.get(entry.system)?.has(writer.system))) {
+                entry.early = "ordered before " + local.map(writer => writer.system).join(", ") + " in " + entry.phase;
+            }
+        }
+    }
+
+    return entries;
+}
+
+// Whether each writer runs before each reader, by the simulated schedule order.
+export function writerReaderOrder(bevyrlyIndex: BevyrlyIndex, entries: TimelineEntry[]): [string, string, RunsBefore][] {
+    const systems = (role: TimelineEntry["role"]) => Array.from(new Set(entries.filter(entry => entry.role == role).map(entry => entry.system))).sort();
+    return systems("writer").flatMap(writer => systems("reader").filter(reader => reader != writer)
+        .map((reader): [string, string, RunsBefore] => [writer, reader, runsBefore(bevyrlyIndex, writer, reader)]));
+}
+
+export function timelineView(event: string, entries: TimelineEntry[], order: [string, string, RunsBefore][] = []): string {
+    const phases = framePhases.filter(phase => entries.some(entry => entry.phase == phase));
+    // Within a phase, systems are listed in their schedule's simulated order.
+    const cell = (entry: TimelineEntry) => "<div class='" + entry.role + (entry.early ? " early" : "") + "'" +
+        (entry.early ? " title='Reads last frame&#39;s events: " + escapeHtml(entry.early) + "'" : "") + ">" +
+        (entry.role == "writer" ? "✎ " : "👁 ") + escapeHtml(entry.system) +
+        (entry.schedule != entry.phase ? " <small>" + escapeHtml(entry.schedule) + "</small>" : "") + "</div>";
+    const sorted = (phase: string) => entries.filter(entry => entry.phase == phase)
+        .sort((a, b) => a.schedule.localeCompare(b.schedule) || a.position - b.position);
+
+    let html = ["<h1>Bevyrly timeline: <code>" + escapeHtml(event) + "</code></h1>"];
+    if (phases.length == 0) {
+        html.push("<p>No registered writers or readers run in a per-frame schedule.</p>");
+    } else {
+        html.push("<table><tr>" + phases.map(phase => "<th>" + phase + "</th>").join("") + "</tr><tr>" +
+            phases.map(phase => "<td>" + sorted(phase).map(cell).join("") + "</td>").join("") +
+            "</tr></table>");
+        const early = entries.filter(entry => entry.early);
+        if (early.length > 0) {
+            html.push("<p>" + early.length + " reader(s) run before any writer in the frame and see the events one frame late.</p>");
+        }
+        if (order.length > 0) {
+            html.push("<h2>Does the writer run before the reader?</h2><table><tr><th>Writer</th><th>Reader</th><th>Before?</th><th>Why</th></tr>" +
+                order.map(([writer, reader, answer]) => "<tr><td>" + escapeHtml(writer) + "</td><td>" + escapeHtml(reader) + "</td><td class='" +
+                    answer.verdict + "'>" + answer.verdict + "</td><td>" + escapeHtml(answer.reason) + "</td></tr>").join("") + "</table>");
+        }
+    }
+
+    return "<!DOCTYPE html><html><head><style>" +
+        "table { border-collapse: collapse; } td, th { border: 1px solid #555; padding: 4px 8px; vertical-align: top; } " +
+        "div { margin: 2px 0; padding: 2px 6px; border-radius: 3px; font-family: monospace; white-space: nowrap; } " +
+        ".writer { background: rgba(230, 160, 60, 0.5); } .reader { background: rgba(90, 160, 240, 0.5); } " +
+        ".early { outline: 2px solid rgba(220, 50, 50, 0.9); } .never { color: #e05050; } .ambiguous { color: #d0a030; }" +
+        "</style></head><body>" + html.join("\n") + "</body></html>";
+}
+
+export function registerTimeline(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let disposableTimeline = vscode.commands.registerCommand('bevyrly.showEventTimeline', async (event?: string) => {
+        await whenIndexed(context, bevyrlyIndex);
+
+        if (!event) {
+            const events = Array.from(new Set([...bevyrlyIndex.event_write.keys(), ...bevyrlyIndex.event_read.keys()])).sort();
+            event = await vscode.window.showQuickPick(events, { title: 'Event' });
+        }
+
+        if (!event) {
+            return;
+        }
+
+        const panel = vscode.window.createWebviewPanel('bevyrlyTimeline', 'Bevyrly Timeline: ' + event, vscode.ViewColumn.One, {});
+        const entries = eventTimeline(bevyrlyIndex, event);
+        panel.webview.html = timelineView(event, entries, writerReaderOrder(bevyrlyIndex, entries));
+    });
+
+    context.subscriptions.push(disposableTimeline);
+}
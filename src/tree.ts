@@ -0,0 +1,120 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex } from '.';
+import { expandLinkFromName, revealSystem } from './extension';
+
+interface SystemTreeNode {
+    label: string;
+    schedule?: string;
+    set?: string;
+    system?: string;
+}
+
+const unregistered = "Unregistered";
+const noSet = "(no set)";
+
+class SystemTreeProvider implements vscode.TreeDataProvider<SystemTreeNode> {
+    private _bevyrlyIndex: BevyrlyIndex;
+    private _onDidChangeTreeData = new vscode.EventEmitter<void>();
+    readonly onDidChangeTreeData = this._onDidChangeTreeData.event;
+    filter: string = "";
+
+    constructor(bevyrlyIndex: BevyrlyIndex) {
+        this._bevyrlyIndex = bevyrlyIndex;
+        bevyrlyIndex.onDidIndex(() => this.refresh());
+    }
+
+    refresh() {
+        this._onDidChangeTreeData.fire();
+    }
+
+    // [schedule, set, system] triples for every registration, plus unregistered systems.
+    private entries(): [string, string, string][] {
+        let entries: [string, string, string][] = [];
+        for (const [system, registrations] of this._bevyrlyIndex.registrations) {
+            for (const reg of registrations) {
+                for (const set of reg.sets.length > 0 ? reg.sets : [noSet]) {
+                    entries.push([reg.schedule, set, system]);
+                }
+            }
+        }
+
+        for (const system of this._bevyrlyIndex.systems.keys()) {
+            if (!this._bevyrlyIndex.registrations.has(system)) {
+                entries.push([unregistered, noSet, system]);
+            }
+        }
+
+        return entries.filter(([_schedule, _set, system]) => system.includes(this.filter));
+    }
+
+    getTreeItem(node: SystemTreeNode): vscode.TreeItem {
+        if (node.system) {
+            let item = new vscode.TreeItem(node.system, vscode.TreeItemCollapsibleState.None);
+            item.description = expandLinkFromName(this._bevyrlyIndex, node.system)?.[1];
+            item.command = { title: "Go to system", command: 'bevyrly.revealSystem', arguments: [node.system] };
+            return item;
+        }
+
+        const count = new Set(this.entries()
+            .filter(([schedule, set, _]) => schedule == node.schedule && (node.set === undefined || set == node.set))
+            .map(([_schedule, _set, system]) => system)).size;
+        let item = new vscode.TreeItem(node.label, vscode.TreeItemCollapsibleState.Collapsed);
+        item.description = String(count);
+        return item;
+    }
+
+    getChildren(node?: SystemTreeNode): SystemTreeNode[] {
+        const entries = this.entries();
+        const unique = (items: string[]) => Array.from(new Set(items)).sort();
+
+        if (!node) {
+            return unique(entries.map(([schedule, _set, _system]) => schedule)).map(schedule => ({ label: schedule, schedule }));
+        } else if (node.set === undefined && node.system === undefined) {
+            const local = entries.filter(([schedule, _set, _system]) => schedule == node.schedule);
+            const sets = unique(local.map(([_schedule, set, _system]) => set)).filter(set => set != noSet);
+            return [
+                ...sets.map(set => ({ label: set, schedule: node.schedule, set })),
+                ...unique(local.filter(([_schedule, set, _system]) => set == noSet).map(([_schedule, _set, system]) => system))
+                    .map(system => ({ label: system, system })),
+            ];
+        } else if (node.system === undefined) {
+            return unique(entries
+                .filter(([schedule, set, _system]) => schedule == node.schedule && set == node.set)
+                .map(([_schedule, _set, system]) => system))
+                .map(system => ({ label: system, system }));
+        }
+
+        return [];
+    }
+}
+
+export function registerSystemTree(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    const provider = new SystemTreeProvider(bevyrlyIndex);
+    const view = vscode.window.createTreeView('bevyrlySystems', { treeDataProvider: provider });
+    context.subscriptions.push(view);
+
+    const setFilter = (filter: string) => {
+        provider.filter = filter;
+        view.message = filter.length > 0 ? "Filtered by \"" + filter + "\"" : undefined;
+        provider.refresh();
+    };
+
+    let disposableFilter = vscode.commands.registerCommand('bevyrly.filterSystems', async () => {
+        const filter = await vscode.window.showInputBox({
+            value: provider.filter,
+            placeHolder: 'Filter systems by name'
+        });
+
+        if (filter !== undefined) {
+            setFilter(filter);
+        }
+    });
+
+    let disposableClear = vscode.commands.registerCommand('bevyrly.clearSystemsFilter', () => setFilter(""));
+
+    let disposableReveal = vscode.commands.registerCommand('bevyrly.revealSystem', async (system: string) => {
+        await revealSystem(bevyrlyIndex, system);
+    });
+
+    context.subscriptions.push(disposableFilter, disposableClear, disposableReveal);
+}
@@ -0,0 +1,68 @@
+import * as vscode from 'vscode';
+import { BevyrlyIndex, isIndexedSource, startBevyrlyIndexing } from '.';
+
+// Reindexes after edits to indexed sources once they have been quiet for `bevyrly.reindexDelay` ms, and at least every
+// four delays while edits keep coming. A burst of changes coalesces into one run, which reads every open editor's
+// current text at once; changes made while a run is in progress queue one more run after it. With the index cache on,
+// only the edited files are parsed again.
+export function registerReindexOnChange(context: vscode.ExtensionContext, bevyrlyIndex: BevyrlyIndex) {
+    let timer: NodeJS.Timeout | undefined = undefined;
+    // When the first change not yet reindexed came in.
+    let pendingSince: number | undefined = undefined;
+    let running = false;
+    let queued = false;
+
+    const reindex = async () => {
+        timer = undefined;
+        pendingSince = undefined;
+        if (running) {
+            queued = true;
+            return;
+        }
+
+        running = true;
+        try {
+            do {
+                queued = false;
+                // A run started elsewhere (a command, a bevyrly.toml change) finishes rather than being superseded.
+                await bevyrlyIndex.indexing?.done;
+                await startBevyrlyIndexing(context, bevyrlyIndex);
+            } while (queued);
+        } finally {
+            running = false;
+        }
+    };
+
+    // Whether each file is indexed, until the next run, which may change the config deciding it.
+    let indexed: Map<string, Promise<boolean>> = new Map();
+    context.subscriptions.push(bevyrlyIndex.onDidIndex(() => indexed.clear()));
+
+    const schedule = async (uri: vscode.Uri) => {
+        const config = vscode.workspace.getConfiguration('bevyrly');
+        if (!bevyrlyIndex.hasStarted || !(config.get<boolean>('reindexOnChange') ?? true)) {
+            return;
+        }
+        if (!indexed.has(uri.toString())) {
+            indexed.set(uri.toString(), isIndexedSource(bevyrlyIndex, uri));
+        }
+        if (!await indexed.get(uri.toString())) {
+            return;
+        }
+
+        const delay = config.get<number>('reindexDelay') ?? 750;
+        const now = Date.now();
+        const since = pendingSince ?? now;
+        pendingSince = since;
+        clearTimeout(timer);
+        timer = setTimeout(reindex, Math.max(0, Math.min(delay, since + delay * 4 - now)));
+    };
+
+    context.subscriptions.push(vscode.workspace.onDidChangeTextDocument(event => {
+        if (event.contentChanges.length > 0) {
+            schedule(event.document.uri);
+        }
+    }));
+    context.subscriptions.push(vscode.workspace.onDidCreateFiles(event => event.files.forEach(schedule)));
+    context.subscriptions.push(vscode.workspace.onDidDeleteFiles(event => event.files.forEach(schedule)));
+    context.subscriptions.push({ dispose: () => clearTimeout(timer) });
+}